@@ -9,8 +9,11 @@
 extern crate alloc;
 
 use libax::{
-    axerrno::AxResult,
-    io::{File, Read, Write},
+    axerrno::{AxError, AxResult},
+    io::{
+        event::{Event, EventFlags, EventQueue},
+        File, Read, Write,
+    },
 };
 
 const LOCAL_IP: &str = "10.0.2.15";
@@ -60,10 +63,12 @@ fn accept_loop() -> AxResult {
     println!("{}", path);
     let mut listener = File::create(path).unwrap();
     println!("listen on: {}", path);
+    let mut events = EventQueue::new()?;
+    events.subscribe(&listener, EventFlags::EVENT_READ, 0)?;
 
     let mut i = 0;
     loop {
-        match loop_wait!(listener.dup("accept")) {
+        match listener.dup("accept") {
             Ok(stream) => {
                 info!("new client {}: ", i);
                 libax::task::spawn(move || match http_server(stream) {
@@ -71,6 +76,11 @@ fn accept_loop() -> AxResult {
                     Ok(()) => info!("client {} closed successfully", i),
                 });
             }
+            Err(AxError::WouldBlock) => {
+                // sleep until a client connects
+                events.wait(&mut [Event::default()])?;
+                continue;
+            }
             Err(e) => return Err(e),
         }
         i += 1;
@@ -1,12 +1,11 @@
 extern crate alloc;
 
 use alloc::string::String;
-use libax::axerrno::from_ret_code;
 use libax::io as fs;
 use libax::io::File;
 use libax::io::{self, prelude::*};
-use libax::process::{exec, fork, wait};
-use libax::task::exit;
+
+use super::pipeline;
 
 macro_rules! print_err {
     ($cmd: literal, $msg: literal) => {
@@ -25,7 +24,7 @@ macro_rules! print_err {
     };
 }
 
-type CmdHandler = fn(&str);
+pub(super) type CmdHandler = fn(&str);
 
 const CMD_TABLE: &[(&str, CmdHandler)] = &[
     ("cat", do_cat),
@@ -33,10 +32,14 @@ const CMD_TABLE: &[(&str, CmdHandler)] = &[
     ("echo", do_echo),
     ("exit", do_exit),
     ("help", do_help),
+    ("kill", do_kill),
     ("ls", do_ls),
     ("mkdir", do_mkdir),
+    ("mv", do_mv),
     ("pwd", do_pwd),
+    ("realpath", do_realpath),
     ("rm", do_rm),
+    ("schemes", do_schemes),
     ("uname", do_uname),
 ];
 
@@ -100,10 +103,11 @@ fn do_cat(args: &str) {
     fn cat_one(fname: &str) -> io::Result<()> {
         let mut buf = [0; 1024];
         let mut file = File::open(fname)?;
+        let mut stdout = io::dup_stdout()?;
         loop {
             let n = file.read(&mut buf)?;
             if n > 0 {
-                io::stdout().write(&buf[..n])?;
+                stdout.write(&buf[..n])?;
             } else {
                 return Ok(());
             }
@@ -118,34 +122,7 @@ fn do_cat(args: &str) {
 }
 
 fn do_echo(args: &str) {
-    fn echo_file(fname: &str, text_list: &[&str]) -> io::Result<()> {
-        let mut file = File::create(fname)?;
-        for text in text_list {
-            file.write_all(text.as_bytes())?;
-        }
-        Ok(())
-    }
-
-    if let Some(pos) = args.rfind('>') {
-        let text_before = args[..pos].trim();
-        let (fname, text_after) = split_whitespace(&args[pos + 1..]);
-        if fname.is_empty() {
-            print_err!("echo", "no file specified");
-            return;
-        };
-
-        let text_list = [
-            text_before,
-            if !text_after.is_empty() { " " } else { "" },
-            text_after,
-            "\n",
-        ];
-        if let Err(e) = echo_file(fname, &text_list) {
-            print_err!("echo", fname, e.as_str());
-        }
-    } else {
-        println!("{}", args)
-    }
+    println!("{}", args)
 }
 
 fn do_mkdir(args: &str) {
@@ -169,6 +146,32 @@ fn do_mkdir(args: &str) {
     }
 }
 
+fn do_mv(args: &str) {
+    let mut paths = args.split_whitespace();
+    match (paths.next(), paths.next(), paths.next()) {
+        (Some(from), Some(to), None) => {
+            if let Err(e) = fs::rename(from, to) {
+                print_err!("mv", format_args!("cannot move '{from}'"), e.as_str());
+            }
+        }
+        (_, _, Some(_)) => print_err!("mv", "too many arguments"),
+        _ => print_err!("mv", "missing operand"),
+    }
+}
+
+fn do_realpath(args: &str) {
+    if args.is_empty() {
+        print_err!("realpath", "missing operand");
+        return;
+    }
+    for path in args.split_whitespace() {
+        match fs::canonicalize(path) {
+            Ok(path) => println!("{}", path),
+            Err(e) => print_err!("realpath", path, e.as_str()),
+        }
+    }
+}
+
 fn do_rm(args: &str) {
     if args.is_empty() {
         print_err!("rm", "missing operand");
@@ -199,6 +202,26 @@ fn do_rm(args: &str) {
     }
 }
 
+fn do_schemes(_args: &str) {
+    fn list_schemes() -> io::Result<()> {
+        let mut buf = [0; 1024];
+        let mut file = File::open(":")?;
+        let mut stdout = io::dup_stdout()?;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n > 0 {
+                stdout.write(&buf[..n])?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    if let Err(e) = list_schemes() {
+        print_err!("schemes", ":", e.as_str());
+    }
+}
+
 fn do_cd(mut args: &str) {
     if args.is_empty() {
         args = "/";
@@ -241,45 +264,69 @@ fn do_help(_args: &str) {
     }
 }
 
+fn do_kill(args: &str) {
+    use libax::signal::*;
+    const SIGNALS: &[(&str, usize)] = &[
+        ("HUP", SIGHUP),
+        ("INT", SIGINT),
+        ("QUIT", SIGQUIT),
+        ("KILL", SIGKILL),
+        ("USR1", SIGUSR1),
+        ("USR2", SIGUSR2),
+        ("PIPE", SIGPIPE),
+        ("ALRM", SIGALRM),
+        ("TERM", SIGTERM),
+        ("CHLD", SIGCHLD),
+        ("CONT", SIGCONT),
+        ("STOP", SIGSTOP),
+    ];
+
+    let (mut sig, mut pids) = (SIGTERM, args);
+    if let Some(rest) = args.strip_prefix('-') {
+        let (name, rest) = split_whitespace(rest);
+        let name = name.strip_prefix("SIG").unwrap_or(name);
+        sig = match SIGNALS.iter().find(|(n, _)| *n == name) {
+            Some((_, sig)) => *sig,
+            None => match name.parse() {
+                Ok(sig) => sig,
+                Err(_) => {
+                    print_err!("kill", name, "invalid signal");
+                    return;
+                }
+            },
+        };
+        pids = rest;
+    }
+    if pids.is_empty() {
+        print_err!("kill", "missing operand");
+        return;
+    }
+    for pid in pids.split_whitespace() {
+        let result = pid
+            .parse()
+            .map_err(|_| io::Error::InvalidInput)
+            .and_then(|pid| kill(pid, sig));
+        if let Err(e) = result {
+            print_err!("kill", pid, e.as_str());
+        }
+    }
+}
+
 fn do_exit(_args: &str) {
     libax::task::exit(0);
 }
 
-fn do_exec(cmd: &str, _args: &str) -> Option<i32> {
-    match fork() {
-        pid if pid > 0 => {
-            let mut ret: i32 = 0;
-            wait(pid as usize, &mut ret);
-            Some(ret)
-        }
-        0 => exit(exec(cmd) as usize),
-        _ => None,
-    }
+/// Finds a built-in command by name
+pub(super) fn find_builtin(name: &str) -> Option<CmdHandler> {
+    CMD_TABLE
+        .iter()
+        .find(|(cmd, _)| *cmd == name)
+        .map(|(_, func)| *func)
 }
 
 pub fn run_cmd(line: &[u8]) {
     let line_str = unsafe { core::str::from_utf8_unchecked(line) };
-    let (cmd, args) = split_whitespace(line_str);
-    if !cmd.is_empty() {
-        for (name, func) in CMD_TABLE {
-            if cmd == *name {
-                func(args);
-                return;
-            }
-        }
-        if let Some(code) = do_exec(cmd, args) {
-            match from_ret_code(code as isize) {
-                Ok(code) => {
-                    println!("{} returned with exit code {}", cmd, code);
-                }
-                Err(e) => {
-                    println!("{}: {:?}", cmd, e);
-                }
-            }
-            return;
-        }
-        println!("{}: command not found", cmd);
-    }
+    pipeline::run_line(line_str);
 }
 
 fn split_whitespace(str: &str) -> (&str, &str) {
@@ -1,4 +1,5 @@
 mod cmd;
+pub mod pipeline;
 
 use libax::io::prelude::*;
 
@@ -7,6 +8,7 @@ const CR: u8 = b'\r';
 const DL: u8 = b'\x7f';
 const BS: u8 = b'\x08';
 const SPACE: u8 = b' ';
+const ETX: u8 = b'\x03';
 
 const MAX_CMD_LEN: usize = 256;
 
@@ -20,6 +22,7 @@ pub fn main() {
 
     let mut buf = [0; MAX_CMD_LEN];
     let mut cursor = 0;
+    pipeline::import_env();
     cmd::run_cmd("help".as_bytes());
     print_prompt();
     stdout.flush().unwrap();
@@ -40,6 +43,12 @@ pub fn main() {
                 }
                 print_prompt();
             }
+            // Ctrl-C discards the line
+            ETX => {
+                println!("^C");
+                cursor = 0;
+                print_prompt();
+            }
             BS | DL => {
                 if cursor > 0 {
                     stdout.write(&[BS, SPACE, BS]).unwrap();
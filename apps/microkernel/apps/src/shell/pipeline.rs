@@ -0,0 +1,368 @@
+//! Pipelines: `cmd1 < in | cmd2 > out &`
+//!
+//! Each command runs in a child process with its stdin and stdout redirected,
+//! except a single built-in command without redirections, which runs in the shell itself.
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use libax::io::{self, File};
+use libax::process::{execve, fork, waitpid, WNOHANG};
+use libax::task::exit;
+use libax::{Mutex, OpenFlags};
+
+use super::cmd::find_builtin;
+
+/// Exit status of a command that cannot be found or executed
+const NOT_FOUND: i32 = 127;
+
+/// Exit status of the last foreground pipeline, for `$?`
+static LAST_STATUS: AtomicI32 = AtomicI32::new(0);
+
+/// Shell variables, set by `NAME=value` and expanded by `$NAME`,
+/// the environment of the programs run by the shell
+static VARS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Background processes not reaped yet
+static JOBS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Directories searched for programs, if `PATH` is not set
+const DEFAULT_PATH: &str = "/bin:/";
+
+/// Words and operators of a command line
+#[derive(Debug, PartialEq, Eq)]
+pub enum Token {
+    Word(String),
+    Pipe,
+    Input,
+    Output,
+    Append,
+    Background,
+}
+
+/// Splits a command line at whitespace and operators
+pub fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '|' => Token::Pipe,
+            '<' => Token::Input,
+            '>' if chars.peek() == Some(&'>') => {
+                chars.next();
+                Token::Append
+            }
+            '>' => Token::Output,
+            '&' => Token::Background,
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    tokens.push(Token::Word(core::mem::take(&mut word)));
+                }
+                continue;
+            }
+            c => {
+                word.push(c);
+                continue;
+            }
+        };
+        if !word.is_empty() {
+            tokens.push(Token::Word(core::mem::take(&mut word)));
+        }
+        tokens.push(token);
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    tokens
+}
+
+/// Expands `$?` and `$NAME` in a word
+pub fn expand(word: &str) -> String {
+    let mut result = String::new();
+    let mut rest = word;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('?') {
+            result.push_str(&LAST_STATUS.load(Ordering::Relaxed).to_string());
+            rest = after;
+            continue;
+        }
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            result.push('$');
+        } else if let Some(value) = VARS.lock().get(&rest[..len]) {
+            result.push_str(value);
+        }
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// A command in a pipeline
+#[derive(Default)]
+pub struct Command {
+    pub words: Vec<String>,
+    pub input: Option<String>,
+    /// file and whether to append to it
+    pub output: Option<(String, bool)>,
+}
+
+/// Commands connected by pipes, run in the background if `background`
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    pub background: bool,
+}
+
+/// Parses a command line, expanding the words
+pub fn parse(line: &str) -> Result<Pipeline, &'static str> {
+    let mut commands = Vec::new();
+    let mut command = Command::default();
+    let mut background = false;
+    let mut tokens = tokenize(line).into_iter();
+    while let Some(token) = tokens.next() {
+        if background {
+            return Err("`&` must be at the end of the line");
+        }
+        match token {
+            Token::Word(word) => command.words.push(expand(&word)),
+            Token::Pipe => {
+                if command.words.is_empty() {
+                    return Err("missing command before `|`");
+                }
+                commands.push(core::mem::take(&mut command));
+            }
+            Token::Background => background = true,
+            redirect => {
+                let Some(Token::Word(file)) = tokens.next() else {
+                    return Err("missing file name for redirection");
+                };
+                let file = expand(&file);
+                match redirect {
+                    Token::Input => command.input = Some(file),
+                    Token::Output => command.output = Some((file, false)),
+                    _ => command.output = Some((file, true)),
+                }
+            }
+        }
+    }
+    if command.words.is_empty() {
+        if !commands.is_empty() || background {
+            return Err("missing command");
+        }
+    } else {
+        commands.push(command);
+    }
+    Ok(Pipeline {
+        commands,
+        background,
+    })
+}
+
+/// Finds a program by `PATH`, names with `/` are used as they are.
+fn resolve(name: &str) -> Option<String> {
+    let is_file = |path: &str| io::metadata(path).map_or(false, |stat| stat.is_file());
+    if name.contains('/') {
+        return is_file(name).then(|| name.to_string());
+    }
+    let path = VARS
+        .lock()
+        .get("PATH")
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_PATH.to_string());
+    path.split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| {
+            let mut path = String::from(dir.trim_end_matches('/'));
+            path.push('/');
+            path.push_str(name);
+            path
+        })
+        .find(|path| is_file(path))
+}
+
+/// Opens the redirections of a command, in a child process.
+fn redirect(command: &Command) -> io::Result<()> {
+    if let Some(path) = &command.input {
+        File::open(path)?.dup2(0, "")?;
+    }
+    if let Some((path, append)) = &command.output {
+        let mut flags = OpenFlags::CREATE | OpenFlags::WRITE;
+        flags |= if *append {
+            OpenFlags::APPEND
+        } else {
+            OpenFlags::TRUNCATE
+        };
+        File::open_with(path, flags)?.dup2(1, "")?;
+    }
+    Ok(())
+}
+
+/// Runs a command in a child process with stdin and stdout set up, never returns.
+fn run_child(command: &Command, stdin: Option<File>, stdout: Option<File>) -> ! {
+    let name = command.words[0].as_str();
+    // The files are dropped (closed) here, so that only fd 0 and 1 refer to the pipes.
+    if let Some(mut file) = stdin {
+        file.dup2(0, "").unwrap();
+    }
+    if let Some(mut file) = stdout {
+        file.dup2(1, "").unwrap();
+    }
+    if let Err(e) = redirect(command) {
+        println!("{}: {}", name, e.as_str());
+        exit(1);
+    }
+    if let Some(func) = find_builtin(name) {
+        func(&command.words[1..].join(" "));
+        exit(0);
+    }
+    match resolve(name) {
+        Some(path) => {
+            let args: Vec<&str> = command.words.iter().map(String::as_str).collect();
+            let envs: Vec<String> = VARS
+                .lock()
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            let envs: Vec<&str> = envs.iter().map(String::as_str).collect();
+            execve(&path, &args, &envs);
+            println!("{}: cannot execute", name);
+        }
+        None => println!("{}: command not found", name),
+    }
+    exit(NOT_FOUND as usize);
+}
+
+/// Waits for all processes in `pids`, returns the exit code of the last one.
+fn wait_all(pids: Vec<usize>) -> i32 {
+    let mut status = 0;
+    for pid in pids {
+        if waitpid(pid as isize, &mut status, 0).is_err() {
+            status = NOT_FOUND;
+        }
+    }
+    status
+}
+
+/// Reports background jobs that have finished, without blocking.
+fn reap_jobs() {
+    JOBS.lock().retain(|pid| {
+        let mut code: i32 = 0;
+        match waitpid(*pid as isize, &mut code, WNOHANG) {
+            Ok(0) => true,
+            Ok(_) => {
+                println!("[{}] Done ({})", pid, code);
+                false
+            }
+            Err(_) => false,
+        }
+    });
+}
+
+/// Runs a pipeline, returns its exit status.
+fn run(pipeline: Pipeline) -> i32 {
+    let Pipeline {
+        commands,
+        background,
+    } = pipeline;
+    if let [command] = commands.as_slice() {
+        if let (Some(func), None, None, false) = (
+            find_builtin(&command.words[0]),
+            &command.input,
+            &command.output,
+            background,
+        ) {
+            // `cd` and `exit` must run in the shell itself.
+            func(&command.words[1..].join(" "));
+            return 0;
+        }
+    }
+
+    let mut pids = Vec::new();
+    let mut stdin: Option<File> = None;
+    for (i, command) in commands.iter().enumerate() {
+        let (next_stdin, stdout) = if i + 1 < commands.len() {
+            match io::pipe() {
+                Ok((reader, writer)) => (Some(reader), Some(writer)),
+                Err(e) => {
+                    println!("pipe: {}", e.as_str());
+                    break;
+                }
+            }
+        } else {
+            (None, None)
+        };
+        match fork() {
+            0 => {
+                drop(next_stdin);
+                run_child(command, stdin, stdout);
+            }
+            pid if pid > 0 => pids.push(pid as usize),
+            _ => {
+                println!("fork failed");
+                break;
+            }
+        }
+        // The child has its own copies, the write end must be closed here to get EOF.
+        stdin = next_stdin;
+    }
+    drop(stdin);
+
+    if background {
+        if let Some(pid) = pids.last() {
+            println!("[{}]", pid);
+        }
+        JOBS.lock().extend(pids);
+        0
+    } else {
+        wait_all(pids)
+    }
+}
+
+/// Sets the shell variables from the environment of the shell
+pub(super) fn import_env() {
+    VARS.lock().extend(libax::env::vars());
+}
+
+/// Sets a variable if `line` is `NAME=value`
+pub fn assign(line: &str) -> bool {
+    let Some((name, value)) = line.trim().split_once('=') else {
+        return false;
+    };
+    if name.is_empty()
+        || value.contains(char::is_whitespace)
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return false;
+    }
+    VARS.lock().insert(name.to_string(), expand(value));
+    true
+}
+
+/// Parses and runs a command line, setting `$?`, then reports finished background jobs
+pub(super) fn run_line(line: &str) {
+    let status = if assign(line) {
+        Some(0)
+    } else {
+        match parse(line) {
+            Ok(pipeline) if pipeline.commands.is_empty() => None,
+            Ok(pipeline) => Some(run(pipeline)),
+            Err(msg) => {
+                println!("syntax error: {}", msg);
+                Some(2)
+            }
+        }
+    };
+    if let Some(status) = status {
+        LAST_STATUS.store(status, Ordering::Relaxed);
+    }
+    reap_jobs();
+}
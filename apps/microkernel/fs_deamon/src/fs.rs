@@ -1,15 +1,16 @@
 use core::sync::atomic::AtomicUsize;
 extern crate alloc;
 
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
 use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
-use axfs::api::{create_dir, read_dir, File as RawFile, OpenOptions};
+use axfs::api::{create_dir, read_dir, File as RawFile, OpenOptions, Permissions};
 use libax::axerrno::{ax_err, AxError, AxResult};
-use libax::io::{File, Read, Seek, SeekFrom, Write};
-use libax::scheme::{Packet, Stat};
+use libax::io::{Read, Seek, SeekFrom, Write};
+use libax::scheme::{BlockServer, Map, MapFlags, Stat};
 use libax::{scheme::Scheme, Mutex, OpenFlags};
-use syscall_number::io::{SEEK_CUR, SEEK_END, SEEK_SET};
+use syscall_number::io::{F_SETFL, SEEK_CUR, SEEK_END, SEEK_SET};
 
 enum FileHandle {
     Directory {
@@ -20,12 +21,26 @@ enum FileHandle {
     File {
         path: String,
         handle: RawFile,
+        /// flags the file is opened with
+        flags: OpenFlags,
     },
 }
 
+/// Memory given out by `fmap`
+struct Mapping {
+    layout: Layout,
+    /// file to write back to on `funmap`, for shared writable mappings
+    write_back: Option<String>,
+    offset: usize,
+    /// bytes read from the file
+    len: usize,
+}
+
 #[derive(Default)]
 struct VfsScheme {
     handles: Mutex<BTreeMap<usize, FileHandle>>,
+    /// Key is the address of the mapped memory
+    maps: Mutex<BTreeMap<usize, Mapping>>,
     next_id: AtomicUsize,
 }
 
@@ -34,27 +49,70 @@ pub(crate) fn init_fs() {
     libax::println!("FS inited");
 }
 
+/// Number of threads handling requests
+const WORKERS: usize = 4;
+
 pub(crate) fn run() {
-    let fs = VfsScheme::default();
-    let mut channel = File::create(":/file").unwrap();
     libax::println!("FS deamon started!");
-    loop {
-        let mut packet: Packet = Packet::default();
-        assert_eq!(
-            channel.read_data(&mut packet).unwrap(),
-            core::mem::size_of::<Packet>()
-        );
-        fs.handle(&mut packet);
-        assert_eq!(
-            channel.write_data(&packet).unwrap(),
-            core::mem::size_of::<Packet>()
-        );
+    BlockServer::new(VfsScheme::default())
+        .run("file", WORKERS)
+        .unwrap();
+}
+
+/// Checks whether user `uid` in group `gid` has the `access` to `path`.
+///
+/// The file system does not record owners, so every file belongs to root (uid 0, gid 0).
+/// Root may access everything, other users are checked against the group or others bits.
+/// `access` is given in the owner bits.
+fn check_access(path: &str, uid: u32, gid: u32, access: Permissions) -> AxResult<()> {
+    if uid == 0 {
+        return Ok(());
+    }
+    let access = if gid == 0 {
+        Permissions::from_bits_truncate(access.bits() >> 3)
+    } else {
+        Permissions::from_bits_truncate(access.bits() >> 6)
+    };
+    if axfs::api::metadata(path)?.permissions().contains(access) {
+        Ok(())
+    } else {
+        ax_err!(PermissionDenied)
+    }
+}
+
+/// The directory containing `path`, whose write permission is needed to create or remove it.
+fn parent(path: &str) -> &str {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", _)) => "/",
+        Some((parent, _)) => parent,
+        None => ".",
     }
 }
 
+/// Checks the permissions needed to open `path` with `flags`.
+fn check_open(path: &str, flags: OpenFlags, uid: u32, gid: u32) -> AxResult<()> {
+    if axfs::api::metadata(path).is_err() {
+        // A missing file is created in its parent, or `open` fails later anyway.
+        return if flags.contains(OpenFlags::CREATE) {
+            check_access(parent(path), uid, gid, Permissions::OWNER_WRITE)
+        } else {
+            Ok(())
+        };
+    }
+    let mut access = Permissions::empty();
+    if flags.contains(OpenFlags::READ) || flags.contains(OpenFlags::DIRECTORY) {
+        access |= Permissions::OWNER_READ;
+    }
+    if flags.intersects(OpenFlags::WRITE | OpenFlags::TRUNCATE | OpenFlags::APPEND) {
+        access |= Permissions::OWNER_WRITE;
+    }
+    check_access(path, uid, gid, access)
+}
+
 impl Scheme for VfsScheme {
-    fn open(&self, path: &str, flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+    fn open(&self, path: &str, flags: usize, uid: u32, gid: u32) -> AxResult<usize> {
         let flags = OpenFlags::from_bits_truncate(flags);
+        check_open(path, flags, uid, gid)?;
         let handle = if flags.contains(OpenFlags::DIRECTORY) {
             if flags.contains(OpenFlags::CREATE) {
                 match create_dir(path) {
@@ -79,6 +137,7 @@ impl Scheme for VfsScheme {
                     .read(flags.contains(OpenFlags::READ))
                     .write(flags.contains(OpenFlags::WRITE))
                     .open(path)?,
+                flags,
             }
         };
         let id = self
@@ -205,14 +264,166 @@ impl Scheme for VfsScheme {
         Ok(core::mem::size_of_val(stat))
     }
 
-    fn rmdir(&self, path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+    fn fmap(&self, id: usize, map: &Map) -> AxResult<usize> {
+        info!("FS fmap: {} [{:#x}, +{:#x})", id, map.offset, map.size);
+        let mut handles = self.handles.lock();
+        let (path, handle) = match handles.get_mut(&id).ok_or(AxError::BadFileDescriptor)? {
+            FileHandle::Directory { .. } => return ax_err!(BadFileDescriptor),
+            FileHandle::File { path, handle, .. } => (path, handle),
+        };
+        if map.size == 0 {
+            return ax_err!(InvalidInput);
+        }
+        // Pages are lent to the client, so the memory must be page-aligned.
+        let layout = Layout::from_size_align(map.size, 4096).map_err(|_| AxError::InvalidInput)?;
+        let ptr = unsafe { alloc_zeroed(layout.pad_to_align()) };
+        if ptr.is_null() {
+            return ax_err!(NoMemory);
+        }
+        let buf = unsafe { core::slice::from_raw_parts_mut(ptr, map.size) };
+
+        let read = (|| -> AxResult<usize> {
+            let pos = handle.seek(SeekFrom::Current(0))?;
+            handle.seek(SeekFrom::Start(map.offset as u64))?;
+            let mut len = 0;
+            while len < buf.len() {
+                match handle.read(&mut buf[len..])? {
+                    0 => break,
+                    n => len += n,
+                }
+            }
+            handle.seek(SeekFrom::Start(pos))?;
+            Ok(len)
+        })();
+        let len = match read {
+            Ok(len) => len,
+            Err(e) => {
+                unsafe { dealloc(ptr, layout.pad_to_align()) };
+                return Err(e);
+            }
+        };
+
+        let write_back = map
+            .flags
+            .contains(MapFlags::MAP_SHARED | MapFlags::PROT_WRITE)
+            .then(|| path.clone());
+        self.maps.lock().insert(
+            ptr as usize,
+            Mapping {
+                layout: layout.pad_to_align(),
+                write_back,
+                offset: map.offset,
+                len,
+            },
+        );
+        Ok(ptr as usize)
+    }
+
+    fn funmap(&self, address: usize, _length: usize) -> AxResult<usize> {
+        info!("FS funmap: {:#x}", address);
+        let mapping = self
+            .maps
+            .lock()
+            .remove(&address)
+            .ok_or(AxError::InvalidInput)?;
+        let ret = if let Some(path) = &mapping.write_back {
+            // Only the part backed by the file is written, a mapping never extends the file.
+            let data = unsafe { core::slice::from_raw_parts(address as *const u8, mapping.len) };
+            OpenOptions::new()
+                .write(true)
+                .open(path)
+                .and_then(|mut file| {
+                    file.seek(SeekFrom::Start(mapping.offset as u64))?;
+                    file.write_all(data)
+                })
+        } else {
+            Ok(())
+        };
+        unsafe { dealloc(address as *mut u8, mapping.layout) };
+        ret.map(|_| 0)
+    }
+
+    /// Reads and writes never block, so only `NONBLOCK` may change,
+    /// `APPEND` is fixed when the file is opened.
+    fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> AxResult<usize> {
+        info!("FS fcntl: {} {}", id, cmd);
+        let handles = self.handles.lock();
+        let handle = handles.get(&id).ok_or(AxError::BadFileDescriptor)?;
+        match cmd {
+            F_SETFL => {
+                let append = OpenFlags::from_bits_truncate(arg) & OpenFlags::APPEND;
+                match handle {
+                    FileHandle::File { flags, .. } if *flags & OpenFlags::APPEND != append => {
+                        ax_err!(Unsupported)
+                    }
+                    _ => Ok(0),
+                }
+            }
+            _ => ax_err!(InvalidInput),
+        }
+    }
+
+    fn fpath(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        info!("FS fpath: {}", id);
+        let handles = self.handles.lock();
+        let path = match handles.get(&id).ok_or(AxError::BadFileDescriptor)? {
+            FileHandle::Directory { path, .. } => path,
+            FileHandle::File { path, .. } => path,
+        };
+        let mut len = 0;
+        for part in ["file:", path.as_str()] {
+            let n = part.len().min(buf.len() - len);
+            buf[len..len + n].copy_from_slice(&part.as_bytes()[..n]);
+            len += n;
+        }
+        Ok(len)
+    }
+
+    /// The file system cannot rename, so the file is copied to `new_path` and then removed.
+    fn frename(&self, id: usize, new_path: &str, uid: u32, gid: u32) -> AxResult<usize> {
+        info!("FS frename: {} -> {}", id, new_path);
+        let mut handles = self.handles.lock();
+        let (path, handle, flags) = match handles.get_mut(&id).ok_or(AxError::BadFileDescriptor)? {
+            FileHandle::Directory { .. } => return ax_err!(Unsupported),
+            FileHandle::File {
+                path,
+                handle,
+                flags,
+            } => (path, handle, *flags),
+        };
+        if path.as_str() == new_path {
+            return Ok(0);
+        }
+        if axfs::api::metadata(new_path).is_ok() {
+            return ax_err!(AlreadyExists);
+        }
+        check_access(parent(path), uid, gid, Permissions::OWNER_WRITE)?;
+        check_access(parent(new_path), uid, gid, Permissions::OWNER_WRITE)?;
+        let pos = handle.seek(SeekFrom::Current(0))?;
+        axfs::api::write(new_path, axfs::api::read(path)?)?;
+        axfs::api::remove_file(path)?;
+        // The file is reopened with the access it was opened with
+        let mut new_handle = OpenOptions::new()
+            .append(flags.contains(OpenFlags::APPEND))
+            .read(flags.contains(OpenFlags::READ))
+            .write(flags.contains(OpenFlags::WRITE))
+            .open(new_path)?;
+        new_handle.seek(SeekFrom::Start(pos))?;
+        *handle = new_handle;
+        *path = new_path.into();
+        Ok(0)
+    }
+
+    fn rmdir(&self, path: &str, uid: u32, gid: u32) -> AxResult<usize> {
         info!("FS rmdir: {}", path);
+        check_access(parent(path), uid, gid, Permissions::OWNER_WRITE)?;
         axfs::api::remove_dir(path)?;
         Ok(0)
     }
 
-    fn unlink(&self, path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+    fn unlink(&self, path: &str, uid: u32, gid: u32) -> AxResult<usize> {
         info!("FS unlink: {}", path);
+        check_access(parent(path), uid, gid, Permissions::OWNER_WRITE)?;
         axfs::api::remove_file(path)?;
         Ok(0)
     }
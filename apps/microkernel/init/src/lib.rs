@@ -4,9 +4,10 @@ use libax::{
     task::exit,
 };
 
-pub fn fake_exec(f: fn()) {
+/// Runs `f` in a child process, returns its pid
+pub fn fake_exec(f: fn()) -> usize {
     match fork() {
-        pid if pid > 0 => {}
+        pid if pid > 0 => pid as usize,
         0 => {
             f();
             exit(0);
@@ -17,9 +18,10 @@ pub fn fake_exec(f: fn()) {
     }
 }
 
-pub fn real_exec(cmd: &str) {
+/// Runs program `cmd` in a child process, returns its pid
+pub fn real_exec(cmd: &str) -> usize {
     match fork() {
-        pid if pid > 0 => {}
+        pid if pid > 0 => pid as usize,
         0 => exit(exec(cmd) as usize),
         _ => {
             panic!("Error fork()");
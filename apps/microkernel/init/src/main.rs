@@ -1,7 +1,8 @@
 #![no_std]
 #![no_main]
 
-use libax::process::wait;
+use libax::process::waitpid;
+use libax::signal::exit_signal;
 use microkernel_init::{fake_exec, real_exec};
 
 #[macro_use]
@@ -13,14 +14,25 @@ fn main() {
 
     println!("Start TCP deamon");
 
-    fake_exec(fs_deamon::init);
+    let mut fs_pid = fake_exec(fs_deamon::init);
     #[cfg(feature = "net_deamon")]
     real_exec("./net_deamon");
     real_exec("./shell");
 
     loop {
         let mut ret: i32 = 0;
-        let pid = wait(0, &mut ret);
-        println!("init: process {} exited with code {}", pid, ret);
+        let Ok(pid) = waitpid(-1, &mut ret, 0) else {
+            println!("init: no process left");
+            break;
+        };
+        match exit_signal(ret) {
+            Some(sig) => println!("init: process {} killed by signal {}", pid, sig),
+            None => println!("init: process {} exited with code {}", pid, ret),
+        }
+        if pid == fs_pid {
+            // Its scheme is gone with it, so it can simply be started again.
+            println!("init: restart fs_deamon");
+            fs_pid = fake_exec(fs_deamon::init);
+        }
     }
 }
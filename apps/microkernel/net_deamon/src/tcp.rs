@@ -1,30 +1,88 @@
 use core::{
     str::FromStr,
     sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
 };
 
-use alloc::collections::BTreeMap;
+use alloc::{collections::BTreeMap, vec::Vec};
 use axnet::{IpAddr, SocketAddr, TcpSocket};
 use libax::{
     axerrno::{ax_err, AxError, AxResult},
-    io::File,
-    scheme::{Packet, Scheme},
+    io::{
+        event::{Event, EventQueue},
+        File,
+    },
+    scheme::{EventFlags, Packet, Scheme},
     Mutex, OpenFlags,
 };
+use syscall_number::SYS_FEVENT;
 
 extern crate alloc;
 
+/// Sockets are polled at least this often, as the device does not post its readiness.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 struct TcpScheme {
     handles: Mutex<BTreeMap<usize, TcpSocket>>,
+    /// watched events of a handle, and the ready ones that have been posted
+    events: Mutex<BTreeMap<usize, (EventFlags, EventFlags)>>,
     next_id: AtomicUsize,
 }
 impl TcpScheme {
     fn new() -> TcpScheme {
         TcpScheme {
             handles: Mutex::new(BTreeMap::new()),
+            events: Mutex::new(BTreeMap::new()),
             next_id: 1.into(),
         }
     }
+
+    /// Lets the readiness of `id` be posted again, after the client has operated on it.
+    fn rearm(&self, id: usize) {
+        if let Some((_, posted)) = self.events.lock().get_mut(&id) {
+            *posted = EventFlags::empty();
+        }
+    }
+
+    /// `SYS_FEVENT` packets of handles that became ready since the last call.
+    ///
+    /// Events are edge-triggered: a handle is posted again only after it has been not ready,
+    /// or the client has operated on it.
+    fn poll_events(&self) -> Vec<Packet> {
+        let handles = self.handles.lock();
+        let mut packets = Vec::new();
+        self.events.lock().retain(|id, (flags, posted)| {
+            let Some(socket) = handles.get(id) else {
+                return false;
+            };
+            let ready = readiness(socket) & *flags;
+            let new = ready & !*posted;
+            *posted = ready;
+            if !new.is_empty() {
+                packets.push(Packet {
+                    a: SYS_FEVENT,
+                    b: *id,
+                    c: new.bits(),
+                    ..Default::default()
+                });
+            }
+            true
+        });
+        packets
+    }
+}
+
+fn readiness(socket: &TcpSocket) -> EventFlags {
+    let mut flags = EventFlags::empty();
+    if let Ok(state) = socket.poll() {
+        if state.readable {
+            flags |= EventFlags::EVENT_READ;
+        }
+        if state.writable {
+            flags |= EventFlags::EVENT_WRITE;
+        }
+    }
+    flags
 }
 impl Scheme for TcpScheme {
     fn open(&self, path: &str, flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
@@ -57,6 +115,7 @@ impl Scheme for TcpScheme {
 
     fn close(&self, id: usize) -> AxResult<usize> {
         info!("CLOSE {}", id);
+        self.events.lock().remove(&id);
         self.handles
             .lock()
             .remove(&id)
@@ -67,6 +126,7 @@ impl Scheme for TcpScheme {
 
     fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
         info!("READ {}", id);
+        self.rearm(id);
         self.handles
             .lock()
             .get(&id)
@@ -76,17 +136,34 @@ impl Scheme for TcpScheme {
 
     fn write(&self, id: usize, buf: &[u8]) -> AxResult<usize> {
         info!("WRITE {}", id);
+        self.rearm(id);
         self.handles
             .lock()
             .get(&id)
             .ok_or(AxError::BadFileDescriptor)?
             .send(buf)
     }
+    fn fevent(&self, id: usize, flags: EventFlags) -> AxResult<EventFlags> {
+        info!("FEVENT {} {:?}", id, flags);
+        let ready = readiness(
+            self.handles
+                .lock()
+                .get(&id)
+                .ok_or(AxError::BadFileDescriptor)?,
+        ) & flags;
+        if flags.is_empty() {
+            self.events.lock().remove(&id);
+        } else {
+            self.events.lock().insert(id, (flags, ready));
+        }
+        Ok(ready)
+    }
     fn dup(&self, old_id: usize, buf: &[u8]) -> AxResult<usize> {
         if buf != b"accept" {
             return ax_err!(InvalidInput);
         }
         info!("DUP {}", old_id);
+        self.rearm(old_id);
         let mut handles = self.handles.lock();
         let handle = handles.get_mut(&old_id).ok_or(AxError::BadFileDescriptor)?;
 
@@ -99,18 +176,39 @@ impl Scheme for TcpScheme {
 }
 pub fn start_tcp() {
     let tcp = TcpScheme::new();
-    let mut channel = File::create(":/tcp").unwrap();
+    let mut channel = File::open_with(
+        ":/tcp",
+        OpenFlags::CREATE | OpenFlags::READ | OpenFlags::WRITE | OpenFlags::NONBLOCK,
+    )
+    .unwrap();
+    // Requests are taken until none is left, then the deamon sleeps until another one arrives
+    // or the sockets are due to be polled.
+    let mut events = EventQueue::with_timeout(POLL_INTERVAL).unwrap();
+    events
+        .subscribe(&channel, EventFlags::EVENT_READ, 0)
+        .unwrap();
     libax::println!("TCP deamon started!");
+    let mut ready = [Event::default(); 4];
     loop {
-        let mut packet: Packet = Packet::default();
-        assert_eq!(
-            channel.read_data(&mut packet).unwrap(),
-            core::mem::size_of::<Packet>()
-        );
-        tcp.handle(&mut packet);
-        assert_eq!(
-            channel.write_data(&packet).unwrap(),
-            core::mem::size_of::<Packet>()
-        );
+        loop {
+            let mut packet: Packet = Packet::default();
+            match channel.read_data(&mut packet) {
+                Ok(len) => {
+                    assert_eq!(len, core::mem::size_of::<Packet>());
+                    tcp.handle(&mut packet);
+                    assert_eq!(
+                        channel.write_data(&packet).unwrap(),
+                        core::mem::size_of::<Packet>()
+                    );
+                }
+                Err(AxError::WouldBlock) => break,
+                Err(e) => panic!("TCP deamon: {:?}", e),
+            }
+        }
+        // Sockets are polled here, so that clients can block on the `event:` scheme.
+        for packet in tcp.poll_events() {
+            channel.write_data(&packet).unwrap();
+        }
+        events.wait(&mut ready).unwrap();
     }
 }
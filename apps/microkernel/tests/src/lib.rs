@@ -5,5 +5,30 @@ extern crate libax;
 extern crate alloc;
 
 pub mod scheme;
+pub mod test_args;
+pub mod test_block;
+pub mod test_cow;
+pub mod test_cred;
+pub mod test_event;
+pub mod test_exec;
+pub mod test_exit;
+pub mod test_fcntl;
+pub mod test_fault;
+pub mod test_fmap;
+pub mod test_forward;
+pub mod test_lazy;
+pub mod test_lend;
 pub mod test_mem;
+pub mod test_mmap;
+pub mod test_ns;
+pub mod test_pie;
+pub mod test_pipe;
+pub mod test_request;
+pub mod test_scheme_close;
+pub mod test_shell;
+pub mod test_signal;
 pub mod test_sleep;
+pub mod test_stack;
+pub mod test_tls;
+pub mod test_wait;
+pub mod test_wake;
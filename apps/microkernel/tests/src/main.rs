@@ -8,8 +8,33 @@ extern crate libax;
 extern crate alloc;
 
 mod scheme;
+mod test_args;
+mod test_block;
+mod test_cow;
+mod test_cred;
+mod test_event;
+mod test_exec;
+mod test_exit;
+mod test_fcntl;
+mod test_fault;
+mod test_fmap;
+mod test_forward;
+mod test_lazy;
+mod test_lend;
 mod test_mem;
+mod test_mmap;
+mod test_ns;
+mod test_pie;
+mod test_pipe;
+mod test_request;
+mod test_scheme_close;
+mod test_shell;
+mod test_signal;
 mod test_sleep;
+mod test_stack;
+mod test_tls;
+mod test_wait;
+mod test_wake;
 
 #[no_mangle]
 fn main() {
@@ -0,0 +1,171 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::{cell::Cell, time::Duration};
+
+use libax::{
+    axerrno::AxResult,
+    env,
+    io::File,
+    process::{execve, fork, waitpid},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+
+/// Address the program is loaded at
+const BASE: u64 = 0x1_0000;
+
+/// Encodes its initial stack into the exit code:
+/// `argc | envc << 4 | (page size >> 12) << 8 | argv[1][0] << 12`
+const CODE: [u32; 31] = [
+    0x0001_3283, // ld t0, 0(sp)
+    0x0081_0313, // addi t1, sp, 8
+    0x0032_9393, // slli t2, t0, 3
+    0x0073_0333, // add t1, t1, t2
+    0x0083_0313, // addi t1, t1, 8
+    0x0000_0393, // li t2, 0
+    0x0003_3e03, // 1: ld t3, 0(t1)
+    0x0083_0313, // addi t1, t1, 8
+    0x000e_0663, // beqz t3, 2f
+    0x0013_8393, // addi t2, t2, 1
+    0xff1f_f06f, // j 1b
+    0x0000_0e93, // 2: li t4, 0
+    0x0060_0f93, // li t6, 6
+    0x0003_3e03, // 3: ld t3, 0(t1)
+    0x0083_3f03, // ld t5, 8(t1)
+    0x0103_0313, // addi t1, t1, 16
+    0x000e_0863, // beqz t3, 4f
+    0xfffe_18e3, // bne t3, t6, 3b
+    0x000f_0e93, // mv t4, t5
+    0xfe9f_f06f, // j 3b
+    0x00ce_de93, // 4: srli t4, t4, 12
+    0x008e_9e93, // slli t4, t4, 8
+    0x0043_9393, // slli t2, t2, 4
+    0x0072_8533, // add a0, t0, t2
+    0x01d5_0533, // add a0, a0, t4
+    0x0101_3f03, // ld t5, 16(sp)
+    0x000f_4f03, // lbu t5, 0(t5)
+    0x00cf_1f13, // slli t5, t5, 12
+    0x01e5_0533, // add a0, a0, t5
+    0x00a0_0893, // li a7, 10
+    0x0000_0073, // ecall
+];
+
+/// An ELF file with a single segment of [`CODE`], starting right after the headers
+pub(crate) fn program() -> Vec<u8> {
+    const EHDR_SIZE: usize = 64;
+    const PHDR_SIZE: usize = 56;
+    let code_offset = (EHDR_SIZE + PHDR_SIZE) as u64;
+    let size = code_offset + (CODE.len() * 4) as u64;
+    let mut elf = Vec::new();
+    // 64-bit, little endian, version 1
+    elf.extend_from_slice(b"\x7fELF\x02\x01\x01");
+    elf.resize(16, 0);
+    // e_type (executable), e_machine (RISC-V)
+    elf.extend(2u16.to_le_bytes());
+    elf.extend(0xf3u16.to_le_bytes());
+    elf.extend(1u32.to_le_bytes());
+    // e_entry, e_phoff, e_shoff
+    for word in [BASE + code_offset, EHDR_SIZE as u64, 0] {
+        elf.extend(word.to_le_bytes());
+    }
+    elf.extend(0u32.to_le_bytes());
+    // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx
+    for half in [EHDR_SIZE, PHDR_SIZE, 1, 64, 0, 0] {
+        elf.extend((half as u16).to_le_bytes());
+    }
+    // p_type (load), p_flags (read and execute)
+    elf.extend(1u32.to_le_bytes());
+    elf.extend(5u32.to_le_bytes());
+    // p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_align
+    for word in [0, BASE, BASE, size, size, 0x1000] {
+        elf.extend(word.to_le_bytes());
+    }
+    for word in CODE {
+        elf.extend(word.to_le_bytes());
+    }
+    elf
+}
+
+/// Serves the program, until it is closed
+struct Server {
+    program: Vec<u8>,
+    offset: Cell<usize>,
+    closed: Cell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, _path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        self.offset.set(0);
+        Ok(0)
+    }
+    fn read(&self, _id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let rest = &self.program[self.offset.get()..];
+        let len = buf.len().min(rest.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        self.offset.set(self.offset.get() + len);
+        Ok(len)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        self.closed.set(true);
+        Ok(0)
+    }
+}
+
+fn server() {
+    let server = Server {
+        program: program(),
+        offset: Cell::new(0),
+        closed: Cell::new(false),
+    };
+    let mut channel = File::create(":/prog").unwrap();
+    while !server.closed.get() {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+    }
+}
+
+fn spawn(f: fn()) -> usize {
+    match fork() {
+        0 => {
+            f();
+            exit(0);
+        }
+        pid if pid > 0 => pid as usize,
+        _ => panic!("Error fork()"),
+    }
+}
+
+pub fn main() {
+    println!(
+        "init has {} arguments and {} environment variables",
+        env::args().count(),
+        env::vars().count()
+    );
+
+    let server_pid = spawn(server);
+    sleep(Duration::from_millis(100));
+    let pid = spawn(|| {
+        execve(
+            "prog:/decode",
+            &["decode", "abc", "def"],
+            &["HOME=/", "PATH=/bin"],
+        );
+        panic!("Error execve()");
+    });
+
+    let mut code: i32 = 0;
+    waitpid(pid as isize, &mut code, 0).unwrap();
+    println!(
+        "argc {}, envc {}, page size {}, argv[1] starts with {:?}",
+        code & 0xf,
+        (code >> 4) & 0xf,
+        ((code >> 8) & 0xf) << 12,
+        char::from((code >> 12) as u8)
+    );
+    waitpid(server_pid as isize, &mut code, 0).unwrap();
+    println!("Exec arguments tests run OK!");
+}
@@ -0,0 +1,87 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use libax::{
+    axerrno::AxResult,
+    io::{File, Read, Write},
+    process::{fork, wait},
+    scheme::{BlockServer, SchemeBlock},
+    task::{exit, sleep, spawn, yield_now},
+    Mutex,
+};
+
+/// A single buffer, reads wait until there is data in it.
+#[derive(Default)]
+struct Channel {
+    data: Mutex<Vec<u8>>,
+}
+
+static READ_DONE: AtomicBool = AtomicBool::new(false);
+
+impl SchemeBlock for Channel {
+    fn open(&self, _path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<Option<usize>> {
+        Ok(Some(0))
+    }
+    fn read(&self, _id: usize, buf: &mut [u8]) -> AxResult<Option<usize>> {
+        let mut data = self.data.lock();
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        data.drain(..len);
+        Ok(Some(len))
+    }
+    fn write(&self, _id: usize, buf: &[u8]) -> AxResult<Option<usize>> {
+        self.data.lock().extend_from_slice(buf);
+        Ok(Some(buf.len()))
+    }
+    fn close(&self, _id: usize) -> AxResult<Option<usize>> {
+        Ok(Some(0))
+    }
+}
+
+fn server() {
+    println!("Server started");
+    BlockServer::new(Channel::default())
+        .run("block", 2)
+        .unwrap();
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+
+    spawn(|| {
+        let mut file = File::open("block:/").unwrap();
+        let mut buf = [0u8; 16];
+        let len = file.read(&mut buf).unwrap();
+        println!("Reader got: {}", core::str::from_utf8(&buf[..len]).unwrap());
+        READ_DONE.store(true, Ordering::Release);
+    });
+    // The reader is waiting for the server now, but the server can still answer others.
+    sleep(Duration::from_millis(100));
+    let mut file = File::create("block:/").unwrap();
+    println!("Writer sends: Hello");
+    file.write_all(b"Hello").unwrap();
+    drop(file);
+
+    // The server keeps running, only the reader is waited for.
+    while !READ_DONE.load(Ordering::Acquire) {
+        yield_now();
+    }
+    println!("Block scheme tests run OK!");
+}
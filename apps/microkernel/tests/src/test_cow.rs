@@ -0,0 +1,118 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::vec;
+use core::ptr::addr_of_mut;
+use core::time::Duration;
+
+use libax::{
+    io::{pipe, File, Read, Write},
+    process::{fork, waitpid},
+    task::{exit, sleep},
+};
+
+/// Several pages, so that some are shared and some copied
+const LEN: usize = 4096 * 4;
+
+/// A heap of many pages, far more than fork allocates for the child itself
+const BIG_LEN: usize = 4096 * 1024;
+
+static mut DATA: [u8; LEN] = [1; LEN];
+
+fn data() -> &'static mut [u8; LEN] {
+    unsafe { &mut *addr_of_mut!(DATA) }
+}
+
+/// free frames of the system
+fn free_frames() -> u64 {
+    File::open("memory:").unwrap().statvfs().unwrap().f_bfree
+}
+
+fn wait_child(pid: isize) -> i32 {
+    let mut ret: i32 = 0;
+    assert_eq!(waitpid(pid, &mut ret, 0), Ok(pid as usize));
+    ret
+}
+
+pub fn main() {
+    let mut heap = vec![2u8; LEN];
+    let mut stack = [3u8; 256];
+
+    // The child writes, the parent keeps its values.
+    match fork() {
+        0 => {
+            data()[LEN / 2] = 10;
+            heap[0] = 20;
+            stack[0] = 30;
+            assert_eq!((data()[LEN / 2], heap[0], stack[0]), (10, 20, 30));
+            assert!(data()[..LEN / 2].iter().all(|x| *x == 1));
+            exit(0);
+        }
+        pid if pid > 0 => assert_eq!(wait_child(pid), 0),
+        _ => panic!("Error fork()"),
+    }
+    assert!(data().iter().all(|x| *x == 1));
+    assert!(heap.iter().all(|x| *x == 2));
+    assert!(stack.iter().all(|x| *x == 3));
+    println!("Parent's memory is unchanged after the child writes");
+
+    // The parent writes, the child keeps its values.
+    let pid = match fork() {
+        0 => {
+            sleep(Duration::from_millis(100));
+            exit((data()[0] + heap[LEN - 1] + stack[0]) as usize);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    data()[0] = 100;
+    heap[LEN - 1] = 100;
+    stack[0] = 100;
+    println!("Child sees its own values: {}", wait_child(pid));
+
+    // Written by the kernel, in a syscall of the parent.
+    let (mut reader, mut writer) = pipe().unwrap();
+    let pid = match fork() {
+        0 => {
+            sleep(Duration::from_millis(100));
+            exit(data()[1] as usize);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    writer.write_all(&[42]).unwrap();
+    reader.read_exact(&mut data()[1..2]).unwrap();
+    assert_eq!(data()[1], 42);
+    println!(
+        "Child is not affected by a read of the parent: {}",
+        wait_child(pid)
+    );
+
+    // Pages not written are never copied.
+    let big = vec![6u8; BIG_LEN];
+    let free = free_frames();
+    let pid = match fork() {
+        0 => {
+            sleep(Duration::from_millis(100));
+            exit(big[BIG_LEN - 1] as usize);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let used = free.saturating_sub(free_frames());
+    assert!(
+        used < (BIG_LEN / 4096 / 4) as u64,
+        "fork allocated {} frames",
+        used
+    );
+    println!(
+        "Fork shares the frames of a large heap: {}",
+        wait_child(pid)
+    );
+
+    // Pages no longer shared are writable again.
+    data().fill(5);
+    heap.fill(5);
+    assert!(data().iter().chain(heap.iter()).all(|x| *x == 5));
+    println!("Copy-on-write tests run OK!");
+}
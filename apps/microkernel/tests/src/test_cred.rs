@@ -0,0 +1,99 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::format;
+use core::time::Duration;
+
+use libax::{
+    axerrno::{ax_err, AxError, AxResult},
+    io::{self, File, Read},
+    process::{fork, getegid, geteuid, getuid, setgid, setuid, wait},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+
+/// Only root may remove files, reading a file tells the reader who it is.
+struct Server;
+
+/// Number of requests sent by the clients
+const REQUESTS: usize = 5;
+
+impl Scheme for Server {
+    fn open(&self, _path: &str, _flags: usize, uid: u32, _gid: u32) -> AxResult<usize> {
+        Ok(uid as usize)
+    }
+    fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let data = format!("uid {}", id);
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data.as_bytes()[..len]);
+        Ok(len)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+    fn unlink(&self, path: &str, uid: u32, _gid: u32) -> AxResult<usize> {
+        println!("Server: unlink {} by uid {}", path, uid);
+        if uid != 0 {
+            return ax_err!(PermissionDenied);
+        }
+        Ok(0)
+    }
+}
+
+fn server() {
+    let mut channel = File::create(":/cred").unwrap();
+    for _ in 0..REQUESTS {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        Server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+    }
+}
+
+fn user() {
+    setgid(100).unwrap();
+    setuid(1000).unwrap();
+    assert_eq!((getuid(), geteuid(), getegid()), (1000, 1000, 100));
+    assert_eq!(setuid(0), Err(AxError::PermissionDenied));
+    println!("User cannot become root");
+
+    let mut buf = [0u8; 16];
+    let mut file = File::open("cred:/").unwrap();
+    let len = file.read(&mut buf).unwrap();
+    println!("User is {}", core::str::from_utf8(&buf[..len]).unwrap());
+    drop(file);
+    assert_eq!(
+        io::remove_file("cred:/system"),
+        Err(AxError::PermissionDenied)
+    );
+    println!("User cannot remove files");
+}
+
+fn spawn(f: fn()) {
+    match fork() {
+        0 => {
+            f();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+}
+
+pub fn main() {
+    assert_eq!((getuid(), geteuid()), (0, 0));
+    spawn(server);
+    sleep(Duration::from_millis(100));
+
+    io::remove_file("cred:/system").unwrap();
+    println!("Root removes files");
+
+    let mut ret: i32 = 0;
+    spawn(user);
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    assert_eq!(getuid(), 0);
+    println!("Credential tests run OK!");
+}
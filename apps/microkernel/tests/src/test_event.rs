@@ -0,0 +1,121 @@
+#![allow(unused)]
+extern crate alloc;
+
+use core::{cell::Cell, time::Duration};
+
+use libax::{
+    axerrno::AxResult,
+    io::{
+        self,
+        event::{Event, EventFlags, EventQueue},
+        File, Write,
+    },
+    process::{fork, wait},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+use syscall_number::SYS_FEVENT;
+
+/// `/ready` is readable at once, `/later` once something is written to it
+#[derive(Default)]
+struct Server {
+    /// the id of `/later`, and whether it has become readable
+    later: Cell<Option<usize>>,
+    posted: Cell<bool>,
+    next_id: Cell<usize>,
+    done: Cell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        if path == "/later" {
+            self.later.set(Some(id));
+        }
+        Ok(id)
+    }
+    fn write(&self, _id: usize, buf: &[u8]) -> AxResult<usize> {
+        self.posted.set(true);
+        Ok(buf.len())
+    }
+    fn fevent(&self, id: usize, flags: EventFlags) -> AxResult<EventFlags> {
+        if self.later.get() == Some(id) {
+            println!("Server: watching later");
+            Ok(EventFlags::empty())
+        } else {
+            println!("Server: watching ready");
+            Ok(flags & EventFlags::EVENT_READ)
+        }
+    }
+    fn unlink(&self, _path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+        self.done.set(true);
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+}
+
+fn server() {
+    let server = Server::default();
+    let mut channel = File::create(":/event_test").unwrap();
+    while !server.done.get() {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+        // Readiness is posted with a packet that is not a response.
+        if server.posted.take() {
+            channel
+                .write_data(&Packet {
+                    a: SYS_FEVENT,
+                    b: server.later.get().unwrap(),
+                    c: EventFlags::EVENT_READ.bits(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+    }
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+
+    let mut events = [Event::default(); 4];
+    let mut queue = EventQueue::with_timeout(Duration::from_millis(100)).unwrap();
+    assert_eq!(queue.wait(&mut events), Ok(0));
+    println!("Client: no events before the timeout");
+
+    let ready = File::open("event_test:/ready").unwrap();
+    let mut later = File::open("event_test:/later").unwrap();
+    let mut queue = EventQueue::new().unwrap();
+    queue.subscribe(&ready, EventFlags::EVENT_READ, 1).unwrap();
+    queue.subscribe(&later, EventFlags::EVENT_READ, 2).unwrap();
+    // Only `ready` is reported at once.
+    assert_eq!(queue.wait(&mut events), Ok(1));
+    assert_eq!(events[0].flags, EventFlags::EVENT_READ);
+    println!("Client: got event {}", events[0].data);
+
+    later.write_all(b"x").unwrap();
+    assert_eq!(queue.wait(&mut events), Ok(1));
+    assert_eq!(events[0].flags, EventFlags::EVENT_READ);
+    println!("Client: got event {}", events[0].data);
+
+    drop(queue);
+    drop(ready);
+    drop(later);
+    io::remove_file("event_test:/").unwrap();
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("Event tests run OK!");
+}
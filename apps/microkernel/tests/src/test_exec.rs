@@ -0,0 +1,144 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{cell::RefCell, time::Duration};
+
+use libax::{
+    axerrno::{ax_err, from_ret_code, AxResult},
+    io::{pipe, File, Read},
+    process::{execve, fork, waitpid},
+    scheme::{Packet, Scheme},
+    signal::{exit_signal, kill, SIGKILL},
+    task::{exit, sleep, spawn},
+};
+
+use crate::test_args::program;
+
+/// Files served by [`Server`], the program exits with its arguments encoded, see `test_args`
+const SCRIPTS: [(&str, &[u8]); 4] = [
+    ("/script", b"#! prog:/decode -x \nnot read\n"),
+    ("/nested", b"#!prog:/script\n"),
+    ("/loop", b"#!prog:/loop"),
+    ("/text", b"neither a script nor a program\n"),
+];
+
+/// Serves the program and the scripts, with an offset per open file
+struct Server {
+    files: Vec<(&'static str, Vec<u8>)>,
+    open: RefCell<BTreeMap<usize, (usize, usize)>>,
+}
+
+impl Scheme for Server {
+    fn open(&self, path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        let Some(file) = self.files.iter().position(|(name, _)| *name == path) else {
+            return ax_err!(NotFound);
+        };
+        let mut open = self.open.borrow_mut();
+        let id = open.keys().last().map_or(0, |id| id + 1);
+        open.insert(id, (file, 0));
+        Ok(id)
+    }
+    fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let mut open = self.open.borrow_mut();
+        let (file, offset) = open.get_mut(&id).unwrap();
+        let rest = &self.files[*file].1[*offset..];
+        let len = buf.len().min(rest.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        *offset += len;
+        Ok(len)
+    }
+    fn close(&self, id: usize) -> AxResult<usize> {
+        self.open.borrow_mut().remove(&id);
+        Ok(0)
+    }
+}
+
+/// Serves `files` until killed
+pub(crate) fn serve(files: Vec<(&'static str, Vec<u8>)>) {
+    let server = Server {
+        files,
+        open: RefCell::new(BTreeMap::new()),
+    };
+    let mut channel = File::create(":/prog").unwrap();
+    loop {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+    }
+}
+
+fn server() {
+    let mut files = Vec::from([("/decode", program())]);
+    files.extend(SCRIPTS.iter().map(|(name, data)| (*name, data.to_vec())));
+    serve(files);
+}
+
+/// Starts threads spinning, sleeping and waiting on a pipe, which `execve` stops
+fn start_threads() {
+    spawn(|| loop {
+        core::hint::spin_loop();
+    });
+    spawn(|| sleep(Duration::from_secs(3600)));
+    let (mut reader, writer) = pipe().unwrap();
+    core::mem::forget(writer);
+    spawn(move || {
+        let _ = reader.read(&mut [0u8; 1]);
+    });
+    sleep(Duration::from_millis(100));
+}
+
+/// Runs `path` with arguments `[name, "abc"]` in a child after `before`, returns its exit code
+fn run(path: &'static str, name: &'static str, before: fn()) -> i32 {
+    let pid = match fork() {
+        0 => {
+            before();
+            execve(path, &[name, "abc"], &[]);
+            panic!("Error execve()");
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let mut code: i32 = 0;
+    waitpid(pid, &mut code, 0).unwrap();
+    code
+}
+
+pub fn main() {
+    let server_pid = match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    sleep(Duration::from_millis(100));
+
+    for (path, name) in [("prog:/script", "script"), ("prog:/nested", "nested")] {
+        let code = run(path, name, || {});
+        println!(
+            "{}: argc {}, argv[1] starts with {:?}",
+            name,
+            code & 0xf,
+            char::from((code >> 12) as u8)
+        );
+    }
+    let code = run("prog:/decode", "threads", start_threads);
+    println!(
+        "Program with threads: argc {}, argv[1] starts with {:?}",
+        code & 0xf,
+        char::from((code >> 12) as u8)
+    );
+    // Failed `execve`s return, current process is kept.
+    for path in ["prog:/loop", "prog:/text", "prog:/missing"] {
+        println!("{}: {:?}", path, from_ret_code(execve(path, &[path], &[])));
+    }
+
+    kill(server_pid as usize, SIGKILL).unwrap();
+    let mut code: i32 = 0;
+    waitpid(server_pid, &mut code, 0).unwrap();
+    println!("Server killed by signal {:?}", exit_signal(code));
+    println!("Exec path tests run OK!");
+}
@@ -0,0 +1,100 @@
+#![allow(unused)]
+use core::time::Duration;
+
+use libax::{
+    io::{pipe, Read},
+    process::{fork, waitpid},
+    signal::{exit_signal, kill, SIGTERM},
+    task::{exit, sleep, spawn, thread_exit},
+    Mutex,
+};
+
+/// Locked by the main thread of a child, so that its other threads wait on a futex
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Runs `f` in a child, returns its pid
+fn run(f: fn()) -> isize {
+    match fork() {
+        0 => {
+            f();
+            panic!("Child returned");
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    }
+}
+
+fn wait_code(pid: isize) -> i32 {
+    let mut code: i32 = 0;
+    waitpid(pid, &mut code, 0).unwrap();
+    code
+}
+
+/// Blocks forever reading a pipe whose write end is kept open
+fn read_pipe() {
+    let (mut reader, writer) = pipe().unwrap();
+    let mut buf = [0u8; 1];
+    let _ = reader.read(&mut buf);
+    drop(writer);
+}
+
+/// Threads spinning, sleeping, and waiting on a pipe and a futex do not keep the process.
+fn busy_process() {
+    core::mem::forget(LOCK.lock());
+    spawn(|| loop {
+        core::hint::spin_loop();
+    });
+    spawn(|| sleep(Duration::from_secs(3600)));
+    spawn(read_pipe);
+    spawn(|| drop(LOCK.lock()));
+    sleep(Duration::from_millis(100));
+    exit(7);
+}
+
+/// A thread exits the process while the main thread is blocked.
+fn exiting_thread() {
+    spawn(|| {
+        sleep(Duration::from_millis(100));
+        exit(9);
+    });
+    read_pipe();
+}
+
+/// The main thread ends first, the process exits with its last thread.
+fn last_thread() {
+    spawn(|| {
+        sleep(Duration::from_millis(100));
+        thread_exit(5);
+    });
+    thread_exit(0);
+}
+
+/// All threads are blocked when the signal is sent.
+fn blocked_process() {
+    spawn(|| sleep(Duration::from_secs(3600)));
+    read_pipe();
+}
+
+pub fn main() {
+    println!(
+        "Process with running and blocked threads exits with {}",
+        wait_code(run(busy_process))
+    );
+    println!(
+        "Exit from a thread ends the process with {}",
+        wait_code(run(exiting_thread))
+    );
+    println!(
+        "Process exits with its last thread: {}",
+        wait_code(run(last_thread))
+    );
+
+    let pid = run(blocked_process);
+    sleep(Duration::from_millis(100));
+    kill(pid as usize, SIGTERM).unwrap();
+    println!(
+        "Blocked process killed by signal {:?}",
+        exit_signal(wait_code(pid))
+    );
+    println!("Exit tests run OK!");
+}
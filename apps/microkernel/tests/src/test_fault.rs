@@ -0,0 +1,69 @@
+#![allow(unused)]
+use libax::{
+    process::{fork, waitpid},
+    signal::*,
+    task::exit,
+};
+
+fn run_child(f: fn()) -> i32 {
+    let pid = match fork() {
+        0 => {
+            f();
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let mut ret: i32 = 0;
+    waitpid(pid, &mut ret, 0).unwrap();
+    ret
+}
+
+/// The first page is never mapped.
+const UNMAPPED: usize = 0x8;
+
+fn write_unmapped() {
+    unsafe { core::ptr::write_volatile(UNMAPPED as *mut usize, 1) };
+}
+
+fn illegal_instruction() {
+    #[cfg(any(target_arch = "riscv64", target_arch = "riscv32"))]
+    unsafe {
+        core::arch::asm!("unimp")
+    };
+}
+
+extern "C" fn exit_on_fault(sig: usize) {
+    exit(100 + sig);
+}
+
+pub fn main() {
+    let ret = run_child(write_unmapped);
+    println!(
+        "Writing to an unmapped address: code {}, signal {:?}",
+        ret,
+        exit_signal(ret)
+    );
+    assert_eq!(exit_signal(ret), Some(SIGSEGV));
+
+    let ret = run_child(illegal_instruction);
+    println!(
+        "Illegal instruction: code {}, signal {:?}",
+        ret,
+        exit_signal(ret)
+    );
+    assert_eq!(exit_signal(ret), Some(SIGILL));
+
+    // Faults cannot be ignored, but can be handled.
+    let ret = run_child(|| {
+        ignore(SIGSEGV).unwrap();
+        write_unmapped();
+    });
+    assert_eq!(exit_signal(ret), Some(SIGSEGV));
+    let ret = run_child(|| {
+        signal(SIGSEGV, exit_on_fault).unwrap();
+        write_unmapped();
+    });
+    println!("Handler exited with code {}", ret);
+    println!("Fault tests run OK!");
+}
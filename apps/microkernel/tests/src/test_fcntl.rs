@@ -0,0 +1,47 @@
+#![allow(unused)]
+use libax::{
+    axerrno::AxError,
+    io::{pipe, stdout, Read, Write},
+    OpenFlags,
+};
+
+pub fn main() {
+    let (mut reader, mut writer) = pipe().unwrap();
+
+    assert_eq!(reader.cloexec(), Ok(false));
+    reader.set_cloexec(true).unwrap();
+    assert_eq!(reader.cloexec(), Ok(true));
+    let mut dup = reader.dup("").unwrap();
+    assert_eq!(dup.cloexec(), Ok(false));
+    println!("Close-on-exec is per descriptor");
+
+    assert_eq!(reader.status_flags(), Ok(OpenFlags::READ));
+    assert_eq!(writer.status_flags(), Ok(OpenFlags::WRITE));
+    reader.set_status_flags(OpenFlags::NONBLOCK).unwrap();
+    assert_eq!(
+        dup.status_flags(),
+        Ok(OpenFlags::READ | OpenFlags::NONBLOCK)
+    );
+    let mut buf = [0u8; 16];
+    assert_eq!(dup.read(&mut buf), Err(AxError::WouldBlock));
+    println!("Status flags are shared by duplicates");
+    reader.set_status_flags(OpenFlags::empty()).unwrap();
+    drop(dup);
+
+    // The scheme enforces the flags, those it refuses are not kept.
+    let mut out = stdout();
+    assert!(out.set_status_flags(OpenFlags::APPEND).is_err());
+    assert_eq!(out.status_flags(), Ok(OpenFlags::READ));
+    drop(out);
+    println!("Status flags refused by the scheme are not kept");
+
+    // Redirect stdout into the pipe, then restore it.
+    writer.dup2(1, "").unwrap();
+    println!("Hello from stdout");
+    stdout().dup2(1, "").unwrap();
+    drop(writer);
+    let mut line = alloc::string::String::new();
+    reader.read_to_string(&mut line).unwrap();
+    print!("Pipe got: {}", line);
+    println!("fcntl tests run OK!");
+}
@@ -0,0 +1,112 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
+use core::{cell::Cell, time::Duration};
+
+use libax::{
+    axerrno::{ax_err, AxResult},
+    io::{funmap, File},
+    process::{fork, wait},
+    scheme::{Map, MapFlags, Packet, Scheme},
+    task::{exit, sleep},
+};
+
+const PAGE_SIZE: usize = 4096;
+const SIZE: usize = 2 * PAGE_SIZE;
+
+struct Server {
+    buffer: *mut u8,
+    unmapped: Cell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, _path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+    fn fmap(&self, _id: usize, map: &Map) -> AxResult<usize> {
+        if map.offset != 0 || map.size > SIZE {
+            return ax_err!(InvalidInput);
+        }
+        Ok(self.buffer as usize)
+    }
+    fn funmap(&self, address: usize, _length: usize) -> AxResult<usize> {
+        assert_eq!(address, self.buffer as usize);
+        let data = unsafe { core::slice::from_raw_parts(self.buffer, SIZE) };
+        println!("Server sees: {}", as_str(data));
+        self.unmapped.set(true);
+        Ok(0)
+    }
+}
+
+fn as_str(data: &[u8]) -> &str {
+    let len = data.iter().position(|c| *c == 0).unwrap_or(data.len());
+    core::str::from_utf8(&data[..len]).unwrap()
+}
+
+fn server() {
+    println!("Server started");
+    let layout = Layout::from_size_align(SIZE, PAGE_SIZE).unwrap();
+    let buffer = unsafe { alloc_zeroed(layout) };
+    unsafe {
+        core::slice::from_raw_parts_mut(buffer, SIZE)[..17].copy_from_slice(b"Hello from server");
+        *buffer.add(PAGE_SIZE) = b'#';
+    }
+    let server = Server {
+        buffer,
+        unmapped: Cell::new(false),
+    };
+    let mut channel = File::create(":/map").unwrap();
+    while !server.unmapped.get() {
+        let mut packet: Packet = Packet::default();
+        assert_eq!(
+            channel.read_data(&mut packet).unwrap(),
+            core::mem::size_of::<Packet>()
+        );
+        server.handle(&mut packet);
+        assert_eq!(
+            channel.write_data(&packet).unwrap(),
+            core::mem::size_of::<Packet>()
+        );
+    }
+    unsafe { dealloc(buffer, layout) };
+}
+
+fn client() {
+    let mut file = File::open("map:/").unwrap();
+    let addr = file
+        .fmap(
+            0,
+            SIZE,
+            MapFlags::PROT_READ | MapFlags::PROT_WRITE | MapFlags::MAP_SHARED,
+        )
+        .unwrap();
+    // The mapping stays valid after the file is closed.
+    drop(file);
+
+    let data = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, SIZE) };
+    println!("Client sees: {}", as_str(data));
+    println!("Second page: {}", data[PAGE_SIZE] as char);
+    data[..17].copy_from_slice(b"Hello from client");
+    funmap(addr, SIZE).unwrap();
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+    client();
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("fmap tests run OK!");
+}
@@ -0,0 +1,87 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::string::String;
+use core::{cell::RefCell, time::Duration};
+
+use libax::{
+    axerrno::{ax_err, AxResult},
+    io::{self, File, Write},
+    process::{fork, wait},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+
+/// Serves a single file whose path can be changed with `frename`
+struct Server {
+    path: RefCell<String>,
+    closed: RefCell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        if path != self.path.borrow().as_str() {
+            return ax_err!(NotFound);
+        }
+        Ok(0)
+    }
+    fn fsync(&self, _id: usize) -> AxResult<usize> {
+        println!("Server: fsync");
+        Ok(0)
+    }
+    fn fpath(&self, _id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let path = self.path.borrow();
+        let len = buf.len().min(8 + path.len());
+        buf[..len].copy_from_slice(&[b"forward:", path.as_bytes()].concat()[..len]);
+        Ok(len)
+    }
+    fn frename(&self, _id: usize, path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+        println!("Server: rename {} -> {}", self.path.borrow(), path);
+        *self.path.borrow_mut() = path.into();
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        *self.closed.borrow_mut() = true;
+        Ok(0)
+    }
+}
+
+fn server() {
+    let server = Server {
+        path: RefCell::new("/a".into()),
+        closed: RefCell::new(false),
+    };
+    let mut channel = File::create(":/forward").unwrap();
+    // serves until the client is done, i.e. the third file is closed
+    let mut closed = 0;
+    while closed < 3 {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+        if server.closed.replace(false) {
+            closed += 1;
+        }
+    }
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+
+    File::open("forward:/a").unwrap().flush().unwrap();
+    io::rename("forward:/a", "forward:/b").unwrap();
+    println!("Path: {}", io::canonicalize("forward:/b").unwrap());
+
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("Forward tests run OK!");
+}
@@ -0,0 +1,42 @@
+#![allow(unused)]
+use core::ptr::addr_of_mut;
+
+use libax::task::sbrk;
+
+/// More than the physical memory
+const HEAP_SIZE: usize = 512 << 20;
+const HEAP_STRIDE: usize = 16 << 20;
+
+/// A large `.bss`, not loaded as a whole either
+const BSS_SIZE: usize = 16 << 20;
+static mut BSS: [u8; BSS_SIZE] = [0; BSS_SIZE];
+
+pub fn main() {
+    let start = sbrk(HEAP_SIZE as isize);
+    assert!(start > 0);
+    println!("Reserved {} MiB of heap", HEAP_SIZE >> 20);
+
+    let heap = start as usize as *mut u8;
+    let mut touched = 0;
+    for offset in (0..HEAP_SIZE).step_by(HEAP_STRIDE) {
+        unsafe {
+            assert_eq!(heap.add(offset).read_volatile(), 0);
+            heap.add(offset).write_volatile(1);
+            assert_eq!(heap.add(offset + 1).read_volatile(), 0);
+        }
+        touched += 1;
+    }
+    println!("Touched {} pages, untouched memory reads zero", touched);
+    assert_eq!(sbrk(-(HEAP_SIZE as isize)), start + HEAP_SIZE as isize);
+
+    let bss = addr_of_mut!(BSS) as *mut u8;
+    for offset in (0..BSS_SIZE).step_by(BSS_SIZE / 4) {
+        unsafe {
+            assert_eq!(bss.add(offset).read_volatile(), 0);
+            bss.add(offset).write_volatile(2);
+            assert_eq!(bss.add(offset).read_volatile(), 2);
+        }
+    }
+    println!("Large .bss is mapped on access");
+    println!("Lazy allocation tests run OK!");
+}
@@ -0,0 +1,146 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::alloc::{alloc, dealloc, Layout};
+use core::{cell::Cell, time::Duration};
+
+use libax::{
+    axerrno::AxResult,
+    io::{self, File, Read, Write},
+    process::{fork, wait},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+
+const PAGE_SIZE: usize = 4096;
+/// Buffers of 4 pages are lent to the server instead of being copied.
+const LEN: usize = 4 * PAGE_SIZE;
+/// Where unaligned buffers start in their first page
+const OFFSET: usize = 100;
+/// The length of unaligned buffers, ending in the middle of a page as well
+const UNALIGNED_LEN: usize = LEN + 200;
+/// Client bytes around the buffers, which must be kept as they are
+const GUARD: u8 = 0xaa;
+
+fn pattern(i: usize) -> u8 {
+    (i % 251) as u8
+}
+
+fn has_pattern(buf: &[u8]) -> bool {
+    buf.iter().enumerate().all(|(i, b)| *b == pattern(i))
+}
+
+fn result(ok: bool) -> &'static str {
+    if ok {
+        "OK"
+    } else {
+        "corrupted"
+    }
+}
+
+/// Checks the data written to it, and fills the buffers read from it
+#[derive(Default)]
+struct Server {
+    done: Cell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, _path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        Ok(0)
+    }
+    fn read(&self, _id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = pattern(i);
+        }
+        Ok(buf.len())
+    }
+    fn write(&self, _id: usize, buf: &[u8]) -> AxResult<usize> {
+        println!(
+            "Server: write {} bytes {}",
+            buf.len(),
+            result(has_pattern(buf))
+        );
+        Ok(buf.len())
+    }
+    fn unlink(&self, _path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+        self.done.set(true);
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+}
+
+fn server() {
+    let server = Server::default();
+    let mut channel = File::create(":/lend").unwrap();
+    while !server.done.get() {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+    }
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+
+    // Page aligned, with a page to spare after the unaligned buffers
+    let layout = Layout::from_size_align(LEN + 2 * PAGE_SIZE, PAGE_SIZE).unwrap();
+    let pages = unsafe { core::slice::from_raw_parts_mut(alloc(layout), layout.size()) };
+    let mut file = File::create("lend:/").unwrap();
+
+    for (i, b) in pages[..LEN].iter_mut().enumerate() {
+        *b = pattern(i);
+    }
+    let len = file.write(&pages[..LEN]).unwrap();
+    println!("Client: wrote {} aligned bytes", len);
+
+    pages.fill(GUARD);
+    for (i, b) in pages[OFFSET..OFFSET + UNALIGNED_LEN].iter_mut().enumerate() {
+        *b = pattern(i);
+    }
+    let len = file.write(&pages[OFFSET..OFFSET + UNALIGNED_LEN]).unwrap();
+    println!("Client: wrote {} unaligned bytes", len);
+
+    pages.fill(GUARD);
+    let len = file.read(&mut pages[..LEN]).unwrap();
+    println!(
+        "Client: read {} aligned bytes {}",
+        len,
+        result(has_pattern(&pages[..LEN]))
+    );
+
+    // Only the buffer is copied back from the partial pages at its ends.
+    pages.fill(GUARD);
+    let len = file
+        .read(&mut pages[OFFSET..OFFSET + UNALIGNED_LEN])
+        .unwrap();
+    println!(
+        "Client: read {} unaligned bytes {}",
+        len,
+        result(has_pattern(&pages[OFFSET..OFFSET + UNALIGNED_LEN]))
+    );
+    let kept = pages[..OFFSET]
+        .iter()
+        .chain(&pages[OFFSET + UNALIGNED_LEN..])
+        .all(|b| *b == GUARD);
+    println!("Client: bytes around the buffer {}", result(kept));
+
+    drop(file);
+    unsafe { dealloc(pages.as_mut_ptr(), layout) };
+    io::remove_file("lend:/").unwrap();
+
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("Lend tests run OK!");
+}
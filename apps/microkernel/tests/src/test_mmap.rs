@@ -0,0 +1,120 @@
+#![allow(unused)]
+use libax::{
+    mem::{mmap, mprotect, munmap, MapFlags},
+    process::{fork, waitpid},
+    signal::exit_signal,
+    task::exit,
+};
+
+const PAGE_SIZE: usize = 4096;
+const RW: MapFlags = MapFlags::PROT_READ.union(MapFlags::PROT_WRITE);
+
+fn page(addr: usize, index: usize) -> *mut u8 {
+    (addr + index * PAGE_SIZE) as *mut u8
+}
+
+/// Runs `f` in a child process, returns the signal that killed it
+fn signal_of(f: fn()) -> Option<i32> {
+    let pid = match fork() {
+        0 => {
+            f();
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let mut ret: i32 = 0;
+    waitpid(pid, &mut ret, 0).unwrap();
+    exit_signal(ret)
+}
+
+static mut ADDR: usize = 0;
+
+/// `li a0, 42; ret`
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+const CODE: [u32; 2] = [0x02a0_0513, 0x0000_8067];
+
+fn run_code(addr: usize) -> usize {
+    #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+    unsafe {
+        core::ptr::copy_nonoverlapping(CODE.as_ptr(), addr as *mut u32, CODE.len());
+        mprotect(addr, PAGE_SIZE, MapFlags::PROT_READ | MapFlags::PROT_EXEC).unwrap();
+        core::arch::asm!("fence.i");
+        let f: extern "C" fn() -> usize = core::mem::transmute(addr);
+        f()
+    }
+    #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+    unimplemented!()
+}
+
+pub fn main() {
+    let addr = mmap(0, 4 * PAGE_SIZE, RW).unwrap();
+    unsafe {
+        ADDR = addr;
+        for i in 0..4 {
+            *page(addr, i) = i as u8 + 1;
+        }
+        println!(
+            "Mapped 4 pages: {}",
+            (0..4).all(|i| *page(addr, i) == i as u8 + 1)
+        );
+    }
+
+    let hint = 0x3000_0000;
+    println!(
+        "Hinted address is used: {}",
+        mmap(hint, PAGE_SIZE, RW) == Ok(hint)
+    );
+
+    let fixed = mmap(addr + PAGE_SIZE, PAGE_SIZE, RW | MapFlags::MAP_FIXED).unwrap();
+    unsafe {
+        println!(
+            "Fixed mapping replaces a page: {}, {}, {}",
+            fixed == addr + PAGE_SIZE,
+            *page(addr, 1),
+            *page(addr, 2)
+        );
+    }
+
+    munmap(addr + 2 * PAGE_SIZE, PAGE_SIZE).unwrap();
+    println!(
+        "Access to an unmapped page: signal {:?}",
+        signal_of(|| unsafe { *page(ADDR, 2) = 1 })
+    );
+    unsafe {
+        println!(
+            "Pages around it are kept: {}, {}",
+            *page(addr, 1),
+            *page(addr, 3)
+        );
+    }
+
+    mprotect(addr, PAGE_SIZE, MapFlags::PROT_READ).unwrap();
+    println!(
+        "Write to a read-only page: signal {:?}",
+        signal_of(|| unsafe { *page(ADDR, 0) = 0 })
+    );
+    mprotect(addr, PAGE_SIZE, MapFlags::empty()).unwrap();
+    println!(
+        "Read of an inaccessible page: signal {:?}",
+        signal_of(|| unsafe {
+            core::ptr::read_volatile(page(ADDR, 0));
+        })
+    );
+    mprotect(addr, PAGE_SIZE, RW).unwrap();
+    unsafe {
+        *page(addr, 0) += 1;
+        println!("Page is writable again, contents kept: {}", *page(addr, 0));
+    }
+
+    println!(
+        "mprotect of unmapped pages: {:?}",
+        mprotect(addr, 3 * PAGE_SIZE, MapFlags::PROT_READ)
+    );
+
+    let code = mmap(0, PAGE_SIZE, RW).unwrap();
+    println!("Generated code returns {}", run_code(code));
+
+    munmap(addr, 4 * PAGE_SIZE).unwrap();
+    println!("mmap tests run OK!");
+}
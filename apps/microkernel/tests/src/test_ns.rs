@@ -0,0 +1,47 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::string::String;
+use libax::{
+    axerrno::AxError,
+    io::{File, Read},
+    process::{fork, mkns, setns, wait},
+    task::exit,
+};
+
+fn sandbox(ns: usize) {
+    setns(ns).unwrap();
+    println!("Entered namespace {}", ns);
+    assert!(matches!(File::open("nstest:/"), Err(AxError::NotFound)));
+    println!("Scheme of the parent is hidden");
+    assert_eq!(setns(0), Err(AxError::PermissionDenied));
+    println!("Cannot escape to the root namespace");
+    assert_eq!(mkns(&["nstest"]), Err(AxError::NotFound));
+    let _channel = File::create(":/nstest").unwrap();
+    println!("Registered a scheme in the namespace");
+    let mut list = String::new();
+    File::open(":").unwrap().read_to_string(&mut list).unwrap();
+    print!("{}", list);
+}
+
+pub fn main() {
+    println!("Running namespace tests...");
+    let _channel = File::create(":/nstest").unwrap();
+    let ns = mkns(&[]).unwrap();
+    match fork() {
+        0 => {
+            sandbox(ns);
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    assert!(matches!(
+        File::create(":/nstest"),
+        Err(AxError::AlreadyExists)
+    ));
+    println!("Namespace tests run OK!");
+}
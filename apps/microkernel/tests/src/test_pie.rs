@@ -0,0 +1,187 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use libax::{
+    process::{execve, fork, waitpid},
+    signal::{kill, SIGKILL},
+    task::{exit, sleep},
+};
+
+use crate::test_exec::serve;
+
+/// Exits with the byte pointed to by the word after it, which is relocated
+const PIE_CODE: [u32; 5] = [
+    0x0000_0297, // auipc t0, 0
+    0x0182_b303, // ld t1, 24(t0)
+    0x0003_4503, // lbu a0, 0(t1)
+    0x00a0_0893, // li a7, 10
+    0x0000_0073, // ecall
+];
+
+/// Exits with `a0 + 40`, `a0` is set by the interpreter
+const PROGRAM_CODE: [u32; 3] = [
+    0x0285_0513, // addi a0, a0, 40
+    0x00a0_0893, // li a7, 10
+    0x0000_0073, // ecall
+];
+
+/// Jumps to `AT_ENTRY` with `a0 = 2` if `AT_BASE` is where it is loaded, `0` otherwise
+const INTERP_CODE: [u32; 27] = [
+    0x0001_3283, // ld t0, 0(sp)
+    0x0081_0313, // addi t1, sp, 8
+    0x0032_9393, // slli t2, t0, 3
+    0x0073_0333, // add t1, t1, t2
+    0x0083_0313, // addi t1, t1, 8
+    0x0003_3e03, // 1: ld t3, 0(t1)
+    0x0083_0313, // addi t1, t1, 8
+    0xfe0e_1ce3, // bnez t3, 1b
+    0x0070_0593, // li a1, 7
+    0x0090_0693, // li a3, 9
+    0x0000_0e93, // li t4, 0
+    0x0000_0613, // li a2, 0
+    0x0003_3e03, // 2: ld t3, 0(t1)
+    0x0083_3f03, // ld t5, 8(t1)
+    0x0103_0313, // addi t1, t1, 16
+    0x000e_0c63, // beqz t3, 4f
+    0x00be_1463, // bne t3, a1, 3f
+    0x000f_0e93, // mv t4, t5
+    0xfede_14e3, // 3: bne t3, a3, 2b
+    0x000f_0613, // mv a2, t5
+    0xfe1f_f06f, // j 2b
+    0x0000_0297, // 4: auipc t0, 0
+    0x405e_8eb3, // sub t4, t4, t0
+    0x0cce_8e93, // addi t4, t4, 204 (offset of the `auipc` in the file)
+    0x001e_b513, // seqz a0, t4
+    0x0015_1513, // slli a0, a0, 1
+    0x0006_0067, // jr a2
+];
+
+/// Address the program with an interpreter is loaded at
+const BASE: u64 = 0x1_0000;
+
+fn code(code: &[u32]) -> Vec<u8> {
+    code.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// An ELF file with its headers and `body` in a single segment loaded at `base`,
+/// position-independent if `base` is `0`.
+///
+/// `entry` and the ranges of the other program `headers` (type, offset, size) are in `body`.
+fn elf(base: u64, entry: usize, body: &[u8], headers: &[(u32, usize, usize)]) -> Vec<u8> {
+    const EHDR_SIZE: usize = 64;
+    const PHDR_SIZE: usize = 56;
+    let body_offset = EHDR_SIZE + PHDR_SIZE * (1 + headers.len());
+    let mut elf = Vec::new();
+    // 64-bit, little endian, version 1
+    elf.extend_from_slice(b"\x7fELF\x02\x01\x01");
+    elf.resize(16, 0);
+    // e_type (executable or shared object), e_machine (RISC-V)
+    elf.extend((if base == 0 { 3u16 } else { 2 }).to_le_bytes());
+    elf.extend(0xf3u16.to_le_bytes());
+    elf.extend(1u32.to_le_bytes());
+    // e_entry, e_phoff, e_shoff
+    for word in [base + (body_offset + entry) as u64, EHDR_SIZE as u64, 0] {
+        elf.extend(word.to_le_bytes());
+    }
+    elf.extend(0u32.to_le_bytes());
+    // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx
+    for half in [EHDR_SIZE, PHDR_SIZE, 1 + headers.len(), 64, 0, 0] {
+        elf.extend((half as u16).to_le_bytes());
+    }
+    let size = (body_offset + body.len()) as u64;
+    // p_type (load), p_flags (read, write and execute)
+    let load = (1, 7, 0, size, 0x1000);
+    for (p_type, p_flags, offset, size, align) in
+        core::iter::once(load).chain(headers.iter().map(|(p_type, offset, size)| {
+            (*p_type, 4, (body_offset + offset) as u64, *size as u64, 8)
+        }))
+    {
+        elf.extend(p_type.to_le_bytes());
+        elf.extend((p_flags as u32).to_le_bytes());
+        // p_offset, p_vaddr, p_paddr, p_filesz, p_memsz, p_align
+        for word in [offset, base + offset, base + offset, size, size, align] {
+            elf.extend(word.to_le_bytes());
+        }
+    }
+    elf.extend_from_slice(body);
+    elf
+}
+
+/// A position-independent program running [`PIE_CODE`], with the word after it
+/// relocated to point to `42`
+fn pie() -> Vec<u8> {
+    const PT_DYNAMIC: u32 = 2;
+    // Offsets of the headers and the body are known as there is one more program header.
+    let body_offset = 64 + 56 * 2;
+    let mut body = code(&PIE_CODE);
+    body.resize(24, 0);
+    // The word relocated to the address of `42`
+    body.extend(0u64.to_le_bytes());
+    body.extend(42u64.to_le_bytes());
+    // DT_RELA, DT_RELASZ, DT_RELAENT, DT_NULL
+    let dynamic = body.len();
+    for (tag, value) in [(7, body_offset + 104), (8, 24), (9, 24), (0, 0)] {
+        body.extend((tag as u64).to_le_bytes());
+        body.extend((value as u64).to_le_bytes());
+    }
+    // r_offset, r_info (R_RISCV_RELATIVE), r_addend
+    for word in [body_offset + 24, 3, body_offset + 32] {
+        body.extend((word as u64).to_le_bytes());
+    }
+    assert_eq!(dynamic + 64, 104);
+    elf(0, 0, &body, &[(PT_DYNAMIC, dynamic, 64)])
+}
+
+/// A program running [`PROGRAM_CODE`] with the interpreter `prog:/interp`
+fn interpreted() -> Vec<u8> {
+    const PT_INTERP: u32 = 3;
+    let mut body = code(&PROGRAM_CODE);
+    let interp = body.len();
+    body.extend_from_slice(b"prog:/interp\0");
+    elf(BASE, 0, &body, &[(PT_INTERP, interp, body.len() - interp)])
+}
+
+/// Runs `path` in a child, returns its exit code
+fn run(path: &'static str) -> i32 {
+    let pid = match fork() {
+        0 => {
+            execve(path, &[path], &[]);
+            panic!("Error execve()");
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let mut code: i32 = 0;
+    waitpid(pid, &mut code, 0).unwrap();
+    code
+}
+
+pub fn main() {
+    let server_pid = match fork() {
+        0 => {
+            serve(Vec::from([
+                ("/pie", pie()),
+                ("/interpreted", interpreted()),
+                ("/interp", elf(0, 0, &code(&INTERP_CODE), &[])),
+            ]));
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    sleep(Duration::from_millis(100));
+
+    println!("PIE program exits with {}", run("prog:/pie"));
+    println!(
+        "Program run by its interpreter exits with {}",
+        run("prog:/interpreted")
+    );
+
+    kill(server_pid as usize, SIGKILL).unwrap();
+    let mut code: i32 = 0;
+    waitpid(server_pid, &mut code, 0).unwrap();
+    println!("PIE tests run OK!");
+}
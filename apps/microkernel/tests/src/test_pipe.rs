@@ -0,0 +1,45 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use libax::{
+    axerrno::AxError,
+    io::{pipe, Read, Write},
+    process::{fork, wait},
+    task::exit,
+};
+
+/// More than the capacity of a pipe, so the writer has to wait for the reader.
+const LEN: usize = 10000;
+
+pub fn main() {
+    let (mut reader, mut writer) = pipe().unwrap();
+    match fork() {
+        0 => {
+            drop(reader);
+            writer.write_all(b"Hello, pipe!\n").unwrap();
+            writer.write_all(&[b'a'; LEN]).unwrap();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    // The child holds the only write end now.
+    drop(writer);
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    let (line, rest) = data.split_at(13);
+    print!("Parent got: {}", core::str::from_utf8(line).unwrap());
+    assert!(rest.len() == LEN && rest.iter().all(|c| *c == b'a'));
+    println!("Parent got {} more bytes before EOF", rest.len());
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+
+    let (reader, mut writer) = pipe().unwrap();
+    drop(reader);
+    assert_eq!(writer.write(b"lost"), Err(AxError::BrokenPipe));
+    println!("Writing without readers fails");
+    println!("Pipe tests run OK!");
+}
@@ -0,0 +1,92 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::{cell::RefCell, time::Duration};
+
+use libax::{
+    axerrno::AxResult,
+    io::{self, File, Read, Write},
+    process::{fork, wait},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+
+/// Echoes the data written to it, and reports the paths and buffers of small requests
+#[derive(Default)]
+struct Server {
+    data: RefCell<Vec<u8>>,
+    done: RefCell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        println!("Server: open {}", path);
+        Ok(0)
+    }
+    fn read(&self, _id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let mut data = self.data.borrow_mut();
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        data.drain(..len);
+        Ok(len)
+    }
+    fn write(&self, _id: usize, buf: &[u8]) -> AxResult<usize> {
+        println!("Server: write {}", core::str::from_utf8(buf).unwrap());
+        self.data.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn dup(&self, _old_id: usize, buf: &[u8]) -> AxResult<usize> {
+        println!("Server: dup {}", core::str::from_utf8(buf).unwrap());
+        Ok(1)
+    }
+    fn unlink(&self, path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+        println!("Server: unlink {}", path);
+        *self.done.borrow_mut() = true;
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+}
+
+fn server() {
+    let server = Server::default();
+    let mut channel = File::create(":/request").unwrap();
+    while !*server.done.borrow() {
+        let mut packet: Packet = Packet::default();
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        channel.write_data(&packet).unwrap();
+    }
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+
+    // Paths and small buffers are copied into read-only pages of the server.
+    let mut file = File::create("request:/a/b").unwrap();
+    file.write_all(b"Hello").unwrap();
+    let mut buf = [0u8; 16];
+    let len = file.read(&mut buf).unwrap();
+    println!(
+        "Client: read {}",
+        core::str::from_utf8(&buf[..len]).unwrap()
+    );
+    drop(file.dup("copy").unwrap());
+    drop(file);
+    io::remove_file("request:/a/b").unwrap();
+
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("Request tests run OK!");
+}
@@ -0,0 +1,87 @@
+#![allow(unused)]
+use core::time::Duration;
+
+use libax::{
+    axerrno::{AxError, AxResult},
+    io::{File, Read},
+    process::{fork, wait},
+    scheme::{Packet, Scheme},
+    task::{exit, sleep},
+};
+
+struct Server;
+
+impl Scheme for Server {
+    fn open(&self, _path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+}
+
+/// Answers the `open` request, then takes the next request without answering it.
+fn serve_one(channel: &mut File) {
+    let mut packet: Packet = Packet::default();
+    channel.read_data(&mut packet).unwrap();
+    Server.handle(&mut packet);
+    channel.write_data(&packet).unwrap();
+}
+
+/// The server exits with a pending request.
+fn exiting_server() {
+    let mut channel = File::create(":/closing").unwrap();
+    serve_one(&mut channel);
+    let mut packet: Packet = Packet::default();
+    channel.read_data(&mut packet).unwrap();
+    println!("Server exits");
+    exit(0);
+}
+
+/// The server closes its root handle while a request is pending.
+fn closing_server() {
+    let mut channel = File::create(":/closing").unwrap();
+    serve_one(&mut channel);
+    sleep(Duration::from_millis(100));
+    println!("Server closes the scheme");
+    drop(channel);
+}
+
+fn spawn(f: fn()) {
+    match fork() {
+        0 => {
+            f();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+}
+
+pub fn main() {
+    let mut buf = [0u8; 16];
+    let mut ret: i32 = 0;
+
+    spawn(exiting_server);
+    sleep(Duration::from_millis(100));
+    let mut file = File::open("closing:/").unwrap();
+    assert_eq!(file.read(&mut buf), Err(AxError::ConnectionReset));
+    println!("Pending request is reset");
+    assert_eq!(file.read(&mut buf), Err(AxError::ConnectionReset));
+    println!("Later request is reset");
+    drop(file);
+    wait(0, &mut ret);
+    assert_eq!(File::open("closing:/").err(), Some(AxError::NotFound));
+    println!("Scheme is unregistered");
+
+    spawn(closing_server);
+    sleep(Duration::from_millis(100));
+    let mut file = File::open("closing:/").unwrap();
+    println!("Scheme is registered again");
+    assert_eq!(file.read(&mut buf), Err(AxError::ConnectionReset));
+    println!("Pending request is reset");
+    drop(file);
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("Scheme close tests run OK!");
+}
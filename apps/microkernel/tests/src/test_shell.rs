@@ -0,0 +1,44 @@
+#![allow(unused)]
+use apps::shell::pipeline::{assign, expand, parse, tokenize, Token};
+
+pub fn main() {
+    // `>>` is a single operator, which needs no spaces around it.
+    assert_eq!(
+        tokenize("a|b>>f"),
+        [
+            Token::Word("a".into()),
+            Token::Pipe,
+            Token::Word("b".into()),
+            Token::Append,
+            Token::Word("f".into()),
+        ]
+    );
+    println!("Tokenized a|b>>f");
+
+    let pipeline = parse("a x | b > f").unwrap();
+    assert!(!pipeline.background);
+    assert_eq!(pipeline.commands.len(), 2);
+    assert_eq!(pipeline.commands[0].words, ["a", "x"]);
+    assert_eq!(pipeline.commands[0].output, None);
+    assert_eq!(pipeline.commands[1].words, ["b"]);
+    assert_eq!(pipeline.commands[1].output, Some(("f".into(), false)));
+    println!("Parsed a x | b > f");
+
+    let pipeline = parse("b < in >> f &").unwrap();
+    assert!(pipeline.background);
+    assert_eq!(pipeline.commands.len(), 1);
+    assert_eq!(pipeline.commands[0].words, ["b"]);
+    assert_eq!(pipeline.commands[0].input, Some("in".into()));
+    assert_eq!(pipeline.commands[0].output, Some(("f".into(), true)));
+    println!("Parsed b < in >> f &");
+
+    for line in ["a & b", "a && b", "| a", "a >"] {
+        println!("{}: {}", line, parse(line).err().unwrap());
+    }
+    assert!(parse("").unwrap().commands.is_empty());
+
+    assert!(assign("NAME=world"));
+    assert!(!assign("NAME=two words"));
+    println!("Expanded {}", expand("hello_$NAME$?$"));
+    println!("Shell tests run OK!");
+}
@@ -0,0 +1,81 @@
+#![allow(unused)]
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use libax::{
+    axerrno::AxError,
+    process::{fork, getpid, waitpid},
+    signal::*,
+    task::{exit, sleep},
+};
+
+/// Signals handled so far, bit `n` for signal `n`
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handler(sig: usize) {
+    HANDLED.fetch_or(1 << sig, Ordering::SeqCst);
+}
+
+fn handled(sig: usize) -> bool {
+    HANDLED.load(Ordering::SeqCst) & (1 << sig) != 0
+}
+
+pub fn main() {
+    let pid = getpid();
+
+    // The handler runs before `kill` returns.
+    signal(SIGUSR1, handler).unwrap();
+    kill(pid, SIGUSR1).unwrap();
+    assert!(handled(SIGUSR1));
+    println!("Handler got SIGUSR1");
+
+    // Blocked signals stay pending.
+    sigprocmask(SIG_BLOCK, Some(sigmask(SIGUSR2))).unwrap();
+    signal(SIGUSR2, handler).unwrap();
+    kill(pid, SIGUSR2).unwrap();
+    assert!(!handled(SIGUSR2));
+    sigprocmask(SIG_UNBLOCK, Some(sigmask(SIGUSR2))).unwrap();
+    assert!(handled(SIGUSR2));
+    println!("Blocked signal is delivered after unblocking");
+
+    ignore(SIGTERM).unwrap();
+    kill(pid, SIGTERM).unwrap();
+    println!("Ignored SIGTERM");
+    assert_eq!(
+        sigaction(SIGKILL, Some(&SigAction::default())),
+        Err(AxError::InvalidInput)
+    );
+
+    // A child spinning in user mode is killed, and its parent gets SIGCHLD.
+    signal(SIGCHLD, handler).unwrap();
+    let child = match fork() {
+        0 => loop {
+            core::hint::spin_loop();
+        },
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    sleep(Duration::from_millis(50));
+    kill(child as usize, SIGKILL).unwrap();
+    let mut ret: i32 = 0;
+    assert_eq!(waitpid(child, &mut ret, 0), Ok(child as usize));
+    println!("Killed child exited with code {}", ret);
+    assert!(handled(SIGCHLD));
+    println!("Parent got SIGCHLD");
+
+    // Handlers are inherited by `fork`, the default action terminates the process.
+    let child = match fork() {
+        0 => {
+            default(SIGUSR1).unwrap();
+            kill(getpid(), SIGUSR1).unwrap();
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    waitpid(child, &mut ret, 0).unwrap();
+    println!("Child exited with code {} on SIGUSR1", ret);
+
+    assert_eq!(kill(pid, NSIG), Err(AxError::InvalidInput));
+    println!("Signal tests run OK!");
+}
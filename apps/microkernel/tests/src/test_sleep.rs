@@ -14,7 +14,7 @@ mod use_fn {
             println!("  tick {}", i);
             task::sleep(Duration::from_millis(500));
         }
-        task::exit(0);
+        task::thread_exit(0);
     }
 
     fn test2() {
@@ -25,7 +25,7 @@ mod use_fn {
         }
         println!("RESULT: {}", result);
         FINISHED_TASKS.fetch_add(1, Ordering::Relaxed);
-        task::exit(0);
+        task::thread_exit(0);
     }
 
     pub fn main() {
@@ -0,0 +1,55 @@
+#![allow(unused)]
+use core::hint::black_box;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use libax::{
+    process::{fork, waitpid},
+    signal::exit_signal,
+    task::{exit, spawn_with_stack, yield_now},
+};
+
+/// Uses a bit more than 1 KiB of stack per frame
+fn recurse(depth: usize) -> usize {
+    let frame = [depth as u8; 1024];
+    if depth == 0 {
+        return 0;
+    }
+    black_box(&frame);
+    recurse(depth - 1) + 1
+}
+
+static FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn main() {
+    // Deeper than the default stack of threads
+    spawn_with_stack(|| FRAMES.store(recurse(1024), Ordering::Release), 4 << 20).unwrap();
+    while FRAMES.load(Ordering::Acquire) == 0 {
+        yield_now();
+    }
+    println!(
+        "Recursed {} frames on a 4 MiB thread stack",
+        FRAMES.load(Ordering::Acquire)
+    );
+
+    println!(
+        "Stack larger than the limit: {:?}",
+        spawn_with_stack(|| {}, 1 << 30)
+    );
+
+    let pid = match fork() {
+        0 => {
+            recurse(usize::MAX);
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let mut ret: i32 = 0;
+    waitpid(pid, &mut ret, 0).unwrap();
+    println!(
+        "Overflowing child: code {}, signal {:?}",
+        ret,
+        exit_signal(ret)
+    );
+    println!("Stack tests run OK!");
+}
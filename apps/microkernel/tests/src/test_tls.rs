@@ -0,0 +1,83 @@
+#![allow(unused)]
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use libax::{
+    process::{fork, waitpid},
+    task::{exit, spawn, spawn_with_stack, yield_now},
+    Mutex,
+};
+
+libax::thread_local! {
+    /// Initialized in the TLS template
+    static ID: Cell<usize> = const { Cell::new(7) };
+    /// Initialized on the first access
+    static NAME: RefCell<String> = RefCell::new(String::from("unnamed"));
+    /// Makes the TLS block larger than a page
+    static BUFFER: Cell<[u8; 8192]> = const { Cell::new([0; 8192]) };
+}
+
+const THREADS: usize = 3;
+
+static FINISHED: AtomicUsize = AtomicUsize::new(0);
+static RESULTS: Mutex<Vec<(usize, usize, String)>> = Mutex::new(Vec::new());
+
+fn describe() -> (usize, String) {
+    (ID.with(Cell::get), NAME.with(|name| name.borrow().clone()))
+}
+
+pub fn main() {
+    println!("Main thread starts with {:?}", describe());
+    ID.with(|id| id.set(100));
+    NAME.with(|name| *name.borrow_mut() = String::from("main"));
+
+    for i in 0..THREADS {
+        spawn(move || {
+            let initial = ID.with(Cell::get);
+            ID.with(|id| id.set(i));
+            NAME.with(|name| name.borrow_mut().push_str(&alloc::format!(" {}", i)));
+            // Other threads run in between, with their own values.
+            for _ in 0..10 {
+                yield_now();
+            }
+            let (id, name) = describe();
+            assert_eq!(id, i);
+            RESULTS.lock().push((id, initial, name));
+            FINISHED.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+    while FINISHED.load(Ordering::SeqCst) < THREADS {
+        yield_now();
+    }
+    let mut results = RESULTS.lock().clone();
+    results.sort();
+    for (id, initial, name) in results {
+        println!("Thread {} started with {}, named {:?}", id, initial, name);
+    }
+    println!("Main thread keeps {:?}", describe());
+
+    // The TLS block is put on the stack of the thread, which is too small for it.
+    println!(
+        "Stack smaller than the TLS block: {:?}",
+        spawn_with_stack(|| BUFFER.with(|buffer| buffer.set([1; 8192])), 4096)
+    );
+
+    // A forked child has a copy of the values of the forking thread.
+    let pid = match fork() {
+        0 => {
+            let (id, _) = describe();
+            exit(id);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    let mut code: i32 = 0;
+    waitpid(pid, &mut code, 0).unwrap();
+    println!("Forked child has {}", code);
+    println!("TLS tests run OK!");
+}
@@ -0,0 +1,44 @@
+#![allow(unused)]
+use core::time::Duration;
+
+use libax::{
+    axerrno::AxError,
+    process::{fork, waitpid, WNOHANG},
+    task::{exit, sleep},
+};
+
+fn spawn_child(code: usize, delay: Duration) -> isize {
+    match fork() {
+        0 => {
+            sleep(delay);
+            exit(code);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    }
+}
+
+pub fn main() {
+    let slow = spawn_child(3, Duration::from_millis(500));
+    let fast = spawn_child(5, Duration::from_millis(10));
+
+    let mut ret: i32 = 0;
+    assert_eq!(waitpid(slow, &mut ret, WNOHANG), Ok(0));
+    println!("WNOHANG returns before the child exits");
+
+    assert_eq!(waitpid(fast, &mut ret, 0), Ok(fast as usize));
+    assert_eq!(ret, 5);
+    println!("Waited for the second child with code {}", ret);
+
+    assert_eq!(waitpid(-1, &mut ret, 0), Ok(slow as usize));
+    assert_eq!(ret, 3);
+    println!("Waited for any child with code {}", ret);
+
+    assert_eq!(waitpid(-1, &mut ret, 0), Err(AxError::NoChildProcess));
+    assert_eq!(
+        waitpid(fast, &mut ret, WNOHANG),
+        Err(AxError::NoChildProcess)
+    );
+    println!("No child left to wait for");
+    println!("Wait tests run OK!");
+}
@@ -0,0 +1,116 @@
+#![allow(unused)]
+extern crate alloc;
+
+use core::{cell::Cell, time::Duration};
+
+use libax::{
+    axerrno::{AxError, AxResult},
+    io::{self, File, Read},
+    process::{fork, wait, waitpid},
+    scheme::{Packet, Scheme},
+    signal::{kill, SIGKILL},
+    task::{exit, sleep},
+    OpenFlags,
+};
+
+/// Answers reads late, and never answers opens of `/hang`
+#[derive(Default)]
+struct Server {
+    hang: Cell<bool>,
+    done: Cell<bool>,
+}
+
+impl Scheme for Server {
+    fn open(&self, path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        println!("Server: open {}", path);
+        self.hang.set(path == "/hang");
+        Ok(0)
+    }
+    fn read(&self, _id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        sleep(Duration::from_millis(100));
+        println!("Server: answering read");
+        buf[..5].copy_from_slice(b"Hello");
+        Ok(5)
+    }
+    fn unlink(&self, _path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
+        self.done.set(true);
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+}
+
+fn server() {
+    let server = Server::default();
+    let mut channel = File::open_with(
+        ":/wake",
+        OpenFlags::CREATE | OpenFlags::READ | OpenFlags::WRITE | OpenFlags::NONBLOCK,
+    )
+    .unwrap();
+    let mut packet: Packet = Packet::default();
+    assert_eq!(channel.read_data(&mut packet), Err(AxError::WouldBlock));
+    println!("Server: no request yet");
+    channel.set_status_flags(OpenFlags::empty()).unwrap();
+
+    let mut hung = None;
+    while !server.done.get() {
+        let mut packet: Packet = Packet::default();
+        // Sleeps until a client sends a request
+        channel.read_data(&mut packet).unwrap();
+        server.handle(&mut packet);
+        if server.hang.take() {
+            hung = Some(packet);
+            continue;
+        }
+        // Its client is killed by then, so the response is dropped.
+        if let Some(hung) = hung.take() {
+            channel.write_data(&hung).unwrap();
+        }
+        channel.write_data(&packet).unwrap();
+    }
+}
+
+pub fn main() {
+    match fork() {
+        0 => {
+            server();
+            exit(0);
+        }
+        pid if pid > 0 => {}
+        _ => panic!("Error fork()"),
+    }
+    sleep(Duration::from_millis(100));
+
+    // The client sleeps until the server answers.
+    let mut file = File::open("wake:/").unwrap();
+    let mut buf = [0u8; 16];
+    let len = file.read(&mut buf).unwrap();
+    println!(
+        "Client: read woke up with {}",
+        core::str::from_utf8(&buf[..len]).unwrap()
+    );
+    drop(file);
+
+    // A client waiting for a hung server can still be killed.
+    let child = match fork() {
+        0 => {
+            let _ = File::open("wake:/hang");
+            println!("Client: open of a hung server returned");
+            exit(0);
+        }
+        pid if pid > 0 => pid,
+        _ => panic!("Error fork()"),
+    };
+    sleep(Duration::from_millis(100));
+    kill(child as usize, SIGKILL).unwrap();
+    let mut ret: i32 = 0;
+    assert_eq!(waitpid(child, &mut ret, 0), Ok(child as usize));
+    println!("Blocked client exited with code {}", ret);
+
+    io::remove_file("wake:/").unwrap();
+    let mut ret: i32 = 0;
+    wait(0, &mut ret);
+    assert_eq!(ret, 0);
+    println!("Wake tests run OK!");
+}
@@ -36,10 +36,16 @@ pub enum AxError {
     BadFileDescriptor,
     /// Bad internal state.
     BadState,
+    /// The operation failed because the other end of a pipe was closed.
+    BrokenPipe,
     /// The connection was refused by the remote server,
     ConnectionRefused,
+    /// The connection was reset by the remote server.
+    ConnectionReset,
     /// A non-empty directory was specified where an empty directory was expected.
     DirectoryNotEmpty,
+    /// The operation was interrupted, e.g. a blocking call of a task being killed.
+    Interrupted,
     /// Data not valid for the operation were encountered.
     ///
     /// Unlike [`InvalidInput`], this typically means that the operation
@@ -57,6 +63,8 @@ pub enum AxError {
     Io,
     /// The filesystem object is, unexpectedly, a directory.
     IsADirectory,
+    /// The process has no (matching) child process to wait for.
+    NoChildProcess,
     /// Not enough space/cannot allocate memory.
     NoMemory,
     /// A filesystem object is, unexpectedly, not a directory.
@@ -203,11 +211,15 @@ impl From<AxError> for LinuxError {
             AlreadyExists => LinuxError::EEXIST,
             BadAddress | BadState => LinuxError::EFAULT,
             BadFileDescriptor => LinuxError::EBADF,
+            BrokenPipe => LinuxError::EPIPE,
             ConnectionRefused => LinuxError::ECONNREFUSED,
+            ConnectionReset => LinuxError::ECONNRESET,
             DirectoryNotEmpty => LinuxError::ENOTEMPTY,
+            Interrupted => LinuxError::EINTR,
             InvalidInput | InvalidData => LinuxError::EINVAL,
             Io => LinuxError::EIO,
             IsADirectory => LinuxError::EISDIR,
+            NoChildProcess => LinuxError::ECHILD,
             NoMemory => LinuxError::ENOMEM,
             NotADirectory => LinuxError::ENOTDIR,
             NotConnected => LinuxError::ENOTCONN,
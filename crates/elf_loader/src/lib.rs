@@ -2,13 +2,26 @@
 //! An ELF file loader.
 //! It takes an elf file as an input, parses all segments, and output its address info, binary data, and access flags.
 //!
+//! Position-independent (`ET_DYN`) files are loaded at a base chosen by the caller,
+//! with their `R_*_RELATIVE` relocations applied.
+//! Files with an interpreter (`PT_INTERP`) are left to it to be relocated.
+//!
 #![no_std]
 
 #[macro_use]
 extern crate alloc;
 
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
 use memory_addr::VirtAddr;
 use page_table_entry::MappingFlags;
+use xmas_elf::program::Type;
+
+/// `e_type` of position-independent files
+const ET_DYN: u16 = 3;
+/// `p_type` of the segment telling whether the stack is executable
+const PT_GNU_STACK: u32 = 0x6474_e551;
 
 /// Segments of the elf file
 pub struct SegmentEntry<'a> {
@@ -16,27 +29,35 @@ pub struct SegmentEntry<'a> {
     pub start_addr: VirtAddr,
     /// Segment length
     pub size: usize,
-    /// Segment data
-    pub data: &'a [u8],
+    /// Segment data, copied if relocations are applied to it
+    pub data: Cow<'a, [u8]>,
     /// Access Flags (RWX)
     pub flags: MappingFlags,
 }
 
 impl<'a> SegmentEntry<'a> {
-    /// creates the `SegmentEntry` from binary data.   
+    /// creates the `SegmentEntry` from binary data.
     pub fn new(data: &'a [u8]) -> Option<alloc::vec::Vec<SegmentEntry<'a>>> {
+        Self::new_at(data, 0.into())
+    }
+
+    /// creates the `SegmentEntry` from binary data, loaded at `base` if it is position-independent.
+    ///
+    /// `base` is ignored for files loaded at fixed addresses, see [`ElfInfo::base`].
+    pub fn new_at(data: &'a [u8], base: VirtAddr) -> Option<alloc::vec::Vec<SegmentEntry<'a>>> {
         let elf = xmas_elf::ElfFile::new(data).ok()?;
         let elf_header = elf.header;
         let magic = elf_header.pt1.magic;
         if magic != [0x7f, 0x45, 0x4c, 0x46] {
             return None;
         }
+        let base = load_base(data, base);
         let ph_count = elf_header.pt2.ph_count();
         let mut result = vec![];
         for i in 0..ph_count {
             let ph = elf.program_header(i).ok()?;
-            if ph.get_type().ok()? == xmas_elf::program::Type::Load {
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
+            if ph.get_type().ok()? == Type::Load {
+                let start_va: VirtAddr = (base + ph.virtual_addr() as usize).into();
                 let size: usize = ph.mem_size() as usize;
                 let mut flags = MappingFlags::empty();
                 let ph_flags = ph.flags();
@@ -49,14 +70,221 @@ impl<'a> SegmentEntry<'a> {
                 if ph_flags.is_execute() {
                     flags |= MappingFlags::EXECUTE;
                 }
+                let data = elf
+                    .input
+                    .get(ph.offset() as usize..(ph.offset() + ph.file_size()) as usize)?;
                 result.push(SegmentEntry {
                     start_addr: start_va,
                     size,
-                    data: &elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize],
+                    data: Cow::Borrowed(data),
                     flags,
                 });
             }
         }
+        if base != 0 && interpreter(&elf)?.is_none() {
+            for (offset, value) in relocations(&elf, base)? {
+                let vaddr = base + offset;
+                let segment = result.iter_mut().find(|segment| {
+                    segment.start_addr.as_usize() <= vaddr
+                        && vaddr + 8 <= segment.start_addr.as_usize() + segment.size
+                })?;
+                let offset = vaddr - segment.start_addr.as_usize();
+                // The word may be beyond the data in the file, which is zero-filled.
+                let data = segment.data.to_mut();
+                if data.len() < offset + 8 {
+                    data.resize(offset + 8, 0);
+                }
+                data[offset..offset + 8].copy_from_slice(&(value as u64).to_le_bytes());
+            }
+        }
         Some(result)
     }
 }
+
+/// Thread-local storage template of the elf file, from its `PT_TLS` segment
+#[derive(Clone, Copy, Debug)]
+pub struct TlsTemplate {
+    /// Address of the initialized data once loaded
+    pub vaddr: VirtAddr,
+    /// Size of the initialized data
+    pub file_size: usize,
+    /// Size of a TLS block, the rest after the initialized data is zero-filled
+    pub mem_size: usize,
+    /// Alignment of a TLS block
+    pub align: usize,
+}
+
+/// Information of the elf file for its auxiliary vector
+pub struct ElfInfo {
+    /// Address the file is loaded at, `0` if its segments have fixed addresses
+    pub base: VirtAddr,
+    /// Entry point
+    pub entry: VirtAddr,
+    /// Address of the program headers once loaded, `0` if they are not loaded
+    pub phdr: VirtAddr,
+    /// Size of a program header
+    pub phent: usize,
+    /// Number of program headers
+    pub phnum: usize,
+    /// Path of the interpreter (i.e. the dynamic loader) to run the file with
+    pub interp: Option<String>,
+    /// Thread-local storage template
+    pub tls: Option<TlsTemplate>,
+    /// Whether the stack should be executable, as `PT_GNU_STACK` asks
+    pub stack_executable: bool,
+}
+
+impl ElfInfo {
+    /// reads the `ElfInfo` from binary data.
+    pub fn new(data: &[u8]) -> Option<ElfInfo> {
+        Self::new_at(data, 0.into())
+    }
+
+    /// reads the `ElfInfo` from binary data, loaded at `base` if it is position-independent.
+    pub fn new_at(data: &[u8], base: VirtAddr) -> Option<ElfInfo> {
+        let elf = xmas_elf::ElfFile::new(data).ok()?;
+        let base = load_base(data, base);
+        let header = &elf.header.pt2;
+        let phoff = header.ph_offset();
+        let mut phdr = 0;
+        let mut tls = None;
+        let mut stack_executable = false;
+        for ph in elf.program_iter() {
+            match ph.get_type().ok()? {
+                Type::Phdr => phdr = ph.virtual_addr(),
+                // The headers are loaded with the segment containing them.
+                Type::Load
+                    if phdr == 0
+                        && ph.offset() <= phoff
+                        && phoff < ph.offset() + ph.file_size() =>
+                {
+                    phdr = ph.virtual_addr() + phoff - ph.offset();
+                }
+                Type::Tls => {
+                    tls = Some(TlsTemplate {
+                        vaddr: (base + ph.virtual_addr() as usize).into(),
+                        file_size: ph.file_size() as usize,
+                        mem_size: ph.mem_size() as usize,
+                        align: ph.align() as usize,
+                    })
+                }
+                Type::OsSpecific(PT_GNU_STACK) => stack_executable = ph.flags().is_execute(),
+                _ => {}
+            }
+        }
+        Some(ElfInfo {
+            base: base.into(),
+            entry: (base + header.entry_point() as usize).into(),
+            phdr: (if phdr == 0 { 0 } else { base + phdr as usize }).into(),
+            phent: header.ph_entry_size() as usize,
+            phnum: header.ph_count() as usize,
+            interp: interpreter(&elf)?,
+            tls,
+            stack_executable,
+        })
+    }
+}
+
+/// Whether the file is position-independent, i.e. it can be loaded at any base
+pub fn is_relocatable(data: &[u8]) -> bool {
+    data.get(16..18) == Some(&ET_DYN.to_le_bytes()[..])
+}
+
+/// `base` for position-independent files, `0` for others
+fn load_base(data: &[u8], base: VirtAddr) -> usize {
+    if is_relocatable(data) {
+        base.as_usize()
+    } else {
+        0
+    }
+}
+
+/// The path in the `PT_INTERP` segment
+fn interpreter(elf: &xmas_elf::ElfFile) -> Option<Option<String>> {
+    for ph in elf.program_iter() {
+        if ph.get_type().ok()? == Type::Interp {
+            let path = elf
+                .input
+                .get(ph.offset() as usize..(ph.offset() + ph.file_size()) as usize)?;
+            let path = path.split(|c| *c == 0).next()?;
+            return Some(Some(String::from(core::str::from_utf8(path).ok()?)));
+        }
+    }
+    Some(None)
+}
+
+/// `r_type` of `R_*_RELATIVE` relocations of the machine
+fn relative_type(machine: u16) -> Option<u32> {
+    match machine {
+        // x86_64
+        62 => Some(8),
+        // AArch64
+        183 => Some(1027),
+        // RISC-V
+        243 => Some(3),
+        _ => None,
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// `R_*_RELATIVE` relocations of a little-endian 64-bit file loaded at `base`,
+/// as offsets (from `base`) of the words to write and their values.
+///
+/// Other relocations are left to the program.
+fn relocations(elf: &xmas_elf::ElfFile, base: usize) -> Option<Vec<(usize, usize)>> {
+    const DT_NULL: u64 = 0;
+    const DT_RELA: u64 = 7;
+    const DT_RELASZ: u64 = 8;
+    const DT_RELAENT: u64 = 9;
+    let data = elf.input;
+    // ELFCLASS64, ELFDATA2LSB
+    if data.get(4..6) != Some(&[2, 1][..]) {
+        return None;
+    }
+    let machine = u16::from_le_bytes(data.get(18..20)?.try_into().ok()?);
+    let Some(dynamic) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(Type::Dynamic))
+    else {
+        return Some(Vec::new());
+    };
+    let (mut rela, mut rela_size, mut rela_ent) = (0, 0, 24);
+    let mut offset = dynamic.offset() as usize;
+    let end = offset + dynamic.file_size() as usize;
+    while offset + 16 <= end {
+        let (tag, value) = (read_u64(data, offset)?, read_u64(data, offset + 8)?);
+        match tag {
+            DT_NULL => break,
+            DT_RELA => rela = value,
+            DT_RELASZ => rela_size = value as usize,
+            DT_RELAENT => rela_ent = value as usize,
+            _ => {}
+        }
+        offset += 16;
+    }
+    if rela_size == 0 {
+        return Some(Vec::new());
+    }
+    let relative = relative_type(machine)?;
+    // `DT_RELA` is an address, found in the file through the segment loading it.
+    let rela_offset = elf.program_iter().find_map(|ph| {
+        (ph.get_type() == Ok(Type::Load)
+            && ph.virtual_addr() <= rela
+            && rela < ph.virtual_addr() + ph.file_size())
+        .then(|| (rela - ph.virtual_addr() + ph.offset()) as usize)
+    })?;
+    let mut result = Vec::new();
+    for entry in (rela_offset..rela_offset + rela_size).step_by(rela_ent.max(24)) {
+        let (r_offset, r_info) = (read_u64(data, entry)?, read_u64(data, entry + 8)?);
+        let addend = read_u64(data, entry + 16)? as usize;
+        if r_info as u32 == relative {
+            result.push((r_offset as usize, base.wrapping_add(addend)));
+        }
+    }
+    Some(result)
+}
@@ -9,11 +9,11 @@
 pub struct Packet {
     /// syscall id
     pub id: u64,
-    /// process id (not used)
+    /// id of the process making the request
     pub pid: usize,
-    /// user id (not used)
+    /// effective user id of the process, set by the kernel
     pub uid: u32,
-    /// group id (not used)
+    /// effective group id of the process, set by the kernel
     pub gid: u32,
     /// param 1
     pub a: usize,
@@ -48,15 +48,92 @@ impl DerefMut for Packet {
     }
 }
 
+/// Parameters of `fmap` syscall
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct Map {
+    /// offset in the file
+    pub offset: usize,
+    /// size of the mapping in bytes
+    pub size: usize,
+    /// protection and sharing flags
+    pub flags: MapFlags,
+    /// desired address, only meaningful with `MAP_FIXED`
+    pub address: usize,
+}
+
+impl Deref for Map {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self as *const Map as *const u8,
+                core::mem::size_of::<Map>(),
+            )
+        }
+    }
+}
+
+bitflags! {
+    /// readiness of a file, used by `fevent`
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct EventFlags: usize {
+        /// the file can be read without blocking
+        const EVENT_READ = 1 << 0;
+        /// the file can be written without blocking
+        const EVENT_WRITE = 1 << 1;
+    }
+}
+
+/// Items read from (and written to) the `event:` scheme
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct Event {
+    /// file descriptor
+    pub id: usize,
+    /// ready (or watched) events
+    pub flags: EventFlags,
+    /// user data given on registration
+    pub data: usize,
+}
+
 /// return type of `stat` syscall, NOT in UNIX format
 pub type Stat = axfs_vfs::VfsNodeAttr;
 
+/// return type of `fstatvfs` syscall
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct StatVfs {
+    /// block size in bytes
+    pub f_bsize: u32,
+    /// total number of blocks
+    pub f_blocks: u64,
+    /// number of free blocks
+    pub f_bfree: u64,
+    /// number of blocks available to unprivileged users
+    pub f_bavail: u64,
+}
+
+/// timestamp used by `futimens`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct TimeSpec {
+    /// seconds
+    pub tv_sec: i64,
+    /// nanoseconds
+    pub tv_nsec: i32,
+}
+
 unsafe fn str_from_raw_parts(ptr: *const u8, len: usize) -> Option<&'static str> {
     let slice = core::slice::from_raw_parts(ptr, len);
     core::str::from_utf8(slice).ok()
 }
 
 mod scheme;
+mod scheme_block;
+use bitflags::bitflags;
 use core::ops::{Deref, DerefMut};
 
 pub use crate::scheme::Scheme;
+pub use crate::scheme_block::SchemeBlock;
+pub use syscall_number::mmap::MapFlags;
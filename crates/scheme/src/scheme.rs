@@ -1,7 +1,7 @@
 use core::slice;
 
-use crate::Stat;
 use crate::{str_from_raw_parts, Packet};
+use crate::{EventFlags, Map, Stat, StatVfs, TimeSpec};
 use axerrno::to_ret_code;
 use axerrno::AxError as Error;
 use axerrno::AxResult as Result;
@@ -28,6 +28,13 @@ pub trait Scheme {
                     Err(Error::InvalidData)
                 }
             }
+            SYS_CHMOD => {
+                if let Some(path) = unsafe { str_from_raw_parts(packet.b as *const u8, packet.c) } {
+                    self.chmod(path, packet.d as u16, packet.uid, packet.gid)
+                } else {
+                    Err(Error::InvalidData)
+                }
+            }
             SYS_UNLINK => {
                 if let Some(path) = unsafe { str_from_raw_parts(packet.b as *const u8, packet.c) } {
                     self.unlink(path, packet.uid, packet.gid)
@@ -51,19 +58,17 @@ pub trait Scheme {
             SYS_FCHMOD => self.fchmod(packet.b, packet.c as u16),
             SYS_FCHOWN => self.fchown(packet.b, packet.c as u32, packet.d as u32),
             SYS_FCNTL => self.fcntl(packet.b, packet.c, packet.d),
-            // SYS_FEVENT => self.fevent(packet.b, EventFlags::from_bits_truncate(packet.c)).map(|f| f.bits()),
-            // SYS_FMAP_OLD => if packet.d >= mem::size_of::<OldMap>() {
-            //     self.fmap_old(packet.b, unsafe { &*(packet.c as *const OldMap) })
-            // } else {
-            //     Err(Error::new(EFAULT))
-            // },
-            // SYS_FMAP => if packet.d >= mem::size_of::<Map>() {
-            //     self.fmap(packet.b, unsafe { &*(packet.c as *const Map) })
-            // } else {
-            //     Err(Error::new(EFAULT))
-            // },
-            // SYS_FUNMAP_OLD => self.funmap_old(packet.b),
-            // SYS_FUNMAP => self.funmap(packet.b, packet.c),
+            SYS_FEVENT => self
+                .fevent(packet.b, EventFlags::from_bits_truncate(packet.c))
+                .map(|f| f.bits()),
+            SYS_FMAP => {
+                if packet.d >= core::mem::size_of::<Map>() {
+                    self.fmap(packet.b, unsafe { &*(packet.c as *const Map) })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
+            SYS_FUNMAP => self.funmap(packet.b, packet.c),
             SYS_FPATH => self.fpath(packet.b, unsafe {
                 slice::from_raw_parts_mut(packet.c as *mut u8, packet.d)
             }),
@@ -81,18 +86,27 @@ pub trait Scheme {
                     Err(Error::BadAddress)
                 }
             }
-            // SYS_FSTATVFS => if packet.d >= mem::size_of::<StatVfs>() {
-            //     self.fstatvfs(packet.b, unsafe { &mut *(packet.c as *mut StatVfs) })
-            // } else {
-            //     Err(Error::BadAddress)
-            // },
+            SYS_FSTATVFS => {
+                if packet.d >= core::mem::size_of::<StatVfs>() {
+                    self.fstatvfs(packet.b, unsafe { &mut *(packet.c as *mut StatVfs) })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
             SYS_FSYNC => self.fsync(packet.b),
             SYS_FTRUNCATE => self.ftruncate(packet.b, packet.c),
-            // SYS_FUTIMENS => if packet.d >= mem::size_of::<TimeSpec>() {
-            //     self.futimens(packet.b, unsafe { slice::from_raw_parts(packet.c as *const TimeSpec, packet.d / mem::size_of::<TimeSpec>()) })
-            // } else {
-            //     Err(Error::BadAddress)
-            // },
+            SYS_FUTIMENS => {
+                if packet.d >= core::mem::size_of::<TimeSpec>() {
+                    self.futimens(packet.b, unsafe {
+                        slice::from_raw_parts(
+                            packet.c as *const TimeSpec,
+                            packet.d / core::mem::size_of::<TimeSpec>(),
+                        )
+                    })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
             SYS_CLOSE => self.close(packet.b),
             _ => Err(Error::BadFileDescriptor),
         };
@@ -170,38 +184,28 @@ pub trait Scheme {
     fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> Result<usize> {
         Err(Error::BadFileDescriptor)
     }
-    /*
-       #[allow(unused_variables)]
-       fn fevent(&self, id: usize, flags: EventFlags) -> Result<EventFlags> {
-           Err(Error::BadFileDescriptor)
-       }
-
-       #[allow(unused_variables)]
-       fn fmap_old(&self, id: usize, map: &OldMap) -> Result<usize> {
-           Err(Error::BadFileDescriptor)
-       }
-       #[allow(unused_variables)]
-       fn fmap(&self, id: usize, map: &Map) -> Result<usize> {
-           if map.flags.contains(MapFlags::MAP_FIXED) {
-               return Err(Error::new(EINVAL));
-           }
-           self.fmap_old(id, &OldMap {
-               offset: map.offset,
-               size: map.size,
-               flags: map.flags,
-           })
-       }
-
-       #[allow(unused_variables)]
-       fn funmap_old(&self, address: usize) -> Result<usize> {
-           Ok(0)
-       }
-
-       #[allow(unused_variables)]
-       fn funmap(&self, address: usize, length: usize) -> Result<usize> {
-           Ok(0)
-       }
-    */
+    /// `fevent` syscall
+    /// sets the events of `id` to watch, returns the ones that are ready now.
+    /// Later readiness is posted with `SYS_FEVENT` packets.
+    #[allow(unused_variables)]
+    fn fevent(&self, id: usize, flags: EventFlags) -> Result<EventFlags> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fmap` syscall
+    /// returns the address of the mapped memory in the address space of the scheme,
+    /// which must stay valid until `funmap` is called with it.
+    #[allow(unused_variables)]
+    fn fmap(&self, id: usize, map: &Map) -> Result<usize> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `funmap` syscall
+    /// `address` is the one returned by `fmap`.
+    #[allow(unused_variables)]
+    fn funmap(&self, address: usize, length: usize) -> Result<usize> {
+        Ok(0)
+    }
 
     /// `fpath` syscall
     #[allow(unused_variables)]
@@ -220,12 +224,12 @@ pub trait Scheme {
     fn fstat(&self, id: usize, stat: &mut Stat) -> Result<usize> {
         Err(Error::BadFileDescriptor)
     }
-    /*
-       #[allow(unused_variables)]
-       fn fstatvfs(&self, id: usize, stat: &mut StatVfs) -> Result<usize> {
-           Err(Error::BadFileDescriptor)
-       }
-    */
+
+    /// `fstatvfs` syscall
+    #[allow(unused_variables)]
+    fn fstatvfs(&self, id: usize, stat: &mut StatVfs) -> Result<usize> {
+        Err(Error::BadFileDescriptor)
+    }
 
     /// `fsync` syscall
     #[allow(unused_variables)]
@@ -238,12 +242,12 @@ pub trait Scheme {
     fn ftruncate(&self, id: usize, len: usize) -> Result<usize> {
         Err(Error::BadFileDescriptor)
     }
-    /*
-       #[allow(unused_variables)]
-       fn futimens(&self, id: usize, times: &[TimeSpec]) -> Result<usize> {
-           Err(Error::BadFileDescriptor)
-       }
-    */
+
+    /// `futimens` syscall
+    #[allow(unused_variables)]
+    fn futimens(&self, id: usize, times: &[TimeSpec]) -> Result<usize> {
+        Err(Error::BadFileDescriptor)
+    }
 
     /// `close` syscall
     #[allow(unused_variables)]
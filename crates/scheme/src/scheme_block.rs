@@ -0,0 +1,269 @@
+use core::slice;
+
+use crate::{str_from_raw_parts, Packet, Scheme};
+use crate::{EventFlags, Map, Stat, StatVfs, TimeSpec};
+use axerrno::to_ret_code;
+use axerrno::AxError as Error;
+use axerrno::AxResult as Result;
+use core::result::Result::Err;
+
+/// SchemeBlock: service handlers that may leave a request pending
+///
+/// A method returns `Ok(None)` if the request cannot be completed now.
+/// The server answers it later by writing a `Packet` with the same `id`.
+/// From <https://gitlab.redox-os.org/redox-os/syscall/-/blob/master/src/scheme/scheme_block.rs>
+pub trait SchemeBlock {
+    /// Do not reimplement. dispatch all `Packet`s to different handlers,
+    /// returns the value to be put in `packet.a` of the response, or `None` if it is pending.
+    fn handle(&self, packet: &Packet) -> Option<usize> {
+        use syscall_number::*;
+        let res = match packet.a {
+            SYS_OPEN => {
+                if let Some(path) = unsafe { str_from_raw_parts(packet.b as *const u8, packet.c) } {
+                    self.open(path, packet.d, packet.uid, packet.gid)
+                } else {
+                    Err(Error::InvalidData)
+                }
+            }
+            SYS_CHMOD => {
+                if let Some(path) = unsafe { str_from_raw_parts(packet.b as *const u8, packet.c) } {
+                    self.chmod(path, packet.d as u16, packet.uid, packet.gid)
+                } else {
+                    Err(Error::InvalidData)
+                }
+            }
+            SYS_RMDIR => {
+                if let Some(path) = unsafe { str_from_raw_parts(packet.b as *const u8, packet.c) } {
+                    self.rmdir(path, packet.uid, packet.gid)
+                } else {
+                    Err(Error::InvalidData)
+                }
+            }
+            SYS_UNLINK => {
+                if let Some(path) = unsafe { str_from_raw_parts(packet.b as *const u8, packet.c) } {
+                    self.unlink(path, packet.uid, packet.gid)
+                } else {
+                    Err(Error::InvalidData)
+                }
+            }
+
+            SYS_DUP => self.dup(packet.b, unsafe {
+                slice::from_raw_parts(packet.c as *const u8, packet.d)
+            }),
+            SYS_READ => self.read(packet.b, unsafe {
+                slice::from_raw_parts_mut(packet.c as *mut u8, packet.d)
+            }),
+            SYS_WRITE => self.write(packet.b, unsafe {
+                slice::from_raw_parts(packet.c as *const u8, packet.d)
+            }),
+            SYS_LSEEK => self
+                .seek(packet.b, packet.c as isize, packet.d)
+                .map(|o| o.map(|o| o as usize)),
+            SYS_FCHMOD => self.fchmod(packet.b, packet.c as u16),
+            SYS_FCHOWN => self.fchown(packet.b, packet.c as u32, packet.d as u32),
+            SYS_FCNTL => self.fcntl(packet.b, packet.c, packet.d),
+            SYS_FEVENT => self
+                .fevent(packet.b, EventFlags::from_bits_truncate(packet.c))
+                .map(|f| f.map(|f| f.bits())),
+            SYS_FMAP => {
+                if packet.d >= core::mem::size_of::<Map>() {
+                    self.fmap(packet.b, unsafe { &*(packet.c as *const Map) })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
+            SYS_FUNMAP => self.funmap(packet.b, packet.c),
+            SYS_FPATH => self.fpath(packet.b, unsafe {
+                slice::from_raw_parts_mut(packet.c as *mut u8, packet.d)
+            }),
+            SYS_FRENAME => {
+                if let Some(path) = unsafe { str_from_raw_parts(packet.c as *const u8, packet.d) } {
+                    self.frename(packet.b, path, packet.uid, packet.gid)
+                } else {
+                    Err(Error::InvalidData)
+                }
+            }
+            SYS_FSTAT => {
+                if packet.d >= core::mem::size_of::<Stat>() {
+                    self.fstat(packet.b, unsafe { &mut *(packet.c as *mut Stat) })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
+            SYS_FSTATVFS => {
+                if packet.d >= core::mem::size_of::<StatVfs>() {
+                    self.fstatvfs(packet.b, unsafe { &mut *(packet.c as *mut StatVfs) })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
+            SYS_FSYNC => self.fsync(packet.b),
+            SYS_FTRUNCATE => self.ftruncate(packet.b, packet.c),
+            SYS_FUTIMENS => {
+                if packet.d >= core::mem::size_of::<TimeSpec>() {
+                    self.futimens(packet.b, unsafe {
+                        slice::from_raw_parts(
+                            packet.c as *const TimeSpec,
+                            packet.d / core::mem::size_of::<TimeSpec>(),
+                        )
+                    })
+                } else {
+                    Err(Error::BadAddress)
+                }
+            }
+            SYS_CLOSE => self.close(packet.b),
+            _ => Err(Error::BadFileDescriptor),
+        };
+
+        match res {
+            Ok(Some(value)) => Some(to_ret_code(Ok(value)) as usize),
+            Ok(None) => None,
+            Err(e) => Some(to_ret_code(Err(e)) as usize),
+        }
+    }
+
+    /* Scheme operations */
+
+    /// `open` syscall
+    #[allow(unused_variables)]
+    fn open(&self, path: &str, flags: usize, uid: u32, gid: u32) -> Result<Option<usize>> {
+        Err(Error::NotFound)
+    }
+
+    /// `chmod` syscall
+    #[allow(unused_variables)]
+    fn chmod(&self, path: &str, mode: u16, uid: u32, gid: u32) -> Result<Option<usize>> {
+        Err(Error::NotFound)
+    }
+
+    /// `rmdir` syscall
+    #[allow(unused_variables)]
+    fn rmdir(&self, path: &str, uid: u32, gid: u32) -> Result<Option<usize>> {
+        Err(Error::NotFound)
+    }
+
+    /// `unlink` syscall
+    #[allow(unused_variables)]
+    fn unlink(&self, path: &str, uid: u32, gid: u32) -> Result<Option<usize>> {
+        Err(Error::NotFound)
+    }
+
+    /* Resource operations */
+
+    /// `dup` syscall, see [`Scheme::dup`]
+    #[allow(unused_variables)]
+    fn dup(&self, old_id: usize, buf: &[u8]) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `read` syscall
+    #[allow(unused_variables)]
+    fn read(&self, id: usize, buf: &mut [u8]) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `write` syscall
+    #[allow(unused_variables)]
+    fn write(&self, id: usize, buf: &[u8]) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `seek` syscall
+    #[allow(unused_variables)]
+    fn seek(&self, id: usize, pos: isize, whence: usize) -> Result<Option<isize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fchmod` syscall
+    #[allow(unused_variables)]
+    fn fchmod(&self, id: usize, mode: u16) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fchown` syscall
+    #[allow(unused_variables)]
+    fn fchown(&self, id: usize, uid: u32, gid: u32) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fcntl` syscall
+    #[allow(unused_variables)]
+    fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fevent` syscall, see [`Scheme::fevent`]
+    #[allow(unused_variables)]
+    fn fevent(&self, id: usize, flags: EventFlags) -> Result<Option<EventFlags>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fmap` syscall, see [`Scheme::fmap`]
+    #[allow(unused_variables)]
+    fn fmap(&self, id: usize, map: &Map) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `funmap` syscall
+    #[allow(unused_variables)]
+    fn funmap(&self, address: usize, length: usize) -> Result<Option<usize>> {
+        Ok(Some(0))
+    }
+
+    /// `fpath` syscall
+    #[allow(unused_variables)]
+    fn fpath(&self, id: usize, buf: &mut [u8]) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `frename` syscall
+    #[allow(unused_variables)]
+    fn frename(&self, id: usize, path: &str, uid: u32, gid: u32) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fstat` syscall
+    #[allow(unused_variables)]
+    fn fstat(&self, id: usize, stat: &mut Stat) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fstatvfs` syscall
+    #[allow(unused_variables)]
+    fn fstatvfs(&self, id: usize, stat: &mut StatVfs) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `fsync` syscall
+    #[allow(unused_variables)]
+    fn fsync(&self, id: usize) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `ftruncate` syscall
+    #[allow(unused_variables)]
+    fn ftruncate(&self, id: usize, len: usize) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `futimens` syscall
+    #[allow(unused_variables)]
+    fn futimens(&self, id: usize, times: &[TimeSpec]) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+
+    /// `close` syscall
+    #[allow(unused_variables)]
+    fn close(&self, id: usize) -> Result<Option<usize>> {
+        Err(Error::BadFileDescriptor)
+    }
+}
+
+/// Every [`Scheme`] is a [`SchemeBlock`] that never leaves a request pending.
+impl<T: Scheme> SchemeBlock for T {
+    fn handle(&self, packet: &Packet) -> Option<usize> {
+        let mut packet = *packet;
+        Scheme::handle(self, &mut packet);
+        Some(packet.a)
+    }
+}
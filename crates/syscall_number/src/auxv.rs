@@ -0,0 +1,17 @@
+//! Keys of the auxiliary vector, put on the initial user stack after the environment
+//! variables of a program as `(key, value)` pairs, ended by [`AT_NULL`].
+
+/// end of the vector
+pub const AT_NULL: usize = 0;
+/// address of the program headers of the program
+pub const AT_PHDR: usize = 3;
+/// size of a program header
+pub const AT_PHENT: usize = 4;
+/// number of program headers
+pub const AT_PHNUM: usize = 5;
+/// size of a page
+pub const AT_PAGESZ: usize = 6;
+/// base address of the interpreter (i.e. the dynamic loader) of the program
+pub const AT_BASE: usize = 7;
+/// entry point of the program
+pub const AT_ENTRY: usize = 9;
@@ -1,6 +1,8 @@
 use bitflags::bitflags;
 
 bitflags! {
+    /// flags of `open`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct OpenFlags: usize {
         /// O_APPEND
         const APPEND = 1 << 0;
@@ -16,9 +18,22 @@ bitflags! {
         const DIRECTORY = 1 << 5;
         /// O_EXCL
         const EXCL = 1 << 6;
+        /// O_NONBLOCK
+        const NONBLOCK = 1 << 7;
+        /// O_CLOEXEC
+        const CLOEXEC = 1 << 8;
     }
 }
 
 pub const SEEK_SET: usize = 0;
 pub const SEEK_CUR: usize = 1;
 pub const SEEK_END: usize = 2;
+
+// `fcntl` commands
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+
+/// File descriptor flag: close the file on `exec`
+pub const FD_CLOEXEC: usize = 1;
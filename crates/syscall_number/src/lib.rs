@@ -3,16 +3,53 @@
 #![no_std]
 #![allow(missing_docs)]
 
+/// exit: code, ends only the calling thread (the process exits with it if it is the last one)
 pub const SYS_EXIT: usize = 10;
+/// spawn: entry, argument, user stack size (`0` for the default)
 pub const SYS_SPAWN: usize = 11;
 pub const SYS_YIELD: usize = 12;
 pub const SYS_SLEEP: usize = 13;
 pub const SYS_TIME_NANO: usize = 14;
+/// exit_group: code, ends all threads of the process
+pub const SYS_EXIT_GROUP: usize = 15;
 pub const SYS_SBRK: usize = 20;
+/// mmap: address (a hint, or exact with `MAP_FIXED`), length, flags (see [`mmap::MapFlags`])
+pub const SYS_MMAP: usize = 21;
+/// munmap: address, length
+pub const SYS_MUNMAP: usize = 22;
+/// mprotect: address, length, flags (`PROT_*` of [`mmap::MapFlags`])
+pub const SYS_MPROTECT: usize = 23;
 pub const SYS_FUTEX: usize = 30;
 pub const SYS_FORK: usize = 40;
+/// wait: pid (`-1` or `0` for any child), status ptr, options (see [`wait`])
 pub const SYS_WAIT: usize = 41;
+/// exec: ELF ptr, ELF len, args ptr, args len, envs ptr, envs len (strings each ended by `\0`)
 pub const SYS_EXEC: usize = 42;
+pub const SYS_GETPID: usize = 43;
+/// execve: path ptr, path len, args ptr, args len, envs ptr, envs len (strings each ended by `\0`)
+pub const SYS_EXECVE: usize = 44;
+/// mkns: names ptr, names len (separated by `\n`)
+pub const SYS_MKNS: usize = 50;
+/// setns: namespace
+pub const SYS_SETNS: usize = 51;
+/// pipe2: fds ptr (`[usize; 2]`, read end and write end), flags
+pub const SYS_PIPE2: usize = 52;
+pub const SYS_GETUID: usize = 60;
+pub const SYS_GETEUID: usize = 61;
+pub const SYS_GETGID: usize = 62;
+pub const SYS_GETEGID: usize = 63;
+/// setuid: uid
+pub const SYS_SETUID: usize = 64;
+/// setgid: gid
+pub const SYS_SETGID: usize = 65;
+/// kill: pid, signal (`0` only checks the process)
+pub const SYS_KILL: usize = 70;
+/// sigaction: signal, new action ptr (or null), old action ptr (or null)
+pub const SYS_SIGACTION: usize = 71;
+/// sigprocmask: how, new mask ptr (or null), old mask ptr (or null)
+pub const SYS_SIGPROCMASK: usize = 72;
+/// sigreturn: restores the context saved on the user stack before calling a signal handler
+pub const SYS_SIGRETURN: usize = 73;
 
 // ============
 // The following are from [redox](https://gitlab.redox-os.org/redox-os/syscall/-/blob/master/src/number.rs),
@@ -30,6 +67,7 @@ pub const SYS_ARG_PATH: usize = 0x0300_0000;
 pub const SYS_RET: usize = 0x00F0_0000;
 pub const SYS_RET_FILE: usize = 0x0010_0000;
 
+pub const SYS_CHMOD: usize = SYS_CLASS_PATH | 15;
 pub const SYS_LINK: usize = SYS_CLASS_PATH | SYS_ARG_PATH | 9;
 /// open: path ptr, path len, option
 pub const SYS_OPEN: usize = SYS_CLASS_PATH | SYS_RET_FILE | 5;
@@ -61,5 +99,9 @@ pub const SYS_FUTIMENS: usize = SYS_CLASS_FILE | SYS_ARG_SLICE | 320;
 // ===========
 
 // We do not use features, because these constants will cause little side effects.
+pub mod auxv;
 pub mod futex;
 pub mod io;
+pub mod mmap;
+pub mod signal;
+pub mod wait;
@@ -0,0 +1,20 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// flags of `fmap` requests and `mmap`
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct MapFlags: usize {
+        /// PROT_READ
+        const PROT_READ = 1 << 0;
+        /// PROT_WRITE
+        const PROT_WRITE = 1 << 1;
+        /// PROT_EXEC
+        const PROT_EXEC = 1 << 2;
+        /// MAP_SHARED: changes are written back to the underlying object
+        const MAP_SHARED = 1 << 3;
+        /// MAP_PRIVATE: changes are private to the mapping
+        const MAP_PRIVATE = 1 << 4;
+        /// MAP_FIXED: map exactly at `address`
+        const MAP_FIXED = 1 << 5;
+    }
+}
@@ -0,0 +1,67 @@
+//! Signal numbers and `sigaction` (the same as Linux)
+
+/// Signals are numbered from 1 to `NSIG - 1`.
+pub const NSIG: usize = 64;
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGSTKFLT: usize = 16;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+pub const SIGURG: usize = 23;
+pub const SIGXCPU: usize = 24;
+pub const SIGXFSZ: usize = 25;
+pub const SIGVTALRM: usize = 26;
+pub const SIGPROF: usize = 27;
+pub const SIGWINCH: usize = 28;
+pub const SIGIO: usize = 29;
+pub const SIGPWR: usize = 30;
+pub const SIGSYS: usize = 31;
+
+/// `handler` of the default action
+pub const SIG_DFL: usize = 0;
+/// `handler` to ignore the signal
+pub const SIG_IGN: usize = 1;
+
+/// do not block the signal while its handler runs
+pub const SA_NODEFER: usize = 0x4000_0000;
+/// reset the action to default before calling the handler
+pub const SA_RESETHAND: usize = 0x8000_0000;
+
+/// `how` of `sigprocmask`: add signals to the blocked set
+pub const SIG_BLOCK: usize = 0;
+/// `how` of `sigprocmask`: remove signals from the blocked set
+pub const SIG_UNBLOCK: usize = 1;
+/// `how` of `sigprocmask`: replace the blocked set
+pub const SIG_SETMASK: usize = 2;
+
+/// Action taken on a signal
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SigAction {
+    /// [`SIG_DFL`], [`SIG_IGN`] or the address of `extern "C" fn(signal: usize)`
+    pub handler: usize,
+    /// `SA_*` flags
+    pub flags: usize,
+    /// where the handler returns to, which must call `sigreturn`
+    pub restorer: usize,
+    /// signals blocked while the handler runs (bit `n` for signal `n`)
+    pub mask: u64,
+}
@@ -0,0 +1,2 @@
+/// return immediately if no child has exited
+pub const WNOHANG: usize = 1;
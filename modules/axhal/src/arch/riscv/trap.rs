@@ -14,6 +14,22 @@ fn handle_breakpoint(sepc: &mut usize) {
     *sepc += 2
 }
 
+#[cfg(feature = "user")]
+fn user_fault(e: E) -> Option<crate::trap::UserFault> {
+    use crate::trap::UserFault;
+    match e {
+        E::InstructionFault | E::LoadFault | E::InstructionPageFault | E::LoadPageFault => {
+            Some(UserFault::PageFault { write: false })
+        }
+        E::StoreFault | E::StorePageFault => Some(UserFault::PageFault { write: true }),
+        E::IllegalInstruction => Some(UserFault::IllegalInstruction),
+        E::InstructionMisaligned | E::LoadMisaligned | E::StoreMisaligned => {
+            Some(UserFault::Misaligned)
+        }
+        _ => None,
+    }
+}
+
 #[no_mangle]
 fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     #[cfg(feature = "user-paging")]
@@ -56,6 +72,25 @@ fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
             tf.regs.a0 = ret as usize;
         }
 
+        #[cfg(feature = "user")]
+        Trap::Exception(e) if from_user && user_fault(e).is_some() => {
+            let fault = user_fault(e).unwrap();
+            // `stval` has the instruction itself for illegal instructions.
+            let addr = match fault {
+                crate::trap::UserFault::IllegalInstruction => tf.sepc,
+                _ => riscv::register::stval::read(),
+            };
+            let resolved = match fault {
+                crate::trap::UserFault::PageFault { write } => {
+                    crate::trap::handle_page_fault_extern(addr, write)
+                }
+                _ => false,
+            };
+            if !resolved {
+                crate::trap::handle_user_fault_extern(fault, addr, tf.sepc);
+            }
+        }
+
         _ => {
             panic!(
                 "Unhandled trap {:?} (stval = {:x}, from user: {}) @ {:#x}:\n{:#x?}",
@@ -70,6 +105,7 @@ fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
 
     #[cfg(feature = "user-paging")]
     if from_user {
+        crate::trap::handle_signal_extern(tf);
         let tf = crate::trap::get_current_trap_frame_virt_addr();
         let satp = crate::trap::get_current_satp();
         enter_uspace(tf, satp);
@@ -17,7 +17,36 @@ pub trait TrapHandler {
     /// Handles syscalls
     #[cfg(feature = "user")]
     fn handle_syscall(id: usize, params: [usize; 6]) -> isize;
-    // more e.g.: handle_page_fault();
+
+    /// Handles pending signals before returning to user space
+    #[cfg(feature = "user")]
+    fn handle_signal(tf: &mut TrapFrame);
+
+    /// Handles a fault of user code at `pc`, `addr` is the faulting address
+    /// (`pc` for illegal instructions).
+    /// The faulting instruction is retried if this returns.
+    #[cfg(feature = "user")]
+    fn handle_user_fault(fault: UserFault, addr: usize, pc: usize);
+
+    /// Handles a page fault of user code at `vaddr`, e.g. by allocating a frame for it.
+    /// Returns whether the access can be retried, otherwise it is a user fault.
+    #[cfg(feature = "user")]
+    fn handle_page_fault(vaddr: usize, write: bool) -> bool;
+}
+
+/// Faults caused by user code
+#[cfg(feature = "user")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserFault {
+    /// Access to an unmapped address, or without permission
+    PageFault {
+        /// whether it is a store
+        write: bool,
+    },
+    /// Illegal (or privileged) instruction
+    IllegalInstruction,
+    /// Misaligned access
+    Misaligned,
 }
 
 /// Call the external IRQ handler.
@@ -33,7 +62,28 @@ pub(crate) fn handle_syscall_extern(syscall_num: usize, param: [usize; 6]) -> is
     call_interface!(TrapHandler::handle_syscall, syscall_num, param)
 }
 
-#[cfg(feature = "user-paging")]
+/// Call the external user fault handler.
+#[cfg(feature = "user")]
+#[allow(dead_code)]
+pub(crate) fn handle_user_fault_extern(fault: UserFault, addr: usize, pc: usize) {
+    call_interface!(TrapHandler::handle_user_fault, fault, addr, pc)
+}
+
+/// Call the external page fault handler.
+#[cfg(feature = "user")]
+#[allow(dead_code)]
+pub(crate) fn handle_page_fault_extern(vaddr: usize, write: bool) -> bool {
+    call_interface!(TrapHandler::handle_page_fault, vaddr, write)
+}
+
+/// Call the external signal handler.
+#[cfg(feature = "user")]
+#[allow(dead_code)]
+pub(crate) fn handle_signal_extern(tf: &mut TrapFrame) {
+    call_interface!(TrapHandler::handle_signal, tf)
+}
+
+#[cfg(feature = "user")]
 use crate::arch::TrapFrame;
 /// Task Infomation Interface
 ///
@@ -55,9 +105,9 @@ pub trait CurrentTask {
     fn current_trap_frame_virt_addr() -> usize;
 }
 
-#[allow(unused)]
+/// Gets `TrapFrame` of current task, e.g. to change the user context in a syscall
 #[cfg(feature = "user-paging")]
-pub(crate) fn get_current_trap_frame() -> *mut TrapFrame {
+pub fn get_current_trap_frame() -> *mut TrapFrame {
     call_interface!(CurrentTask::current_trap_frame)
 }
 #[allow(unused)]
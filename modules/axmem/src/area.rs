@@ -0,0 +1,225 @@
+//! Areas of user address spaces, whose frames are allocated on the first access
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use axalloc::GlobalPage;
+use axerrno::AxResult;
+use axhal::{
+    mem::{phys_to_virt, virt_to_phys},
+    paging::MappingFlags,
+};
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use memory_addr::{PhysAddr, VirtAddr, PAGE_SIZE_4K};
+
+/// A frame of user pages, shared copy-on-write by forked address spaces
+pub(crate) struct Frame {
+    page: GlobalPage,
+    /// number of [`LentFrame`]s of it, which do not make it copy-on-write
+    loans: AtomicUsize,
+}
+
+impl Frame {
+    pub(crate) fn new(page: GlobalPage) -> Arc<Self> {
+        Arc::new(Frame {
+            page,
+            loans: AtomicUsize::new(0),
+        })
+    }
+
+    pub(crate) fn paddr(&self) -> PhysAddr {
+        self.page.start_paddr(virt_to_phys)
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        self.page.as_slice()
+    }
+
+    /// whether it is lent to other address spaces (or the kernel) at the moment
+    pub(crate) fn lent(&self) -> bool {
+        self.loans.load(Ordering::Acquire) > 0
+    }
+}
+
+/// A frame lent to another address space, or used by the kernel for a while.
+///
+/// User frames are kept alive as long as a `LentFrame` of them is, without
+/// being shared copy-on-write: the lender and the borrowers see the same data.
+pub struct LentFrame {
+    paddr: PhysAddr,
+    frame: Option<Arc<Frame>>,
+}
+
+impl LentFrame {
+    pub(crate) fn new(frame: &Arc<Frame>) -> Self {
+        frame.loans.fetch_add(1, Ordering::AcqRel);
+        LentFrame {
+            paddr: frame.paddr(),
+            frame: Some(frame.clone()),
+        }
+    }
+
+    /// a frame of kernel memory (e.g. of a device), which is never freed
+    pub fn unowned(paddr: PhysAddr) -> Self {
+        LentFrame { paddr, frame: None }
+    }
+
+    /// a new zero-filled frame, owned by the loan only
+    pub fn alloc() -> AxResult<Self> {
+        Ok(Self::new(&Frame::new(GlobalPage::alloc_zero()?)))
+    }
+
+    /// physical address of the frame
+    pub fn paddr(&self) -> PhysAddr {
+        self.paddr
+    }
+
+    /// copy the bytes in `range` of the frame from the same range of `other`
+    pub fn copy_from(&self, other: &LentFrame, range: Range<usize>) {
+        assert!(range.end <= PAGE_SIZE_4K);
+        // The frames may be mapped by users, who see the bytes changing as with any write.
+        unsafe {
+            core::ptr::copy(
+                phys_to_virt(other.paddr).as_ptr().add(range.start),
+                phys_to_virt(self.paddr).as_mut_ptr().add(range.start),
+                range.len(),
+            );
+        }
+    }
+}
+
+impl Clone for LentFrame {
+    fn clone(&self) -> Self {
+        match &self.frame {
+            Some(frame) => Self::new(frame),
+            None => Self::unowned(self.paddr),
+        }
+    }
+}
+
+impl Drop for LentFrame {
+    fn drop(&mut self) {
+        if let Some(frame) = &self.frame {
+            frame.loans.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// Where the pages of an area come from
+#[derive(Clone)]
+pub(crate) enum Backing {
+    /// zero-filled pages
+    Anonymous,
+    /// pages of an ELF segment, initialized with `data` starting at `vaddr`,
+    /// zero-filled elsewhere
+    Elf { data: Arc<[u8]>, vaddr: VirtAddr },
+    /// frames lent by another address space (e.g. fmapped from a scheme),
+    /// one per page starting at `vaddr`
+    Lent {
+        frames: Arc<[LentFrame]>,
+        vaddr: VirtAddr,
+    },
+    /// never mapped, below a stack to catch overflows
+    Guard,
+}
+
+/// A range of pages with the same permissions and backing
+pub(crate) struct MapArea {
+    pub(crate) start: VirtAddr,
+    pub(crate) size: usize,
+    pub(crate) flags: MappingFlags,
+    pub(crate) backing: Backing,
+    /// frames of the pages accessed so far, except lent ones.
+    /// They are shared with forked address spaces until written.
+    pub(crate) frames: BTreeMap<VirtAddr, Arc<Frame>>,
+}
+
+impl MapArea {
+    pub(crate) fn new(start: VirtAddr, size: usize, flags: MappingFlags, backing: Backing) -> Self {
+        MapArea {
+            start,
+            size,
+            flags,
+            backing,
+            frames: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn end(&self) -> VirtAddr {
+        self.start + self.size
+    }
+
+    pub(crate) fn contains(&self, vaddr: VirtAddr) -> bool {
+        self.start <= vaddr && vaddr < self.end()
+    }
+
+    /// whether the pages can be accessed at all
+    pub(crate) fn accessible(&self) -> bool {
+        self.flags
+            .intersects(MappingFlags::READ | MappingFlags::WRITE | MappingFlags::EXECUTE)
+    }
+
+    /// whether the frame of the page at `vaddr` is shared copy-on-write with other address spaces
+    pub(crate) fn shared(&self, vaddr: VirtAddr) -> bool {
+        self.frames.get(&vaddr).map_or(false, |frame| {
+            Arc::strong_count(frame) - frame.loans.load(Ordering::Acquire) > 1
+        })
+    }
+
+    /// lend the frame of the page at `vaddr`, allocated first if it is not yet
+    pub(crate) fn lend(&mut self, vaddr: VirtAddr) -> AxResult<LentFrame> {
+        if let Backing::Lent {
+            frames,
+            vaddr: start,
+        } = &self.backing
+        {
+            return Ok(frames[(vaddr.as_usize() - start.as_usize()) / PAGE_SIZE_4K].clone());
+        }
+        self.frame(vaddr)?;
+        Ok(LentFrame::new(&self.frames[&vaddr]))
+    }
+
+    /// get the frame of the page at `vaddr`, allocated and initialized on the first call
+    pub(crate) fn frame(&mut self, vaddr: VirtAddr) -> AxResult<PhysAddr> {
+        if let Some(frame) = self.frames.get(&vaddr) {
+            return Ok(frame.paddr());
+        }
+        let data = match &self.backing {
+            Backing::Lent {
+                frames,
+                vaddr: start,
+            } => {
+                return Ok(frames[(vaddr.as_usize() - start.as_usize()) / PAGE_SIZE_4K].paddr());
+            }
+            Backing::Guard => unreachable!("guard pages are never mapped"),
+            Backing::Anonymous => None,
+            Backing::Elf { data, vaddr: start } => Some((data, start.as_usize())),
+        };
+        let mut page = GlobalPage::alloc_zero()?;
+        if let Some((data, data_start)) = data {
+            let page_start = vaddr.as_usize();
+            let from = page_start.max(data_start);
+            let to = (page_start + PAGE_SIZE_4K).min(data_start + data.len());
+            if from < to {
+                page.as_slice_mut()[from - page_start..to - page_start]
+                    .copy_from_slice(&data[from - data_start..to - data_start]);
+            }
+        }
+        let frame = Frame::new(page);
+        let paddr = frame.paddr();
+        self.frames.insert(vaddr, frame);
+        Ok(paddr)
+    }
+
+    /// split the area at `at`, this one keeps the lower part, the upper part is returned
+    pub(crate) fn split(&mut self, at: VirtAddr) -> MapArea {
+        let upper = MapArea {
+            start: at,
+            size: self.end().as_usize() - at.as_usize(),
+            flags: self.flags,
+            backing: self.backing.clone(),
+            frames: self.frames.split_off(&at),
+        };
+        self.size = at.as_usize() - self.start.as_usize();
+        upper
+    }
+}
@@ -6,11 +6,18 @@ extern crate alloc;
 #[macro_use]
 extern crate axlog;
 
+mod area;
+
 use core::cmp::Ordering;
+use core::mem::size_of;
+use core::sync::atomic::AtomicU32;
 
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use alloc::{collections::BTreeMap, string::String};
+pub use area::LentFrame;
+use area::{Backing, Frame, MapArea};
 use axalloc::GlobalPage;
 use axerrno::{ax_err, AxError, AxResult};
 use axhal::{
@@ -20,20 +27,34 @@ use axhal::{
 use crate_interface::def_interface;
 use memory_addr::{align_up, align_up_4k, PhysAddr, VirtAddr, PAGE_SIZE_4K};
 use spinlock::SpinNoIrq;
+use syscall_number::auxv::*;
 
-/// Start of binary
+/// Start of binary, also where position-independent programs are loaded
 pub const USER_START: usize = 0x0400_0000;
+/// where position-independent interpreters (i.e. dynamic loaders) are loaded
+pub const INTERP_BASE: usize = 0x4_0000_0000;
 /// top of user stack
 pub const USTACK_START: usize = 0x10_0000_0000 - USTACK_SIZE;
-/// size of user stack
-pub const USTACK_SIZE: usize = 4096 * 10;
+/// default size of user stacks of threads, allocated on demand
+pub const USTACK_SIZE: usize = 4096 * 64;
+/// default limit of user stack sizes of a process, also the stack size of main threads
+pub const USTACK_LIMIT: usize = 8 << 20;
+/// start of user stack areas
+pub const USTACK_AREA_START: usize = 0x8_0000_0000;
+/// end of user stack areas
+pub const USTACK_AREA_END: usize = 0x10_0000_0000;
 /// address of trampoline page
 pub const TRAMPOLINE_START: usize = 0xffff_ffc0_0000_0000;
 /// start of default mmap areas
 pub const MMAP_AREA_START: usize = 0x10_0000_0000;
 /// end of default mmap areas
 pub const MMAP_AREA_END: usize = 0x20_0000_0000;
+/// limit of the total size of the arguments and environment variables of a program
+pub const ARG_MAX: usize = 128 << 10;
+/// limit of the size of the TLS block of a thread, put at the top of its stack
+pub const TLS_MAX: usize = USTACK_SIZE / 4;
 
+/// Eagerly allocated pages, accessed by the kernel directly (e.g. trap frames)
 pub(crate) struct MapSegment {
     start_vaddr: VirtAddr,
     size: usize,
@@ -49,9 +70,21 @@ pub(crate) struct HeapSegment {
 /// mutable inner objects of address space
 pub struct AddrSpaceInner {
     segments: alloc::vec::Vec<MapSegment>,
+    /// areas of ELF segments, heap, stacks and mmap, mapped on the first access
+    areas: BTreeMap<VirtAddr, MapArea>,
     page_table: PageTable,
     heap: Option<HeapSegment>,
-    mmap_use: BTreeMap<VirtAddr, GlobalPage>,
+    /// pages of writable areas sharing their frames with other address spaces,
+    /// mapped read-only until written
+    cow: BTreeSet<VirtAddr>,
+    /// limit of user stack sizes
+    stack_limit: usize,
+    /// auxiliary vector of the program, put on its initial stack
+    auxv: Vec<(usize, usize)>,
+    /// where the program starts, the entry of its interpreter if it has one
+    entry: VirtAddr,
+    /// template of the TLS blocks of threads
+    tls: Option<elf_loader::TlsTemplate>,
 }
 
 impl Default for AddrSpaceInner {
@@ -64,9 +97,14 @@ impl AddrSpaceInner {
     pub(crate) fn new() -> AddrSpaceInner {
         AddrSpaceInner {
             segments: vec![],
+            areas: BTreeMap::new(),
             page_table: PageTable::try_new().expect("Creating page table failed!"),
             heap: None,
-            mmap_use: BTreeMap::new(),
+            cow: BTreeSet::new(),
+            stack_limit: USTACK_LIMIT,
+            auxv: Vec::new(),
+            entry: USER_START.into(),
+            tls: None,
         }
     }
 
@@ -110,7 +148,68 @@ impl AddrSpaceInner {
         Ok(())
     }
 
-    /// remove a segment
+    /// create a region of zero-filled pages, allocated on the first access
+    pub fn add_region_lazy(
+        &mut self,
+        vaddr: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+    ) -> AxResult<()> {
+        self.add_area(MapArea::new(
+            vaddr,
+            align_up_4k(size),
+            flags,
+            Backing::Anonymous,
+        ))
+    }
+
+    /// reserve a user stack of `size` bytes above a guard page, allocated on demand.
+    /// @returns: the lowest address of the stack
+    pub fn add_stack(&mut self, size: usize) -> AxResult<VirtAddr> {
+        let size = align_up_4k(size);
+        if size == 0 || size > self.stack_limit {
+            return ax_err!(InvalidInput);
+        }
+        let guard = self
+            .find_free(
+                USTACK_AREA_START.into(),
+                USTACK_AREA_END.into(),
+                size + PAGE_SIZE_4K,
+            )
+            .ok_or(AxError::NoMemory)?;
+        self.add_area(MapArea::new(
+            guard,
+            PAGE_SIZE_4K,
+            MappingFlags::empty(),
+            Backing::Guard,
+        ))?;
+        let start = guard + PAGE_SIZE_4K;
+        self.add_region_lazy(
+            start,
+            size,
+            MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
+        )?;
+        Ok(start)
+    }
+
+    /// get the limit of user stack sizes
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit
+    }
+
+    /// get the smallest size of user stacks of new threads:
+    /// the TLS block at the top, with a page below it for the thread itself
+    pub fn min_stack_size(&self) -> usize {
+        let tls_size = self.tls.map_or(0, |tls| tls.mem_size + tls.align.max(16));
+        align_up_4k(tls_size) + PAGE_SIZE_4K
+    }
+
+    /// get the entry point of the program
+    pub fn entry(&self) -> VirtAddr {
+        self.entry
+    }
+
+    /// remove a segment, or an area created by `add_region_lazy` or `add_stack`
     pub fn remove_region(&mut self, vaddr: VirtAddr) -> AxResult<()> {
         if let Some((idx, _)) = self
             .segments
@@ -123,6 +222,13 @@ impl AddrSpaceInner {
                 .unmap_region(item.start_vaddr, item.size)
                 .map_err(|_| AxError::BadAddress)?;
             Ok(())
+        } else if let Some(area) = self.areas.remove(&vaddr) {
+            self.unmap_area(&area);
+            let guard = vaddr - PAGE_SIZE_4K;
+            if let Some(Backing::Guard) = self.find_area(guard).map(|area| &area.backing) {
+                self.areas.remove(&guard);
+            }
+            Ok(())
         } else {
             ax_err!(BadAddress)
         }
@@ -133,6 +239,123 @@ impl AddrSpaceInner {
         self.page_table.root_paddr()
     }
 
+    fn add_area(&mut self, area: MapArea) -> AxResult<()> {
+        if !area.start.is_aligned_4k() || area.size == 0 {
+            return ax_err!(InvalidInput);
+        }
+        if self.overlaps(area.start, area.size) {
+            return ax_err!(AlreadyExists);
+        }
+        self.areas.insert(area.start, area);
+        Ok(())
+    }
+
+    fn overlaps(&self, vaddr: VirtAddr, size: usize) -> bool {
+        self.areas
+            .range(..vaddr + size)
+            .next_back()
+            .map_or(false, |(_, area)| area.end() > vaddr)
+    }
+
+    fn find_area(&self, vaddr: VirtAddr) -> Option<&MapArea> {
+        self.areas
+            .range(..=vaddr)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.contains(vaddr))
+    }
+
+    fn find_area_mut(&mut self, vaddr: VirtAddr) -> Option<&mut MapArea> {
+        self.areas
+            .range_mut(..=vaddr)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.contains(vaddr))
+    }
+
+    /// find a free range of `size` bytes in `[start, end)`
+    fn find_free(&self, start: VirtAddr, end: VirtAddr, size: usize) -> Option<VirtAddr> {
+        let mut addr = start;
+        for area in self.areas.values() {
+            if area.end() <= addr {
+                continue;
+            }
+            if area.start >= addr + size {
+                break;
+            }
+            addr = area.end();
+        }
+        (addr + size <= end).then_some(addr)
+    }
+
+    /// whether `[vaddr, vaddr + size)` is covered by areas other than guard pages
+    fn covered(&self, vaddr: VirtAddr, size: usize) -> bool {
+        let end = vaddr + size;
+        let mut addr = vaddr;
+        while addr < end {
+            match self.find_area(addr) {
+                Some(area) if !matches!(area.backing, Backing::Guard) => addr = area.end(),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// whether any page in `[vaddr, vaddr + size)` is lent by another address space
+    fn lent_in(&self, vaddr: VirtAddr, size: usize) -> bool {
+        self.areas
+            .range(..vaddr + size)
+            .rev()
+            .take_while(|(_, area)| area.end() > vaddr)
+            .any(|(_, area)| matches!(area.backing, Backing::Lent { .. }))
+    }
+
+    /// split the area across `at`, if any, so that an area starts there
+    fn split_at(&mut self, at: VirtAddr) {
+        if let Some(area) = self.find_area_mut(at) {
+            if area.start != at {
+                let upper = area.split(at);
+                self.areas.insert(at, upper);
+            }
+        }
+    }
+
+    /// unmap the pages of an area removed from `areas`
+    fn unmap_area(&mut self, area: &MapArea) {
+        let unmap = |space: &mut Self, vaddr: VirtAddr| {
+            space.cow.remove(&vaddr);
+            if space.page_table.unmap(vaddr).is_ok() {
+                axhal::arch::flush_tlb(Some(vaddr));
+            }
+        };
+        if let Backing::Lent { .. } = area.backing {
+            for page in 0..area.size / PAGE_SIZE_4K {
+                unmap(self, area.start + page * PAGE_SIZE_4K);
+            }
+        } else {
+            // Only pages with frames are mapped.
+            for vaddr in area.frames.keys() {
+                unmap(self, *vaddr);
+            }
+        }
+    }
+
+    /// unmap the areas in `[vaddr, vaddr + size)`, splitting those across its ends
+    fn unmap_range(&mut self, vaddr: VirtAddr, size: usize) {
+        let end = vaddr + size;
+        self.split_at(vaddr);
+        self.split_at(end);
+        let starts: Vec<VirtAddr> = self
+            .areas
+            .range(vaddr..end)
+            .map(|(start, _)| *start)
+            .collect();
+        for start in starts {
+            let area = self.areas.remove(&start).unwrap();
+            self.unmap_area(&area);
+        }
+    }
+
     pub(crate) fn init_heap(&mut self, vaddr: VirtAddr) {
         if self.heap.is_some() {
             return;
@@ -141,77 +364,45 @@ impl AddrSpaceInner {
             start_vaddr: vaddr,
             actual_size: 0,
         });
-        let page = GlobalPage::alloc_zero().expect("Alloc error!");
-        self.page_table
-            .map_region(
-                vaddr,
-                page.start_paddr(virt_to_phys),
-                page.size(),
-                MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
-                false,
-            )
-            .expect("Mapping Segment Error");
-        self.segments.push(MapSegment {
-            start_vaddr: vaddr,
-            size: page.size(),
-            phy_mem: vec![page.into()],
-        });
+        self.add_region_lazy(
+            vaddr,
+            PAGE_SIZE_4K,
+            MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
+        )
+        .expect("Mapping Segment Error");
         info!("User heap inited @ {:x}", vaddr);
     }
 
     /// modify heap size
     pub fn sbrk(&mut self, size: isize) -> Option<usize> {
-        if let Some(heap) = &mut self.heap {
-            let old_brk: usize = (heap.start_vaddr + heap.actual_size).into();
-            trace!("user sbrk: {} bytes", size);
-            debug!("sbrk: {}, {}", size, old_brk);
-            match size.cmp(&0) {
-                Ordering::Equal => {
-                    return Some(old_brk);
+        let heap = self.heap.as_mut()?;
+        let old_brk: usize = (heap.start_vaddr + heap.actual_size).into();
+        trace!("user sbrk: {} bytes", size);
+        debug!("sbrk: {}, {}", size, old_brk);
+        match size.cmp(&0) {
+            Ordering::Equal => {}
+            Ordering::Less => {
+                if (-size) as usize > heap.actual_size {
+                    return None;
                 }
-                Ordering::Less => {
-                    if (-size) as usize > heap.actual_size {
+                heap.actual_size -= -size as usize
+            }
+            Ordering::Greater => {
+                let actual_size = heap.actual_size + size as usize;
+                let start = heap.start_vaddr;
+                let heap_size = self.areas.get(&start)?.size;
+                if actual_size > heap_size {
+                    // The pages are allocated when used.
+                    let new_size = align_up_4k(actual_size);
+                    if self.overlaps(start + heap_size, new_size - heap_size) {
                         return None;
                     }
-                    heap.actual_size -= -size as usize
-                }
-                Ordering::Greater => {
-                    heap.actual_size += size as usize;
-                    let heap_seg = self
-                        .segments
-                        .iter_mut()
-                        .find(|x| x.start_vaddr == heap.start_vaddr)
-                        .unwrap();
-                    if heap.actual_size > heap_seg.size {
-                        let delta = align_up_4k(heap.actual_size - heap_seg.size);
-                        while heap.actual_size > heap_seg.size {
-                            if let Ok(page) =
-                                GlobalPage::alloc_contiguous(delta / PAGE_SIZE_4K, PAGE_SIZE_4K)
-                            {
-                                self.page_table
-                                    .map_region(
-                                        heap.start_vaddr + heap_seg.size,
-                                        page.start_paddr(virt_to_phys),
-                                        page.size(),
-                                        MappingFlags::READ
-                                            | MappingFlags::WRITE
-                                            | MappingFlags::USER,
-                                        false,
-                                    )
-                                    .expect("Mapping Error");
-                                heap_seg.size += page.size();
-                                heap_seg.phy_mem.push(page.into());
-                            } else {
-                                return None;
-                            }
-                        }
-                    }
+                    self.areas.get_mut(&start).unwrap().size = new_size;
                 }
+                self.heap.as_mut().unwrap().actual_size = actual_size;
             }
-            Some(old_brk)
-        } else {
-            None
         }
+        Some(old_brk)
     }
 
     /// a simple mmap-like page allocator, except that memory is alloced in pages
@@ -220,66 +411,166 @@ impl AddrSpaceInner {
     /// @returns: starting addr of the maped pages
     pub fn mmap_page(
         &mut self,
-        _addr: Option<VirtAddr>,
+        addr: Option<VirtAddr>,
         len: usize,
         flags: MappingFlags,
     ) -> AxResult<VirtAddr> {
-        let mut addr: VirtAddr = MMAP_AREA_START.into();
+        self.mmap(addr.unwrap_or(0.into()), len, flags, false)
+    }
+
+    /// map `len` bytes of zero-filled pages, allocated on the first access.
+    /// With `fixed`, the pages are mapped exactly at `addr`, replacing the areas there;
+    /// otherwise `addr` is a hint, and free mmap areas are used if it is not free.
+    /// @returns: starting addr of the maped pages
+    pub fn mmap(
+        &mut self,
+        addr: VirtAddr,
+        len: usize,
+        flags: MappingFlags,
+        fixed: bool,
+    ) -> AxResult<VirtAddr> {
         let len = align_up_4k(len);
-        let pages = len / PAGE_SIZE_4K;
-        while addr + len < MMAP_AREA_END.into() {
-            if let Some(offset) = (0..pages)
-                .find(|offset| self.mmap_use.contains_key(&(addr + offset * PAGE_SIZE_4K)))
-            {
-                addr += (offset + 1) * PAGE_SIZE_4K;
-            } else {
-                // TODO: undo when error
-                (0..pages).for_each(|offset| {
-                    let phy_page = GlobalPage::alloc_zero().expect("Run out of memory!");
-                    self.page_table
-                        .map_region(
-                            addr + offset * PAGE_SIZE_4K,
-                            phy_page.start_paddr(virt_to_phys),
-                            PAGE_SIZE_4K,
-                            flags,
-                            false,
-                        )
-                        .expect("Mapping error");
-                    self.mmap_use.insert(addr + offset * PAGE_SIZE_4K, phy_page);
-                });
-                return Ok(addr);
-            }
+        if len == 0 {
+            return ax_err!(InvalidInput);
         }
-        ax_err!(NoMemory)
+        let in_user = addr.as_usize() >= PAGE_SIZE_4K
+            && addr
+                .as_usize()
+                .checked_add(len)
+                .map_or(false, |end| end <= MMAP_AREA_END);
+        let addr = if fixed {
+            // Frames lent by schemes are only released by `funmap`.
+            if !addr.is_aligned_4k() || !in_user || self.lent_in(addr, len) {
+                return ax_err!(InvalidInput);
+            }
+            self.unmap_range(addr, len);
+            addr
+        } else if addr.is_aligned_4k() && in_user && !self.overlaps(addr, len) {
+            addr
+        } else {
+            self.find_free(MMAP_AREA_START.into(), MMAP_AREA_END.into(), len)
+                .ok_or(AxError::NoMemory)?
+        };
+        self.add_area(MapArea::new(addr, len, flags, Backing::Anonymous))?;
+        Ok(addr)
+    }
+
+    /// map frames lent by another address space into mmap areas.
+    /// The frames are kept alive until they are unmapped by `munmap_page`.
+    /// @param frames: lent frames, one per page
+    /// @returns: starting addr of the maped pages
+    pub fn mmap_lend(&mut self, frames: &[LentFrame], flags: MappingFlags) -> AxResult<VirtAddr> {
+        let len = frames.len() * PAGE_SIZE_4K;
+        let addr = self
+            .find_free(MMAP_AREA_START.into(), MMAP_AREA_END.into(), len)
+            .ok_or(AxError::NoMemory)?;
+        let backing = Backing::Lent {
+            frames: frames.into(),
+            vaddr: addr,
+        };
+        self.add_area(MapArea::new(addr, len, flags, backing))?;
+        Ok(addr)
     }
 
-    /// unmap a page obtained from `mmap`
+    /// unmap pages obtained from `mmap` or `mmap_lend`
     pub fn munmap_page(&mut self, addr: VirtAddr, len: usize) -> AxResult<()> {
         let len = align_up_4k(len);
         trace!("unmap: [{:x?}, {:x?})", addr, addr + len);
-        let pages = len / PAGE_SIZE_4K;
-        if (0..pages).any(|offset| !self.mmap_use.contains_key(&(addr + offset * PAGE_SIZE_4K))) {
+        if !self.covered(addr, len) {
             return ax_err!(BadAddress);
-        } else {
-            (0..pages).for_each(|offset| {
-                self.page_table.unmap(addr + offset * PAGE_SIZE_4K).unwrap();
-                self.mmap_use.remove(&(addr + offset * PAGE_SIZE_4K));
-            })
         }
+        self.unmap_range(addr, len);
+        Ok(())
+    }
+
+    /// unmap the pages in `[addr, addr + len)`, which may cover parts of areas or none.
+    /// Pages lent by schemes are unmapped by `funmap` instead.
+    pub fn munmap(&mut self, addr: VirtAddr, len: usize) -> AxResult<()> {
+        let len = align_up_4k(len);
+        if len == 0 || !addr.is_aligned_4k() || addr.as_usize().checked_add(len).is_none() {
+            return ax_err!(InvalidInput);
+        }
+        if self.lent_in(addr, len) {
+            return ax_err!(InvalidInput);
+        }
+        self.unmap_range(addr, len);
         Ok(())
     }
 
-    /// translate a slice to slices in kernel
+    /// change the permissions of the pages in `[addr, addr + len)`, which must all be mapped.
+    /// Pages lent by schemes keep the permissions they are lent with.
+    pub fn mprotect(&mut self, addr: VirtAddr, len: usize, flags: MappingFlags) -> AxResult<()> {
+        let len = align_up_4k(len);
+        if !addr.is_aligned_4k() || addr.as_usize().checked_add(len).is_none() {
+            return ax_err!(InvalidInput);
+        }
+        if !self.covered(addr, len) {
+            return ax_err!(BadAddress);
+        }
+        if self.lent_in(addr, len) {
+            return ax_err!(PermissionDenied);
+        }
+        let end = addr + len;
+        self.split_at(addr);
+        self.split_at(end);
+        let mut pages = Vec::new();
+        for area in self.areas.range_mut(addr..end).map(|(_, area)| area) {
+            area.flags = flags;
+            pages.extend(area.frames.keys().copied());
+        }
+        for vaddr in pages {
+            self.map_page(vaddr)?;
+        }
+        Ok(())
+    }
+
+    /// lend the frames of user pages covering `[vaddr, vaddr + size)`.
+    /// If `write` is set, all pages must be writable.
+    /// Pages shared copy-on-write get their own frames first, so that the lender and
+    /// the borrowers keep seeing the same data.
+    pub fn query_frames(
+        &mut self,
+        vaddr: VirtAddr,
+        size: usize,
+        write: bool,
+    ) -> AxResult<Vec<LentFrame>> {
+        let start = vaddr.align_down_4k();
+        let end = (vaddr + size).align_up_4k();
+        let mut result = vec![];
+        let mut vaddr = start;
+        while vaddr < end {
+            self.fault_in(vaddr, write)?;
+            self.break_cow(vaddr)?;
+            let (_, flags, _) = self
+                .page_table
+                .query(vaddr)
+                .map_err(|_| AxError::BadAddress)?;
+            if !flags.contains(MappingFlags::USER)
+                || (write && !flags.contains(MappingFlags::WRITE))
+            {
+                return ax_err!(BadAddress);
+            }
+            // Pages of segments (e.g. trap frames) are not lent.
+            let area = self.find_area_mut(vaddr).ok_or(AxError::BadAddress)?;
+            result.push(area.lend(vaddr)?);
+            vaddr += PAGE_SIZE_4K;
+        }
+        Ok(result)
+    }
+
+    /// translate a slice to slices in kernel.
+    /// The pages are mapped if not accessed yet, and get their own frames if shared and `write` is set.
     pub fn translate_buffer(
-        &self,
+        &mut self,
         vaddr: VirtAddr,
         size: usize,
-        _write: bool,
+        write: bool,
     ) -> AxResult<Vec<&'static mut [u8]>> {
         let mut read_size = 0usize;
         let mut vaddr = vaddr;
         let mut result: Vec<&'static mut [u8]> = vec![];
         while read_size < size {
+            self.fault_in(vaddr, write)?;
             let (paddr, _flag, page_size) = self
                 .page_table
                 .query(vaddr)
@@ -301,69 +592,200 @@ impl AddrSpaceInner {
         Ok(result)
     }
 
+    /// like `translate_buffer`, but fails unless the pages are user pages (and writable if `write` is set).
+    pub fn translate_user_buffer(
+        &mut self,
+        vaddr: VirtAddr,
+        size: usize,
+        write: bool,
+    ) -> AxResult<Vec<&'static mut [u8]>> {
+        let end = vaddr
+            .as_usize()
+            .checked_add(size)
+            .ok_or(AxError::BadAddress)?;
+        let mut page = vaddr.align_down_4k();
+        while page.as_usize() < end {
+            self.fault_in(page, write)?;
+            let (_, flags, _) = self
+                .page_table
+                .query(page)
+                .map_err(|_| AxError::BadAddress)?;
+            if !flags.contains(MappingFlags::USER)
+                || (write && !flags.contains(MappingFlags::WRITE))
+            {
+                return ax_err!(BadAddress);
+            }
+            page += PAGE_SIZE_4K;
+        }
+        self.translate_buffer(vaddr, size, write)
+    }
+
     /// find a mapping of the virtual address
     pub fn query(&self, vaddr: VirtAddr) -> Option<PhysAddr> {
         self.page_table.query(vaddr).ok().map(|x| x.0)
     }
+
+    /// handle a page fault of user code at `vaddr`, returns whether the access can be retried
+    pub fn handle_page_fault(&mut self, vaddr: VirtAddr, write: bool) -> bool {
+        let vaddr = vaddr.align_down_4k();
+        let resolved = if self.page_table.query(vaddr).is_err() {
+            self.populate(vaddr, write)
+        } else if write {
+            self.break_cow(vaddr)
+        } else {
+            Ok(false)
+        };
+        matches!(resolved, Ok(true))
+    }
+
+    /// make the page at `vaddr` accessible to the kernel, as if user code accessed it.
+    /// The kernel may also fill pages the user cannot write, e.g. the shadow buffers of requests,
+    /// so callers check the user's rights themselves.
+    fn fault_in(&mut self, vaddr: VirtAddr, write: bool) -> AxResult<()> {
+        let vaddr = vaddr.align_down_4k();
+        if self.page_table.query(vaddr).is_err() {
+            if !self.populate(vaddr, false)? {
+                return ax_err!(BadAddress);
+            }
+            if write {
+                self.break_cow(vaddr)?;
+            }
+        } else if write {
+            self.break_cow(vaddr)?;
+        }
+        Ok(())
+    }
+
+    /// map the page at `vaddr` of an area not accessed yet,
+    /// returns `false` if there is no such page or it cannot be accessed this way.
+    fn populate(&mut self, vaddr: VirtAddr, write: bool) -> AxResult<bool> {
+        let Some(area) = self.find_area(vaddr) else {
+            return Ok(false);
+        };
+        if let Backing::Guard = area.backing {
+            warn!("User stack overflow @ {:#x}", vaddr);
+            return Ok(false);
+        }
+        if !area.accessible() || (write && !area.flags.contains(MappingFlags::WRITE)) {
+            return Ok(false);
+        }
+        self.map_page(vaddr)?;
+        if write {
+            self.break_cow(vaddr)?;
+        }
+        Ok(true)
+    }
+
+    /// map the page at `vaddr` to its frame with the flags of its area,
+    /// or unmap it if the area cannot be accessed.
+    /// Writable pages whose frames are shared are mapped read-only until written.
+    fn map_page(&mut self, vaddr: VirtAddr) -> AxResult<()> {
+        let area = self.find_area_mut(vaddr).unwrap();
+        let accessible = area.accessible();
+        let mut flags = area.flags;
+        let paddr = area.frame(vaddr)?;
+        if flags.contains(MappingFlags::WRITE) && area.shared(vaddr) {
+            flags -= MappingFlags::WRITE;
+            self.cow.insert(vaddr);
+        } else {
+            self.cow.remove(&vaddr);
+        }
+        if self.page_table.unmap(vaddr).is_ok() {
+            axhal::arch::flush_tlb(Some(vaddr));
+        }
+        if accessible {
+            self.page_table
+                .map_region(vaddr, paddr, PAGE_SIZE_4K, flags, false)
+                .map_err(|_| AxError::BadAddress)?;
+        }
+        Ok(())
+    }
+
+    /// give the page at `vaddr` its own frame if it is shared copy-on-write,
+    /// returns `false` if it is not such a page.
+    fn break_cow(&mut self, vaddr: VirtAddr) -> AxResult<bool> {
+        if !self.cow.contains(&vaddr) {
+            return Ok(false);
+        }
+        let area = self.find_area_mut(vaddr).unwrap();
+        // Otherwise the other address spaces have their own copies now.
+        if area.shared(vaddr) {
+            let mut page = GlobalPage::alloc()?;
+            page.as_slice_mut()
+                .copy_from_slice(area.frames[&vaddr].as_slice());
+            area.frames.insert(vaddr, Frame::new(page));
+        }
+        self.map_page(vaddr)?;
+        Ok(true)
+    }
 }
 
 impl AddrSpaceInner {
-    fn init_global(&mut self, data: &[u8]) -> AxResult<()> {
-        let segments = elf_loader::SegmentEntry::new(data).ok_or(AxError::InvalidData)?;
-
+    /// Adds areas of the segments of an ELF file, loaded at `base` if it is position-independent,
+    /// returns the end of its last segment.
+    fn load_elf(&mut self, data: &[u8], base: VirtAddr) -> AxResult<VirtAddr> {
+        let segments = elf_loader::SegmentEntry::new_at(data, base).ok_or(AxError::InvalidData)?;
         let mut data_end: VirtAddr = 0.into();
 
         for segment in &segments {
-            let mut user_phy_page = GlobalPage::alloc_contiguous(
-                align_up_4k(segment.size) / PAGE_SIZE_4K,
-                PAGE_SIZE_4K,
-            )
-            .expect("Alloc page error!");
-            // init
-            user_phy_page.zero();
-
-            // copy user content
-            user_phy_page.as_slice_mut()[..segment.data.len()].copy_from_slice(segment.data);
-            debug!(
-                "{:x} {:x}",
-                user_phy_page.as_slice()[0],
-                user_phy_page.as_slice()[1]
-            );
-
-            self.add_region(
-                segment.start_addr,
-                user_phy_page.start_paddr(virt_to_phys),
-                Arc::new(user_phy_page),
+            // The pages are loaded when accessed, from a copy of the segment.
+            let start = segment.start_addr.align_down_4k();
+            let end = (segment.start_addr + segment.size).align_up_4k();
+            let backing = Backing::Elf {
+                data: segment.data[..].into(),
+                vaddr: segment.start_addr,
+            };
+            self.add_area(MapArea::new(
+                start,
+                end.as_usize() - start.as_usize(),
                 segment.flags | MappingFlags::USER,
-                false,
-            )
+                backing,
+            ))
             .map_err(|_| AxError::BadAddress)?;
-            data_end = data_end.max(segment.start_addr + align_up_4k(segment.size))
+            data_end = data_end.max(end)
+        }
+        Ok(data_end)
+    }
+
+    fn init_global(&mut self, data: &[u8], interp: Option<&[u8]>) -> AxResult<()> {
+        let info =
+            elf_loader::ElfInfo::new_at(data, USER_START.into()).ok_or(AxError::InvalidData)?;
+        self.auxv = vec![
+            (AT_PHDR, info.phdr.into()),
+            (AT_PHENT, info.phent),
+            (AT_PHNUM, info.phnum),
+            (AT_PAGESZ, PAGE_SIZE_4K),
+            (AT_ENTRY, info.entry.into()),
+        ];
+        self.entry = info.entry;
+        let data_end = self.load_elf(data, USER_START.into())?;
+        if let Some(tls) = info.tls {
+            let align = tls.align.max(1);
+            if tls.file_size > tls.mem_size
+                || tls.mem_size > TLS_MAX
+                || !align.is_power_of_two()
+                || align > TLS_MAX
+                || !self.covered(tls.vaddr, tls.file_size)
+            {
+                return ax_err!(InvalidData);
+            }
+        }
+        self.tls = info.tls;
+
+        // The interpreter loads the libraries and relocates the program, then jumps to `AT_ENTRY`.
+        if let Some(interp) = interp {
+            let interp_info = elf_loader::ElfInfo::new_at(interp, INTERP_BASE.into())
+                .ok_or(AxError::InvalidData)?;
+            if interp_info.interp.is_some() {
+                return ax_err!(InvalidData);
+            }
+            self.load_elf(interp, INTERP_BASE.into())?;
+            self.auxv.push((AT_BASE, interp_info.base.into()));
+            self.entry = interp_info.entry;
         }
 
         self.init_heap(data_end);
 
-        /*
-        // stack allocation
-        //assert!(USTACK_SIZE % PAGE_SIZE_4K == 0);
-        #[cfg(not(feature = "multitask"))]
-        {
-            let user_stack_page =
-                GlobalPage::alloc_contiguous(USTACK_SIZE / PAGE_SIZE_4K, PAGE_SIZE_4K)
-                    .expect("Alloc page error!");
-            debug!("{:?}", user_stack_page);
-
-            self
-                .add_region(
-                    USTACK_START.into(),
-                    user_stack_page.start_paddr(virt_to_phys),
-                    Arc::new(user_stack_page),
-                    MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
-                    false,
-                )
-                .expect("Memory Error");
-        }
-        */
         extern "C" {
             fn strampoline();
         }
@@ -380,11 +802,12 @@ impl AddrSpaceInner {
     }
 }
 
+/// The child shares the frames of the parent, copy-on-write for writable pages.
 impl Clone for AddrSpace {
     fn clone(&self) -> Self {
-        let inner = self.lock();
+        let mut inner = self.lock();
         let mut new_inner = AddrSpaceInner::new();
-        let page_table = &mut new_inner.page_table;
+        let mut writable = Vec::new();
 
         for segment in &inner.segments {
             let mut new_seg = MapSegment {
@@ -393,16 +816,6 @@ impl Clone for AddrSpace {
                 phy_mem: Vec::new(),
             };
             let pages = segment.size / PAGE_SIZE_4K;
-
-            /*
-                if segment.phy_mem.is_empty() { // pages such as trampoline
-                (0..pages).for_each(|page| {
-                let vaddr = segment.start_vaddr + page * PAGE_SIZE_4K;
-                let (paddr, flags, _) = inner.page_table.query(vaddr).unwrap();
-                page_table.map_region(vaddr, paddr, PAGE_SIZE_4K, flags, false).unwrap();
-            })
-            } else {
-                 */
             (0..pages).for_each(|page| {
                 let vaddr = segment.start_vaddr + page * PAGE_SIZE_4K;
                 let (paddr, flags, _) = inner.page_table.query(vaddr).unwrap();
@@ -410,7 +823,8 @@ impl Clone for AddrSpace {
                 user_phy_page.as_slice_mut().copy_from_slice(unsafe {
                     core::slice::from_raw_parts(phys_to_virt(paddr).as_ptr(), PAGE_SIZE_4K)
                 });
-                page_table
+                new_inner
+                    .page_table
                     .map_region(
                         vaddr,
                         user_phy_page.start_paddr(virt_to_phys),
@@ -421,27 +835,61 @@ impl Clone for AddrSpace {
                     .unwrap();
                 new_seg.phy_mem.push(user_phy_page.into());
             });
-            //}
             new_inner.segments.push(new_seg)
         }
         new_inner.heap = inner.heap.clone();
-        for (vaddr, page) in &inner.mmap_use {
-            let mut user_phy_page = GlobalPage::alloc().unwrap();
-            user_phy_page
-                .as_slice_mut()
-                .copy_from_slice(page.as_slice());
-            let (_, flags, _) = inner.page_table.query(*vaddr).unwrap();
-
-            page_table
-                .map_region(
-                    *vaddr,
-                    user_phy_page.start_paddr(virt_to_phys),
-                    PAGE_SIZE_4K,
-                    flags,
-                    false,
-                )
-                .unwrap();
-            new_inner.mmap_use.insert(*vaddr, user_phy_page);
+        new_inner.stack_limit = inner.stack_limit;
+        new_inner.auxv = inner.auxv.clone();
+        new_inner.entry = inner.entry;
+        new_inner.tls = inner.tls;
+        for area in inner.areas.values() {
+            let mut new_area =
+                MapArea::new(area.start, area.size, area.flags, area.backing.clone());
+            if let Backing::Lent {
+                frames,
+                vaddr: first,
+            } = &area.backing
+            {
+                // Lent frames belong to other processes, the child gets its own copy.
+                new_area.backing = Backing::Anonymous;
+                for page in 0..area.size / PAGE_SIZE_4K {
+                    let vaddr = area.start + page * PAGE_SIZE_4K;
+                    let paddr =
+                        frames[(vaddr.as_usize() - first.as_usize()) / PAGE_SIZE_4K].paddr();
+                    let mut user_phy_page = GlobalPage::alloc().unwrap();
+                    user_phy_page.as_slice_mut().copy_from_slice(unsafe {
+                        core::slice::from_raw_parts(phys_to_virt(paddr).as_ptr(), PAGE_SIZE_4K)
+                    });
+                    new_area.frames.insert(vaddr, Frame::new(user_phy_page));
+                }
+            } else {
+                // Frames lent at the moment are not copy-on-write, the child gets its own copy.
+                for (vaddr, frame) in &area.frames {
+                    let frame = if frame.lent() {
+                        let mut user_phy_page = GlobalPage::alloc().unwrap();
+                        user_phy_page
+                            .as_slice_mut()
+                            .copy_from_slice(frame.as_slice());
+                        Frame::new(user_phy_page)
+                    } else {
+                        frame.clone()
+                    };
+                    new_area.frames.insert(*vaddr, frame);
+                }
+                if area.flags.contains(MappingFlags::WRITE) {
+                    writable.extend(
+                        area.frames
+                            .keys()
+                            .filter(|vaddr| inner.page_table.query(**vaddr).is_ok()),
+                    );
+                }
+            }
+            new_inner.areas.insert(area.start, new_area);
+        }
+        // The child maps the pages when accessed, the writable ones of the parent
+        // become read-only until written.
+        for vaddr in writable {
+            inner.map_page(vaddr).unwrap();
         }
 
         AddrSpace(SpinNoIrq::new(new_inner))
@@ -455,15 +903,15 @@ impl AddrSpace {
     /// init address space of a process
     pub fn init_global(user_elf: &[u8]) -> AxResult<AddrSpace> {
         let mut user_space = AddrSpaceInner::new();
-        user_space.init_global(user_elf)?;
+        user_space.init_global(user_elf, None)?;
 
         Ok(AddrSpace(SpinNoIrq::new(user_space)))
     }
 
-    /// init address space in current structure.
-    pub fn init_exec(&self, user_elf: &[u8]) -> AxResult<()> {
+    /// init address space in current structure, with the interpreter of the program if it has one.
+    pub fn init_exec(&self, user_elf: &[u8], interp: Option<&[u8]>) -> AxResult<()> {
         let mut new_space = AddrSpaceInner::new();
-        new_space.init_global(user_elf)?;
+        new_space.init_global(user_elf, interp)?;
         *self.0.lock() = new_space;
         Ok(())
     }
@@ -511,6 +959,78 @@ pub fn alloc_user_page(vaddr: VirtAddr, size: usize, flags: MappingFlags) -> Arc
     user_phy_page
 }
 
+/// reserve a user stack of `size` bytes in current process, returns its lowest address
+pub fn alloc_user_stack(size: usize) -> AxResult<VirtAddr> {
+    current_addr_space().lock().add_stack(size)
+}
+
+/// get the limit of user stack sizes of current process
+pub fn stack_limit() -> usize {
+    current_addr_space().lock().stack_limit()
+}
+
+/// get the smallest size of user stacks of new threads of current process
+pub fn min_stack_size() -> usize {
+    current_addr_space().lock().min_stack_size()
+}
+
+/// get the entry point of the program of current process
+pub fn entry() -> VirtAddr {
+    current_addr_space().lock().entry()
+}
+
+/// start addresses of `strings`, each ended by `\0`, copied to `start`
+fn string_addrs(strings: &[u8], start: usize) -> impl Iterator<Item = usize> + '_ {
+    strings
+        .split_inclusive(|c| *c == 0)
+        .scan(start, |addr, string| {
+            let string_addr = *addr;
+            *addr += string.len();
+            Some(string_addr)
+        })
+}
+
+/// put the arguments and environment variables (strings each ended by `\0`) and the
+/// auxiliary vector of current process below `top` on the user stack, as the System V ABI does.
+///
+/// From the returned stack pointer up, there are `argc`, `argv`, `NULL`, `envp`, `NULL`,
+/// the auxiliary vector ended by `AT_NULL`, and then the strings.
+pub fn init_user_stack(top: VirtAddr, args: &[u8], envs: &[u8]) -> VirtAddr {
+    let auxv = current_addr_space().lock().auxv.clone();
+    let args_start = top.as_usize() - args.len() - envs.len();
+    let envs_start = args_start + args.len();
+
+    let argv: Vec<usize> = string_addrs(args, args_start).collect();
+    let mut words = vec![argv.len()];
+    words.extend(argv);
+    words.push(0);
+    words.extend(string_addrs(envs, envs_start));
+    words.push(0);
+    for (key, value) in auxv.iter().chain(&[(AT_NULL, 0)]) {
+        words.extend([*key, *value]);
+    }
+    let data: Vec<u8> = words.iter().flat_map(|word| word.to_ne_bytes()).collect();
+
+    let sp = (args_start - data.len()) & !0xf;
+    copy_byte_buffer_to_user(0, sp as *const u8, &data);
+    copy_byte_buffer_to_user(0, args_start as *const u8, args);
+    copy_byte_buffer_to_user(0, envs_start as *const u8, envs);
+    sp.into()
+}
+
+/// Puts a TLS block of the program of current process right below `top` of a new user stack,
+/// initialized from its template, returns the start of the block as the thread pointer.
+///
+/// Returns `None` if the program has no thread-local storage.
+pub fn init_user_tls(top: VirtAddr) -> Option<VirtAddr> {
+    let tls = current_addr_space().lock().tls?;
+    let tp = (top.as_usize() - tls.mem_size) & !(tls.align.max(16) - 1);
+    let data = copy_slice_from_user(tls.vaddr, tls.file_size);
+    // The rest of the block is on new stack pages, which are zero-filled.
+    copy_byte_buffer_to_user(0, tp as *const u8, &data);
+    Some(tp.into())
+}
+
 /// manage heap space of current process
 pub fn global_sbrk(size: isize) -> Option<usize> {
     current_addr_space().lock().sbrk(size)
@@ -526,11 +1046,36 @@ pub fn mmap_page(addr: Option<VirtAddr>, len: usize, flags: MappingFlags) -> AxR
     current_addr_space().lock().mmap_page(addr, len, flags)
 }
 
+/// map frames lent by others (e.g. a scheme) into current process
+pub fn mmap_lend(frames: &[LentFrame], flags: MappingFlags) -> AxResult<VirtAddr> {
+    current_addr_space().lock().mmap_lend(frames, flags)
+}
+
 /// unmap a page of current process
 pub fn munmap_page(addr: VirtAddr, len: usize) -> AxResult<()> {
     current_addr_space().lock().munmap_page(addr, len)
 }
 
+/// map zero-filled pages into current process, at `addr` if `fixed`
+pub fn mmap(addr: VirtAddr, len: usize, flags: MappingFlags, fixed: bool) -> AxResult<VirtAddr> {
+    current_addr_space().lock().mmap(addr, len, flags, fixed)
+}
+
+/// unmap pages of current process, including parts of mappings
+pub fn munmap(addr: VirtAddr, len: usize) -> AxResult<()> {
+    current_addr_space().lock().munmap(addr, len)
+}
+
+/// change the permissions of pages of current process
+pub fn mprotect(addr: VirtAddr, len: usize, flags: MappingFlags) -> AxResult<()> {
+    current_addr_space().lock().mprotect(addr, len, flags)
+}
+
+/// lend the frames of a buffer of current process
+pub fn query_frames(vaddr: VirtAddr, size: usize, write: bool) -> AxResult<Vec<LentFrame>> {
+    current_addr_space().lock().query_frames(vaddr, size, write)
+}
+
 /// translate a slice from current process
 pub fn translate_buffer(vaddr: VirtAddr, size: usize, write: bool) -> Vec<&'static mut [u8]> {
     current_addr_space()
@@ -549,12 +1094,44 @@ pub fn copy_slice_from_user(vaddr: VirtAddr, size: usize) -> Vec<u8> {
     result
 }
 
+/// copy a slice of current process into kernel address space, fails unless it is in user pages
+pub fn try_copy_slice_from_user(vaddr: VirtAddr, size: usize) -> AxResult<Vec<u8>> {
+    let mut result = Vec::new();
+    let buffers = current_addr_space()
+        .lock()
+        .translate_user_buffer(vaddr, size, false)?;
+    for fragment in &buffers {
+        result.extend_from_slice(fragment);
+    }
+    Ok(result)
+}
+
 /// copy a `str` in current process into kernel address space
 pub fn copy_str_from_user(vaddr: VirtAddr, size: usize) -> String {
     let result = copy_slice_from_user(vaddr, size);
     String::from_utf8(result).expect("Invalid string!")
 }
 
+/// handle a page fault of current process, returns whether the access can be retried
+pub fn handle_page_fault(vaddr: VirtAddr, write: bool) -> bool {
+    current_addr_space().lock().handle_page_fault(vaddr, write)
+}
+
+/// the futex word at `vaddr` of current process, with its key: the address space and `vaddr`,
+/// which stay the same when the frame of the word changes (e.g. copy-on-write)
+pub fn futex_word(vaddr: VirtAddr) -> AxResult<((usize, usize), &'static AtomicU32)> {
+    if !vaddr.is_aligned(4usize) {
+        return ax_err!(InvalidInput);
+    }
+    let addr_space = current_addr_space();
+    // An aligned word is in a single page.
+    let buffers = addr_space
+        .lock()
+        .translate_user_buffer(vaddr, size_of::<u32>(), false)?;
+    let word = unsafe { &*(buffers[0].as_ptr() as *const AtomicU32) };
+    Ok(((Arc::as_ptr(&addr_space) as usize, vaddr.as_usize()), word))
+}
+
 /// translate a virtual address to phyical address of current process
 pub fn translate_addr(vaddr: VirtAddr) -> Option<PhysAddr> {
     current_addr_space().lock().query(vaddr)
@@ -564,7 +1141,7 @@ pub fn translate_addr(vaddr: VirtAddr) -> Option<PhysAddr> {
 // Copied from my code in rCore
 pub fn copy_byte_buffer_to_user(_token: usize, ptr: *const u8, data: &[u8]) {
     let copy_len = data.len();
-    let dst = translate_buffer((ptr as usize).into(), copy_len, false);
+    let dst = translate_buffer((ptr as usize).into(), copy_len, true);
     let mut offset = 0;
     for dst_space in dst {
         let dst_len = dst_space.len();
@@ -574,6 +1151,24 @@ pub fn copy_byte_buffer_to_user(_token: usize, ptr: *const u8, data: &[u8]) {
     assert_eq!(copy_len, offset);
 }
 
+/// Copy a `data' with type `T' into position `ptr' of current process, fails unless it is in writable user pages
+pub fn try_copy_data_to_user<T>(ptr: *const u8, data: &T) -> AxResult<()> {
+    let data_ptr = data as *const T as *const u8;
+    let data = unsafe { core::slice::from_raw_parts(data_ptr, core::mem::size_of::<T>()) };
+    let dst = current_addr_space().lock().translate_user_buffer(
+        (ptr as usize).into(),
+        data.len(),
+        true,
+    )?;
+    let mut offset = 0;
+    for dst_space in dst {
+        let dst_len = dst_space.len();
+        dst_space.copy_from_slice(&data[offset..offset + dst_len]);
+        offset += dst_len;
+    }
+    Ok(())
+}
+
 /// Copy a `data' with type `T' from current memory space into position `ptr' of the userspace `token' (not used)
 // Copied from my code in rCore
 pub fn copy_data_to_user<T>(token: usize, ptr: *const u8, data: &T) {
@@ -6,19 +6,25 @@ extern crate alloc;
 #[macro_use]
 extern crate axlog;
 
+pub mod signal;
+
 use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
 
 use alloc::{
+    string::String,
     sync::{Arc, Weak},
     vec,
     vec::Vec,
 };
-use axerrno::AxResult;
+use axerrno::{ax_err, AxError, AxResult};
 use axmem::AddrSpace;
-use axscheme::FileTable;
-use axtask::{current, current_task, yield_now, AxTaskRef};
+use axscheme::{Credentials, FileTable, SchemeNamespace};
+use axtask::{current, current_task, AxTaskRef, WaitQueue};
 use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
+use syscall_number::{signal::SIGCHLD, wait::WNOHANG};
+
+use signal::SignalState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct Pid(u64);
@@ -41,9 +47,14 @@ struct AxProcess {
     child: SpinNoIrq<Vec<Arc<AxProcess>>>,
     addr_space: Arc<AddrSpace>,
     file_table: Arc<FileTable>,
+    namespace: SpinNoIrq<SchemeNamespace>,
+    cred: SpinNoIrq<Credentials>,
+    signal: SpinNoIrq<SignalState>,
     tasks: SpinNoIrq<Vec<AxTaskRef>>,
     exit_code: AtomicI32,
     exited: AtomicBool,
+    /// notified when a child exits
+    child_exit_wq: WaitQueue,
 }
 
 static PROCESS_TABLE: LazyInit<SpinNoIrq<Vec<Arc<AxProcess>>>> = LazyInit::new();
@@ -71,9 +82,14 @@ pub fn init() {
         child: SpinNoIrq::new(Vec::new()),
         addr_space: Arc::new(user_space),
         file_table: Arc::new(FileTable::new()),
+        namespace: SpinNoIrq::new(SchemeNamespace::ROOT),
+        // init runs as root
+        cred: SpinNoIrq::new(Credentials::default()),
+        signal: SpinNoIrq::new(SignalState::new()),
         tasks: SpinNoIrq::new(Vec::new()),
         exit_code: AtomicI32::new(0),
         exited: AtomicBool::new(false),
+        child_exit_wq: WaitQueue::new(),
     });
 
     let process_table = vec![init_process.clone()];
@@ -110,9 +126,13 @@ pub fn fork() -> usize {
         child: SpinNoIrq::new(Vec::new()),
         addr_space: Arc::new(current.addr_space.as_ref().clone()),
         file_table: Arc::new(current.file_table.as_ref().clone()),
+        namespace: SpinNoIrq::new(*current.namespace.lock()),
+        cred: SpinNoIrq::new(*current.cred.lock()),
+        signal: SpinNoIrq::new(current.signal.lock().fork()),
         tasks: SpinNoIrq::new(Vec::new()),
         exit_code: AtomicI32::new(0),
         exited: AtomicBool::new(false),
+        child_exit_wq: WaitQueue::new(),
     });
 
     current.child.lock().push(res.clone());
@@ -127,108 +147,263 @@ pub fn add_task(task: AxTaskRef) {
     current_process().tasks.lock().push(task)
 }
 
-/// Exit current process
+/// Exits current task, and the process if it is the last task.
+///
+/// The process exits with `code`, unless it is terminated already by [`exit_group`]
+/// or a signal. Its parent is notified then, and may reap it with [`wait`].
 pub fn exit_current(code: i32) {
     trace!("exit");
     let task = current();
     let process = current_process();
-    let id = process
-        .tasks
-        .lock()
-        .iter()
-        .enumerate()
-        .find(|(_, task_i)| task.id() == task_i.id())
-        .unwrap()
-        .0;
-
-    if id == 0 {
-        if process.pid.0 == 1 {
+    let (task, last) = {
+        let mut tasks = process.tasks.lock();
+        // The tasks are dropped from the list by `exec` of another task.
+        let Some(id) = tasks.iter().position(|task_i| task.id() == task_i.id()) else {
+            return;
+        };
+        if id == 0 && process.pid.0 == 1 {
             // Directly call termination in axtask
             return;
         }
-        // main process
-        // Wait all tasks to complete
-        // TODO: use signals to kill
-        process
-            .tasks
-            .lock()
-            .iter()
-            .skip(1) // exclude current task
-            .for_each(|task_i| {
-                task_i.join();
-            });
-
-        // make all child zombie
-        process.child.lock().iter_mut().for_each(|child_process| {
-            *child_process.parent.lock() = Arc::downgrade(&INIT_PROCESS);
-            INIT_PROCESS.child.lock().push(child_process.clone())
-        });
-        process.child.lock().clear();
-        process.tasks.lock().clear();
-
-        process.exit_code.store(code, Ordering::Release);
-        process.exited.store(true, Ordering::Release);
-        // remove from process table
-        PROCESS_TABLE
-            .lock()
-            .drain_filter(|process_inner| process_inner.pid == process.pid);
+        let task = tasks.remove(id);
+        (task, tasks.is_empty())
+    };
 
-        // There is no need to prevent resource (memory space) to be released before task switch
-        // as one reference is held by its parent
-    } else {
-        // others
-        let task = process.tasks.lock().remove(id);
+    if !last {
         task.on_exit(|vaddr| {
-            process.addr_space.lock().remove_region(vaddr).unwrap();
+            // The stack may have been unmapped by the program already.
+            let _ = process.addr_space.lock().remove_region(vaddr);
         });
-        debug!("{}", process.tasks.lock().len());
+        return;
     }
+
+    // make all child zombie
+    process.child.lock().iter_mut().for_each(|child_process| {
+        *child_process.parent.lock() = Arc::downgrade(&INIT_PROCESS);
+        INIT_PROCESS.child.lock().push(child_process.clone())
+    });
+    process.child.lock().clear();
+    // Some of them may have exited already.
+    INIT_PROCESS.child_exit_wq.notify_all(false);
+    // Close files now rather than when the parent reaps the process,
+    // so that the schemes served by it go away.
+    process.file_table.clear();
+
+    let code = process.signal.lock().exiting().unwrap_or(code);
+    process.exit_code.store(code, Ordering::Release);
+    process.exited.store(true, Ordering::Release);
+    if let Some(parent) = process.parent.lock().upgrade() {
+        parent.send_signal(SIGCHLD);
+        parent.child_exit_wq.notify_all(false);
+    }
+    // remove from process table
+    PROCESS_TABLE
+        .lock()
+        .drain_filter(|process_inner| process_inner.pid == process.pid);
+
+    // There is no need to prevent resource (memory space) to be released before task switch
+    // as one reference is held by its parent
+}
+
+/// Exits current process with `code`: its other tasks are killed, and exit
+/// on their way back to user space.
+///
+/// The process is reaped once the last task exits, see [`exit_current`].
+pub fn exit_group(code: i32) {
+    trace!("exit_group");
+    current_process().set_exiting(code);
+    exit_current(code);
 }
 
-/// Wait for a child process to stop.
-pub fn wait(_pid: u64) -> (u64, i32) {
-    trace!("wait");
+/// Wait for a child process to stop, returns its pid and exit code.
+///
+/// `pid` is the child to wait for, `-1` or `0` for any child (there are no process groups).
+/// With [`WNOHANG`] in `options`, returns `None` if no such child has exited yet,
+/// otherwise blocks until one exits.
+pub fn wait(pid: isize, options: usize) -> AxResult<Option<(u64, i32)>> {
+    trace!("wait {}", pid);
     let process = current_process();
+    let matches = |child: &Arc<AxProcess>| pid <= 0 || child.pid.0 == pid as u64;
+    let exited = |child: &Arc<AxProcess>| matches(child) && child.exited.load(Ordering::Acquire);
     loop {
-        if let Some((id, process_i)) = process
-            .child
-            .lock()
-            .iter()
-            .enumerate()
-            .find(|(_, process_i)| process_i.exited.load(Ordering::Acquire))
         {
-            let ret = (process_i.pid.0, process_i.exit_code.load(Ordering::Acquire));
-            process.child.lock().remove(id);
-            return ret;
+            let mut children = process.child.lock();
+            if !children.iter().any(matches) {
+                return ax_err!(NoChildProcess);
+            }
+            if let Some(id) = children.iter().position(exited) {
+                let child = children.remove(id);
+                return Ok(Some((child.pid.0, child.exit_code.load(Ordering::Acquire))));
+            }
         }
+        if options & WNOHANG != 0 {
+            return Ok(None);
+        }
+        // The children are only removed by this process, so the matching ones are still there.
+        if !process
+            .child_exit_wq
+            .wait_until_interruptible(|| process.child.lock().iter().any(exited))
+        {
+            return ax_err!(Interrupted);
+        }
+    }
+}
+
+/// Gets the credentials of current process
+pub fn credentials() -> Credentials {
+    *current_process().cred.lock()
+}
+
+/// Sets the user id of current process.
+///
+/// Root (effective uid 0) sets both the real and effective uid,
+/// others may only set the effective uid back to the real uid.
+pub fn setuid(uid: u32) -> AxResult<()> {
+    let process = current_process();
+    let mut cred = process.cred.lock();
+    if cred.euid == 0 {
+        cred.uid = uid;
+        cred.euid = uid;
+    } else if uid == cred.uid {
+        cred.euid = uid;
+    } else {
+        return ax_err!(PermissionDenied);
+    }
+    Ok(())
+}
 
-        yield_now();
+/// Sets the group id of current process, with the same rules as [`setuid`].
+pub fn setgid(gid: u32) -> AxResult<()> {
+    let process = current_process();
+    let mut cred = process.cred.lock();
+    if cred.euid == 0 {
+        cred.gid = gid;
+        cred.egid = gid;
+    } else if gid == cred.gid {
+        cred.egid = gid;
+    } else {
+        return ax_err!(PermissionDenied);
     }
+    Ok(())
 }
 
-/// exec syscall
+/// exec syscall, `args` and `envs` are strings each ended by `\0`.
+/// A program with an interpreter (`PT_INTERP`) is started by the interpreter, read by its path.
+/// The other tasks of the process exit before the program is replaced.
 /// only returns on error
-pub fn exec(elf_data: Vec<u8>) -> isize {
+pub fn exec(elf_data: Vec<u8>, args: Vec<u8>, envs: Vec<u8>) -> isize {
     trace!("exec");
-    fn exec_inner(elf_data: Vec<u8>) -> AxResult<()> {
+    fn exec_inner(elf_data: Vec<u8>, args: &[u8], envs: &[u8]) -> AxResult<()> {
+        let terminated = |strings: &[u8]| strings.last().map_or(true, |c| *c == 0);
+        if args.len() + envs.len() > axmem::ARG_MAX || !terminated(args) || !terminated(envs) {
+            return ax_err!(InvalidInput);
+        }
+        // Dynamically linked programs are run by their interpreter, which loads the libraries.
+        let interp = match elf_loader::ElfInfo::new(&elf_data).and_then(|info| info.interp) {
+            Some(path) => Some(axscheme::read_file(&path, PROGRAM_MAX)?),
+            None => None,
+        };
         let process = current_process();
-        process.addr_space.init_exec(&elf_data)?;
+        // The other tasks run in the address space to be replaced.
+        stop_other_tasks(&process)?;
+        process.addr_space.init_exec(&elf_data, interp.as_deref())?;
         process.file_table.reset();
+        process.signal.lock().exec();
         process.tasks.lock().clear();
         Ok(())
         // elf_data should be successfully dropped
     }
 
-    if exec_inner(elf_data).is_err() {
-        return -1;
+    if let Err(e) = exec_inner(elf_data, &args, &envs) {
+        return axerrno::to_ret_code(Err(e));
     }
     let process = current_process();
 
-    axtask::handle_exec(|task| process.tasks.lock().push(task))
+    axtask::handle_exec(args, envs, |task| process.tasks.lock().push(task))
+}
+
+/// Kills the tasks of `process` other than the current one, and waits until they exit.
+///
+/// Fails with `Interrupted` if the current task is killed meanwhile.
+fn stop_other_tasks(process: &AxProcess) -> AxResult<()> {
+    let current = current();
+    let others: Vec<AxTaskRef> = process
+        .tasks
+        .lock()
+        .iter()
+        .filter(|task| task.id() != current.id())
+        .cloned()
+        .collect();
+    for task in &others {
+        axtask::kill(task);
+    }
+    for task in &others {
+        task.join().ok_or(AxError::Interrupted)?;
+    }
+    Ok(())
+}
+
+/// Most interpreter lines (`#!`) followed in a row by [`execve`]
+const MAX_INTERPRETERS: usize = 4;
+/// Size limit of the programs (and scripts) run by [`execve`]
+const PROGRAM_MAX: usize = 64 << 20;
+
+/// execve syscall, runs the program at `path`, read through the schemes of current process.
+///
+/// A file starting with `#!interpreter [arg]` runs the interpreter instead,
+/// with the arguments `interpreter [arg] path args[1..]`.
+/// only returns on error
+pub fn execve(path: &str, args: Vec<u8>, envs: Vec<u8>) -> isize {
+    trace!("execve {}", path);
+    match load(path, args) {
+        Ok((elf_data, args)) => exec(elf_data, args, envs),
+        Err(e) => axerrno::to_ret_code(Err(e)),
+    }
+}
+
+/// Reads the program at `path`, following interpreter lines, returns it with its arguments.
+fn load(path: &str, mut args: Vec<u8>) -> AxResult<(Vec<u8>, Vec<u8>)> {
+    let mut path = String::from(path);
+    for _ in 0..=MAX_INTERPRETERS {
+        let data = axscheme::read_file(&path, PROGRAM_MAX)?;
+        let Some(line) = data.strip_prefix(b"#!") else {
+            return Ok((data, args));
+        };
+        let line = &line[..line.iter().position(|c| *c == b'\n').unwrap_or(line.len())];
+        let line = core::str::from_utf8(line)
+            .map_err(|_| AxError::InvalidData)?
+            .trim();
+        let (interpreter, arg) = match line.split_once(|c| c == ' ' || c == '\t') {
+            Some((interpreter, arg)) => (interpreter, arg.trim()),
+            None => (line, ""),
+        };
+        if interpreter.is_empty() || line.contains('\0') {
+            return ax_err!(InvalidData);
+        }
+        // The interpreter (with its argument) and the script replace `args[0]`.
+        let rest = match args.iter().position(|c| *c == 0) {
+            Some(end) => &args[end + 1..],
+            None => &[][..],
+        };
+        let mut interpreter_args = Vec::new();
+        for string in [interpreter, arg, path.as_str()] {
+            if !string.is_empty() {
+                interpreter_args.extend_from_slice(string.as_bytes());
+                interpreter_args.push(0);
+            }
+        }
+        interpreter_args.extend_from_slice(rest);
+        args = interpreter_args;
+        path = String::from(interpreter);
+    }
+    // Too many interpreters, probably a loop
+    ax_err!(InvalidData)
 }
 
 struct CurrentAddrSpaceImpl;
 struct CurrentFileTableImpl;
+struct CurrentNamespaceImpl;
+struct CurrentCredentialsImpl;
 struct FindAddrSpaceImpl;
 
 #[crate_interface::impl_interface]
@@ -245,6 +420,23 @@ impl axscheme::CurrentFileTable for CurrentFileTableImpl {
     }
 }
 
+#[crate_interface::impl_interface]
+impl axscheme::CurrentNamespace for CurrentNamespaceImpl {
+    fn current_namespace() -> SchemeNamespace {
+        *current_process().namespace.lock()
+    }
+    fn set_current_namespace(ns: SchemeNamespace) {
+        *current_process().namespace.lock() = ns;
+    }
+}
+
+#[crate_interface::impl_interface]
+impl axscheme::CurrentCredentials for CurrentCredentialsImpl {
+    fn current_credentials() -> Credentials {
+        credentials()
+    }
+}
+
 #[crate_interface::impl_interface]
 impl axscheme::FindAddrSpace for FindAddrSpaceImpl {
     fn find_addr_space(pid: u64) -> Option<Arc<AddrSpace>> {
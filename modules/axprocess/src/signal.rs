@@ -0,0 +1,360 @@
+//! POSIX-style signals
+//!
+//! Signals are pending per process, and one of them is delivered whenever a task
+//! of the process returns to user space (after a syscall or an interrupt).
+//! A user handler is called on the user stack, below a frame with the
+//! interrupted context, and returns to `restorer`, which calls `sigreturn`.
+//!
+//! A process terminated by a signal exits with code `128 + signal`,
+//! after all of its tasks have returned to user space.
+//! Blocked tasks are woken up for it, as a signal terminating the process
+//! kills its tasks once it is sent.
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+use core::mem::size_of;
+
+use axerrno::{ax_err, AxError, AxResult};
+use axhal::arch::TrapFrame;
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+use axmem::{try_copy_data_to_user, try_copy_slice_from_user};
+use syscall_number::signal::*;
+
+use super::{credentials, current_process, exit_current, find, AxProcess, Pid};
+
+const fn sigmask(sig: usize) -> u64 {
+    1 << sig
+}
+
+/// Whether user handlers can be called, the context switch is only written for RISC-V.
+const HANDLERS_SUPPORTED: bool = cfg!(any(target_arch = "riscv32", target_arch = "riscv64"));
+
+/// Signals that can be neither blocked nor caught
+const UNCATCHABLE: u64 = sigmask(SIGKILL) | sigmask(SIGSTOP);
+
+/// Signals ignored by default, others terminate the process.
+///
+/// Stopping is not supported, so the stop signals are ignored.
+const IGNORED_BY_DEFAULT: u64 = sigmask(SIGCHLD)
+    | sigmask(SIGCONT)
+    | sigmask(SIGURG)
+    | sigmask(SIGWINCH)
+    | sigmask(SIGSTOP)
+    | sigmask(SIGTSTP)
+    | sigmask(SIGTTIN)
+    | sigmask(SIGTTOU);
+
+/// Signal states of a process
+pub(crate) struct SignalState {
+    pending: u64,
+    blocked: u64,
+    actions: [SigAction; NSIG],
+    /// exit code, once the process is terminated by a signal
+    exiting: Option<i32>,
+}
+
+impl SignalState {
+    pub(crate) fn new() -> Self {
+        SignalState {
+            pending: 0,
+            blocked: 0,
+            actions: [SigAction::default(); NSIG],
+            exiting: None,
+        }
+    }
+
+    /// States of a forked child: actions and blocked signals are inherited,
+    /// pending signals are not.
+    pub(crate) fn fork(&self) -> Self {
+        SignalState {
+            pending: 0,
+            blocked: self.blocked,
+            actions: self.actions,
+            exiting: None,
+        }
+    }
+
+    /// Handlers are gone after `exec`, so they are reset to the default action.
+    pub(crate) fn exec(&mut self) {
+        for action in self.actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SigAction::default();
+            }
+        }
+    }
+
+    /// Exit code of the process, once it is terminated
+    pub(crate) fn exiting(&self) -> Option<i32> {
+        self.exiting
+    }
+
+    /// Whether `sig` terminates the process once it is sent, by its default action
+    fn terminates(&self, sig: usize) -> bool {
+        self.blocked & sigmask(sig) == 0
+            && self.actions[sig].handler == SIG_DFL
+            && IGNORED_BY_DEFAULT & sigmask(sig) == 0
+    }
+
+    /// Takes the lowest pending signal that is not blocked
+    fn take(&mut self) -> Option<usize> {
+        let deliverable = self.pending & !self.blocked;
+        if deliverable == 0 {
+            return None;
+        }
+        let sig = deliverable.trailing_zeros() as usize;
+        self.pending &= !sigmask(sig);
+        Some(sig)
+    }
+}
+
+impl AxProcess {
+    pub(crate) fn send_signal(&self, sig: usize) {
+        self.signal.lock().pending |= sigmask(sig);
+    }
+
+    /// Terminates the process with `code`, unless it is terminated already.
+    ///
+    /// Other tasks than the current one are killed, so that the blocked ones
+    /// wake up and return to user space, where they exit.
+    pub(crate) fn set_exiting(&self, code: i32) {
+        self.signal.lock().exiting.get_or_insert(code);
+        let current = axtask::current();
+        let tasks = self.tasks.lock().clone();
+        for task in tasks.iter().filter(|task| task.id() != current.id()) {
+            axtask::kill(task);
+        }
+    }
+}
+
+/// Saved on the user stack while a handler runs, restored by [`sigreturn`]
+#[repr(C)]
+#[derive(Clone)]
+struct SignalFrame {
+    /// the interrupted context
+    tf: TrapFrame,
+    /// blocked signals before the handler
+    blocked: u64,
+}
+
+fn check_signal(sig: usize) -> AxResult<()> {
+    if sig == 0 || sig >= NSIG {
+        return ax_err!(InvalidInput);
+    }
+    Ok(())
+}
+
+/// Sends signal `sig` to process `pid`, `0` only checks whether it can be sent.
+///
+/// Root may signal any process, others only the processes of the same (real) user.
+pub fn kill(pid: u64, sig: usize) -> AxResult<()> {
+    if sig != 0 {
+        check_signal(sig)?;
+    }
+    let target = find(Pid(pid)).ok_or(AxError::NotFound)?;
+    let cred = credentials();
+    let target_uid = target.cred.lock().uid;
+    if cred.euid != 0 && cred.euid != target_uid && cred.uid != target_uid {
+        return ax_err!(PermissionDenied);
+    }
+    if sig != 0 {
+        target.send_signal(sig);
+        // Blocked tasks would not return to user space to take the signal.
+        let terminates = target.signal.lock().terminates(sig);
+        if terminates && target.pid != Pid(1) {
+            target.set_exiting(128 + sig as i32);
+        }
+    }
+    Ok(())
+}
+
+/// Gets the action on signal `sig` of current process, and sets it to `action` if given.
+///
+/// Installing a handler fails with `Unsupported` on architectures other than RISC-V.
+pub fn sigaction(sig: usize, action: Option<SigAction>) -> AxResult<SigAction> {
+    check_signal(sig)?;
+    let process = current_process();
+    let mut state = process.signal.lock();
+    let old = state.actions[sig];
+    if let Some(action) = action {
+        if UNCATCHABLE & sigmask(sig) != 0 {
+            return ax_err!(InvalidInput);
+        }
+        if !HANDLERS_SUPPORTED && action.handler != SIG_DFL && action.handler != SIG_IGN {
+            return ax_err!(Unsupported);
+        }
+        state.actions[sig] = action;
+    }
+    Ok(old)
+}
+
+/// Gets the blocked signals of current process, and changes them with `set` if given
+pub fn sigprocmask(how: usize, set: Option<u64>) -> AxResult<u64> {
+    let process = current_process();
+    let mut state = process.signal.lock();
+    let old = state.blocked;
+    if let Some(set) = set {
+        state.blocked = match how {
+            SIG_BLOCK => old | set,
+            SIG_UNBLOCK => old & !set,
+            SIG_SETMASK => set,
+            _ => return ax_err!(InvalidInput),
+        } & !UNCATCHABLE;
+    }
+    Ok(old)
+}
+
+/// Sends `sig` to current process for a fault it caused.
+///
+/// The fault would happen again if the signal were blocked or ignored,
+/// so the default action is taken in that case.
+pub fn send_fault_signal(sig: usize) {
+    let process = current_process();
+    if process.pid == Pid(1) {
+        halt_init(sig);
+    }
+    let mut state = process.signal.lock();
+    if state.blocked & sigmask(sig) != 0 || state.actions[sig].handler == SIG_IGN {
+        state.blocked &= !sigmask(sig);
+        state.actions[sig] = SigAction::default();
+    }
+    state.pending |= sigmask(sig);
+}
+
+/// init cannot go on after a fault, nor be terminated.
+fn halt_init(sig: usize) -> ! {
+    error!("init process faulted with signal {}, halting", sig);
+    axhal::misc::terminate()
+}
+
+/// Terminates current process with SIGSEGV, as a signal frame cannot be
+/// pushed to or popped from its user stack.
+fn force_segv(process: &AxProcess) -> ! {
+    process.signal.lock().actions[SIGSEGV] = SigAction::default();
+    if process.pid == Pid(1) {
+        halt_init(SIGSEGV);
+    }
+    let code = 128 + SIGSEGV as i32;
+    process.set_exiting(code);
+    terminate(code)
+}
+
+/// Exits current task, and the process if it is the last task.
+fn terminate(code: i32) -> ! {
+    axhal::arch::enable_irqs();
+    exit_current(code);
+    axtask::exit(code)
+}
+
+/// Delivers a pending signal of current process before returning to user space with `tf`.
+pub fn handle_signals(tf: &mut TrapFrame) {
+    // The process may be gone while its other tasks are still running.
+    let Some(process) = axtask::current_pid().and_then(|pid| find(Pid(pid))) else {
+        return;
+    };
+    loop {
+        let mut state = process.signal.lock();
+        if let Some(code) = state.exiting {
+            drop(state);
+            terminate(code);
+        }
+        // Killed by `exec` of another task, the process goes on without this task.
+        if axtask::current().is_killed() {
+            drop(state);
+            terminate(0);
+        }
+        let Some(sig) = state.take() else {
+            return;
+        };
+        let action = state.actions[sig];
+        match action.handler {
+            SIG_IGN => continue,
+            // init cannot be killed
+            SIG_DFL if IGNORED_BY_DEFAULT & sigmask(sig) != 0 || process.pid == Pid(1) => continue,
+            SIG_DFL => {
+                let code = 128 + sig as i32;
+                drop(state);
+                process.set_exiting(code);
+                terminate(code);
+            }
+            handler => {
+                let frame = SignalFrame {
+                    tf: tf.clone(),
+                    blocked: state.blocked,
+                };
+                if action.flags & SA_NODEFER == 0 {
+                    state.blocked |= sigmask(sig);
+                }
+                state.blocked = (state.blocked | action.mask) & !UNCATCHABLE;
+                if action.flags & SA_RESETHAND != 0 {
+                    state.actions[sig] = SigAction::default();
+                }
+                drop(state);
+                if enter_handler(tf, &frame, handler, sig, action.restorer).is_err() {
+                    force_segv(&process);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Restores the context saved before calling a signal handler from the user stack.
+///
+/// Returns the restored `a0`, as the return value of the syscall is put there.
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+pub fn sigreturn() -> isize {
+    let tf = unsafe { &mut *axhal::trap::get_current_trap_frame() };
+    let Ok(data) = try_copy_slice_from_user(user_sp(tf).into(), size_of::<SignalFrame>()) else {
+        force_segv(&current_process());
+    };
+    let frame = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const SignalFrame) };
+    current_process().signal.lock().blocked = frame.blocked & !UNCATCHABLE;
+    restore_context(tf, &frame.tf)
+}
+
+/// No handler is ever called, so there is no context to restore.
+#[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+pub fn sigreturn() -> isize {
+    axerrno::to_ret_code(ax_err!(Unsupported))
+}
+
+// TODO: make a HAL wrapper
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+fn user_sp(tf: &TrapFrame) -> usize {
+    tf.regs.sp
+}
+
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+fn enter_handler(
+    tf: &mut TrapFrame,
+    frame: &SignalFrame,
+    handler: usize,
+    sig: usize,
+    restorer: usize,
+) -> AxResult<()> {
+    let sp = (tf.regs.sp.wrapping_sub(size_of::<SignalFrame>())) & !0xf;
+    try_copy_data_to_user(sp as *const u8, frame)?;
+    tf.regs.sp = sp;
+    tf.regs.a0 = sig;
+    tf.regs.ra = restorer;
+    tf.sepc = handler;
+    Ok(())
+}
+
+/// Only user registers are restored, the others are not trusted.
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+fn restore_context(tf: &mut TrapFrame, saved: &TrapFrame) -> isize {
+    tf.regs = saved.regs.clone();
+    tf.sepc = saved.sepc;
+    tf.regs.a0 as isize
+}
+
+/// Never called, as handlers cannot be installed (see [`sigaction`]).
+#[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+fn enter_handler(
+    _tf: &mut TrapFrame,
+    _frame: &SignalFrame,
+    _handler: usize,
+    _sig: usize,
+    _restorer: usize,
+) -> AxResult<()> {
+    ax_err!(Unsupported)
+}
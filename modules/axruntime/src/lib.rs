@@ -203,7 +203,7 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         axscheme::init_scheme();
     }
 
-    #[cfg(any(feature = "user-net", feature = "user-fs"))]
+    #[cfg(any(feature = "user-net", feature = "user-fs", feature = "user-display"))]
     {
         let all_devices = axdriver::init_drivers();
         axscheme::dev::init(all_devices);
@@ -378,7 +378,7 @@ fn init_user_space(page_table: &mut PageTable) -> Result<(), axhal::paging::Pagi
         user_phy_page.zero();
 
         // copy user content
-        user_phy_page.as_slice_mut()[..segment.data.len()].copy_from_slice(segment.data);
+        user_phy_page.as_slice_mut()[..segment.data.len()].copy_from_slice(&segment.data);
         debug!(
             "{:x} {:x}",
             user_phy_page.as_slice()[0],
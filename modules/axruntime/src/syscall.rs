@@ -59,7 +59,9 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
         }
         #[cfg(feature = "scheme")]
         file_syscall if file_syscall & SYS_CLASS != 0 => axscheme::syscall_handler(id, params),
-        SYS_EXIT => {
+        #[cfg(feature = "scheme")]
+        SYS_MKNS | SYS_SETNS | SYS_PIPE2 => axscheme::syscall_handler(id, params),
+        SYS_EXIT | SYS_EXIT_GROUP => {
             unsafe {
                 if USER_BUFFER.is_init() {
                     USER_BUFFER.get_mut_unchecked().flush();
@@ -68,14 +70,31 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
             axlog::info!("task exit with code {}", params[0] as isize);
 
             #[cfg(feature = "process")]
-            axprocess::exit_current(params[0] as i32);
+            if id == SYS_EXIT_GROUP {
+                axprocess::exit_group(params[0] as i32);
+            } else {
+                axprocess::exit_current(params[0] as i32);
+            }
 
             axtask::exit(params[0] as i32);
         }
         #[cfg(feature = "user-paging")]
         SYS_SPAWN => {
+            // The size of the user stack, `0` for the default
+            let stack_size = match params[2] {
+                0 => axmem::USTACK_SIZE,
+                size => size,
+            };
+            // The TLS block of the thread is put on its stack as well.
+            if stack_size < axmem::min_stack_size() || stack_size > axmem::stack_limit() {
+                return axerrno::to_ret_code(Err(axerrno::AxError::InvalidInput));
+            }
+            // The stack may not fit in the address space
             #[allow(unused_variables)]
-            let task = axtask::spawn_args(params[0], params[1]);
+            let task = match axtask::spawn_args(params[0], params[1], stack_size) {
+                Ok(task) => task,
+                Err(_) => return axerrno::to_ret_code(Err(axerrno::AxError::NoMemory)),
+            };
 
             #[cfg(feature = "process")]
             axprocess::add_task(task);
@@ -104,10 +123,37 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
                 -1
             }
         }
+        #[cfg(feature = "user-paging")]
+        SYS_MMAP => {
+            use syscall_number::mmap::MapFlags;
+            let flags = MapFlags::from_bits_truncate(params[2]);
+            // Anonymous mappings are private, they are not shared with forked processes.
+            if flags.contains(MapFlags::MAP_SHARED) {
+                return axerrno::to_ret_code(Err(axerrno::AxError::InvalidInput));
+            }
+            axerrno::to_ret_code(
+                axmem::mmap(
+                    params[0].into(),
+                    params[1],
+                    mapping_flags(flags),
+                    flags.contains(MapFlags::MAP_FIXED),
+                )
+                .map(|vaddr| vaddr.as_usize()),
+            )
+        }
+        #[cfg(feature = "user-paging")]
+        SYS_MUNMAP => axerrno::to_ret_code(axmem::munmap(params[0].into(), params[1]).map(|_| 0)),
+        #[cfg(feature = "user-paging")]
+        SYS_MPROTECT => {
+            let flags = syscall_number::mmap::MapFlags::from_bits_truncate(params[2]);
+            axerrno::to_ret_code(
+                axmem::mprotect(params[0].into(), params[1], mapping_flags(flags)).map(|_| 0),
+            )
+        }
         #[cfg(feature = "futex")]
         SYS_FUTEX => {
-            if let Some(phy_addr) = axmem::translate_addr(params[0].into()) {
-                axsync::futex::futex_call(phy_addr.into(), params[1], params[2] as u32)
+            if let Ok((key, word)) = axmem::futex_word(params[0].into()) {
+                axsync::futex::futex_call(key, word, params[1], params[2] as u32)
             } else {
                 -1
             }
@@ -116,17 +162,119 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
         SYS_FORK => axprocess::fork() as isize,
 
         #[cfg(feature = "process")]
-        SYS_WAIT => {
-            let (id, code) = axprocess::wait(params[0] as u64);
-            copy_data_to_user(0, params[1] as *const u8, &code);
-            id as isize
-        }
+        SYS_WAIT => axerrno::to_ret_code(axprocess::wait(params[0] as isize, params[2]).map(
+            |ret| match ret {
+                Some((id, code)) => {
+                    copy_data_to_user(0, params[1] as *const u8, &code);
+                    id as usize
+                }
+                None => 0,
+            },
+        )),
 
         #[cfg(feature = "process")]
-        SYS_EXEC => {
-            let data = copy_slice_from_user(params[0].into(), params[1]);
-            axprocess::exec(data)
+        SYS_EXEC => exec(&params).unwrap_or_else(|e| axerrno::to_ret_code(Err(e))),
+        #[cfg(feature = "process")]
+        SYS_EXECVE => execve(&params).unwrap_or_else(|e| axerrno::to_ret_code(Err(e))),
+        #[cfg(feature = "process")]
+        SYS_GETPID => axtask::current_pid().unwrap_or(0) as isize,
+        #[cfg(feature = "process")]
+        SYS_KILL => {
+            axerrno::to_ret_code(axprocess::signal::kill(params[0] as u64, params[1]).map(|_| 0))
+        }
+        #[cfg(feature = "process")]
+        SYS_SIGACTION => {
+            let action = (params[1] != 0).then(|| {
+                use syscall_number::signal::SigAction;
+                let data =
+                    copy_slice_from_user(params[1].into(), core::mem::size_of::<SigAction>());
+                unsafe { core::ptr::read_unaligned(data.as_ptr() as *const SigAction) }
+            });
+            axerrno::to_ret_code(axprocess::signal::sigaction(params[0], action).map(|old| {
+                if params[2] != 0 {
+                    copy_data_to_user(0, params[2] as *const u8, &old);
+                }
+                0
+            }))
+        }
+        #[cfg(feature = "process")]
+        SYS_SIGPROCMASK => {
+            let set = (params[1] != 0).then(|| {
+                let data = copy_slice_from_user(params[1].into(), core::mem::size_of::<u64>());
+                unsafe { core::ptr::read_unaligned(data.as_ptr() as *const u64) }
+            });
+            axerrno::to_ret_code(axprocess::signal::sigprocmask(params[0], set).map(|old| {
+                if params[2] != 0 {
+                    copy_data_to_user(0, params[2] as *const u8, &old);
+                }
+                0
+            }))
         }
+        #[cfg(feature = "process")]
+        SYS_SIGRETURN => axprocess::signal::sigreturn(),
+        #[cfg(feature = "process")]
+        SYS_GETUID => axprocess::credentials().uid as isize,
+        #[cfg(feature = "process")]
+        SYS_GETEUID => axprocess::credentials().euid as isize,
+        #[cfg(feature = "process")]
+        SYS_GETGID => axprocess::credentials().gid as isize,
+        #[cfg(feature = "process")]
+        SYS_GETEGID => axprocess::credentials().egid as isize,
+        #[cfg(feature = "process")]
+        SYS_SETUID => axerrno::to_ret_code(axprocess::setuid(params[0] as u32).map(|_| 0)),
+        #[cfg(feature = "process")]
+        SYS_SETGID => axerrno::to_ret_code(axprocess::setgid(params[0] as u32).map(|_| 0)),
         _ => -1,
     }
 }
+
+/// Copies the arguments and environment variables of `exec` from user space,
+/// their total size is checked first as it is given by the user.
+#[cfg(feature = "process")]
+fn copy_exec_strings(
+    params: &[usize; 6],
+) -> axerrno::AxResult<(alloc::vec::Vec<u8>, alloc::vec::Vec<u8>)> {
+    if params[3].saturating_add(params[5]) > axmem::ARG_MAX {
+        return Err(axerrno::AxError::InvalidInput);
+    }
+    let args = axmem::try_copy_slice_from_user(params[2].into(), params[3])?;
+    let envs = axmem::try_copy_slice_from_user(params[4].into(), params[5])?;
+    Ok((args, envs))
+}
+
+/// `exec` syscall, runs the program in user memory, only returns on error
+#[cfg(feature = "process")]
+fn exec(params: &[usize; 6]) -> axerrno::AxResult<isize> {
+    let (args, envs) = copy_exec_strings(params)?;
+    let data = axmem::try_copy_slice_from_user(params[0].into(), params[1])?;
+    Ok(axprocess::exec(data, args, envs))
+}
+
+/// `execve` syscall, runs the program at a path, only returns on error
+#[cfg(feature = "process")]
+fn execve(params: &[usize; 6]) -> axerrno::AxResult<isize> {
+    let (args, envs) = copy_exec_strings(params)?;
+    let path = axmem::try_copy_slice_from_user(params[0].into(), params[1])?;
+    let path =
+        alloc::string::String::from_utf8(path).map_err(|_| axerrno::AxError::InvalidInput)?;
+    Ok(axprocess::execve(&path, args, envs))
+}
+
+/// Page table flags of user pages with the `PROT_*` permissions of `flags`
+#[cfg(feature = "user-paging")]
+fn mapping_flags(flags: syscall_number::mmap::MapFlags) -> axhal::paging::MappingFlags {
+    use axhal::paging::MappingFlags;
+    use syscall_number::mmap::MapFlags;
+    let mut mapping = MappingFlags::USER;
+    // Write-only pages cannot be mapped, they are readable as well.
+    if flags.intersects(MapFlags::PROT_READ | MapFlags::PROT_WRITE) {
+        mapping |= MappingFlags::READ;
+    }
+    if flags.contains(MapFlags::PROT_WRITE) {
+        mapping |= MappingFlags::WRITE;
+    }
+    if flags.contains(MapFlags::PROT_EXEC) {
+        mapping |= MappingFlags::EXECUTE;
+    }
+    mapping
+}
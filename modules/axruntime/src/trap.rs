@@ -15,6 +15,46 @@ impl axhal::trap::TrapHandler for TrapHandlerImpl {
     fn handle_syscall(syscall_num: usize, params: [usize; 6]) -> isize {
         crate::syscall::syscall_handler(syscall_num, params)
     }
+
+    #[cfg(feature = "user")]
+    fn handle_user_fault(fault: axhal::trap::UserFault, addr: usize, pc: usize) {
+        warn!("User fault {:?} @ {:#x}, address = {:#x}", fault, pc, addr);
+        #[cfg(feature = "process")]
+        {
+            use axhal::trap::UserFault;
+            use syscall_number::signal::{SIGBUS, SIGILL, SIGSEGV};
+            // The process is terminated on its way back to user space, unless it handles the signal.
+            axprocess::signal::send_fault_signal(match fault {
+                UserFault::PageFault { .. } => SIGSEGV,
+                UserFault::IllegalInstruction => SIGILL,
+                UserFault::Misaligned => SIGBUS,
+            });
+        }
+        #[cfg(not(feature = "process"))]
+        {
+            error!("User program faulted, terminating");
+            axhal::misc::terminate();
+        }
+    }
+
+    #[cfg(feature = "user")]
+    fn handle_page_fault(_vaddr: usize, _write: bool) -> bool {
+        // Pages are allocated on the first access, and copied on the first write after `fork`.
+        #[cfg(feature = "user-paging")]
+        {
+            axmem::handle_page_fault(_vaddr.into(), _write)
+        }
+        #[cfg(not(feature = "user-paging"))]
+        {
+            false
+        }
+    }
+
+    #[cfg(feature = "user")]
+    fn handle_signal(_tf: &mut axhal::arch::TrapFrame) {
+        #[cfg(feature = "process")]
+        axprocess::signal::handle_signals(_tf);
+    }
 }
 
 #[cfg(feature = "user")]
@@ -8,15 +8,18 @@ use axdriver::prelude::*;
 use axdriver::AllDevices;
 use axerrno::{ax_err, AxError, AxResult};
 use axsync::Mutex;
-use scheme::Scheme;
+use scheme::{Map, Scheme};
 
 #[cfg(feature = "user_fs")]
 use self::block::BlockDev;
+#[cfg(feature = "user_display")]
+use self::display::DisplayDevice;
+use self::dma::DmaDevice;
 #[cfg(feature = "user_net")]
 use self::net::NetDevice;
 
-use super::schemes;
 use super::KernelScheme;
+use super::{schemes, SchemeNamespace};
 
 /// Device Scheme
 pub struct DeviceScheme {
@@ -24,7 +27,12 @@ pub struct DeviceScheme {
     net: Option<Arc<NetDevice>>,
     #[cfg(feature = "user_fs")]
     fs: Option<Arc<BlockDev>>,
+    #[cfg(feature = "user_display")]
+    display: Option<Arc<DisplayDevice>>,
+    dma: Arc<DmaDevice>,
     handles: Mutex<BTreeMap<usize, Arc<dyn Device + Sync + Send>>>,
+    /// devices of `fmap`ed regions, key is the address returned by `fmap`
+    fmaps: Mutex<BTreeMap<usize, Arc<dyn Device + Sync + Send>>>,
     next_id: AtomicUsize,
 }
 trait Device {
@@ -36,6 +44,15 @@ trait Device {
     fn lseek(&self, id: usize, offset: isize, whence: usize) -> AxResult<isize> {
         ax_err!(Unsupported)
     }
+    /// returns a page-aligned kernel address
+    #[allow(unused)]
+    fn fmap(&self, id: usize, map: &Map) -> AxResult<usize> {
+        ax_err!(Unsupported)
+    }
+    #[allow(unused)]
+    fn funmap(&self, address: usize, length: usize) -> AxResult<usize> {
+        Ok(0)
+    }
 }
 
 /// Initializes all devices
@@ -47,16 +64,25 @@ pub fn init(mut all_device: AllDevices) {
     #[cfg(feature = "user_fs")]
     let fs = self::block::init(&mut all_device);
 
+    #[cfg(feature = "user_display")]
+    let display = self::display::init(&mut all_device);
+
     let device_scheme = Arc::new(DeviceScheme {
         #[cfg(feature = "user_net")]
         net,
         #[cfg(feature = "user_fs")]
         fs,
+        #[cfg(feature = "user_display")]
+        display,
+        dma: Arc::new(DmaDevice::new()),
         handles: Mutex::new(BTreeMap::new()),
+        fmaps: Mutex::new(BTreeMap::new()),
         next_id: 0.into(),
     });
 
-    schemes().insert("dev", device_scheme);
+    schemes()
+        .insert(SchemeNamespace::ROOT, "dev", device_scheme)
+        .unwrap();
 }
 
 impl Scheme for DeviceScheme {
@@ -74,6 +100,9 @@ impl Scheme for DeviceScheme {
             "net" => self.net.clone().ok_or(AxError::NotFound)?,
             #[cfg(feature = "user_fs")]
             "disk" => self.fs.clone().ok_or(AxError::NotFound)?,
+            #[cfg(feature = "user_display")]
+            "fb" => self.display.clone().ok_or(AxError::NotFound)?,
+            "dma" => self.dma.clone(),
             _ => return ax_err!(NotFound),
         };
         let id = self
@@ -111,6 +140,24 @@ impl Scheme for DeviceScheme {
             .ok_or(AxError::BadFileDescriptor)?
             .lseek(id, pos, whence)
     }
+    fn fmap(&self, id: usize, map: &Map) -> AxResult<usize> {
+        let device = self
+            .handles
+            .lock()
+            .get(&id)
+            .ok_or(AxError::BadFileDescriptor)?
+            .clone();
+        let address = device.fmap(id, map)?;
+        self.fmaps.lock().insert(address, device);
+        Ok(address)
+    }
+    fn funmap(&self, address: usize, length: usize) -> AxResult<usize> {
+        self.fmaps
+            .lock()
+            .remove(&address)
+            .ok_or(AxError::InvalidInput)?
+            .funmap(address, length)
+    }
 }
 
 impl KernelScheme for DeviceScheme {}
@@ -379,6 +426,200 @@ mod block {
     }
 }
 
+#[cfg(feature = "user_display")]
+mod display {
+    use alloc::{collections::BTreeMap, sync::Arc};
+    use axdriver::{AllDevices, AxDisplayDevice};
+    use axerrno::{ax_err, AxError, AxResult};
+    use axhal::mem::PAGE_SIZE_4K;
+    use axsync::Mutex;
+    use driver_display::{BaseDriverOps, DisplayDriverOps};
+    use scheme::Map;
+
+    use super::{map_err, Device, ReadOnlyFile};
+
+    enum DisplayFileType {
+        Fb,
+        Stat(ReadOnlyFile),
+    }
+    pub struct DisplayDevice {
+        handles: Mutex<BTreeMap<usize, DisplayFileType>>,
+        driver: Mutex<AxDisplayDevice>,
+    }
+
+    pub fn init(all_device: &mut AllDevices) -> Option<Arc<DisplayDevice>> {
+        info!("dev:/fb started");
+        let dev = all_device.display.take_one()?;
+        info!("  use display device 0: {:?}", dev.device_name());
+        Some(Arc::new(DisplayDevice {
+            handles: Mutex::new(BTreeMap::new()),
+            driver: Mutex::new(dev),
+        }))
+    }
+
+    impl Device for DisplayDevice {
+        fn open(&self, path: &str, id: usize) -> AxResult {
+            match path.trim_matches('/') {
+                "" => {
+                    self.handles.lock().insert(id, DisplayFileType::Fb);
+                    Ok(())
+                }
+                "info" => {
+                    // width, height (u32) and size of the framebuffer (usize)
+                    let info = self.driver.lock().info();
+                    let mut data = alloc::vec::Vec::new();
+                    data.extend_from_slice(&info.width.to_ne_bytes());
+                    data.extend_from_slice(&info.height.to_ne_bytes());
+                    data.extend_from_slice(&info.fb_size.to_ne_bytes());
+                    self.handles
+                        .lock()
+                        .insert(id, DisplayFileType::Stat(ReadOnlyFile::new(&data)));
+                    Ok(())
+                }
+                _ => ax_err!(NotFound),
+            }
+        }
+
+        fn close(&self, id: usize) -> AxResult<usize> {
+            self.handles
+                .lock()
+                .remove(&id)
+                .map(|_| 0)
+                .ok_or(AxError::BadFileDescriptor)
+        }
+
+        fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+            let mut handle = self.handles.lock();
+            match handle.get_mut(&id).ok_or(AxError::BadFileDescriptor)? {
+                DisplayFileType::Fb => ax_err!(Unsupported),
+                DisplayFileType::Stat(file) => Ok(file.read(buf)),
+            }
+        }
+
+        /// Any write to the framebuffer handle flushes it to the screen.
+        fn write(&self, id: usize, buf: &[u8]) -> AxResult<usize> {
+            let mut handle = self.handles.lock();
+            match handle.get_mut(&id).ok_or(AxError::BadFileDescriptor)? {
+                DisplayFileType::Fb => {
+                    self.driver.lock().flush().map_err(map_err)?;
+                    Ok(buf.len())
+                }
+                DisplayFileType::Stat(_) => ax_err!(PermissionDenied),
+            }
+        }
+
+        fn fmap(&self, id: usize, map: &Map) -> AxResult<usize> {
+            match self
+                .handles
+                .lock()
+                .get(&id)
+                .ok_or(AxError::BadFileDescriptor)?
+            {
+                DisplayFileType::Fb => {
+                    let info = self.driver.lock().info();
+                    let end = map.offset.checked_add(map.size);
+                    if map.size == 0
+                        || map.offset % PAGE_SIZE_4K != 0
+                        || end.map_or(true, |end| end > info.fb_size)
+                    {
+                        return ax_err!(InvalidInput);
+                    }
+                    Ok(info.fb_base_vaddr + map.offset)
+                }
+                DisplayFileType::Stat(_) => ax_err!(Unsupported),
+            }
+        }
+    }
+}
+
+mod dma {
+    use alloc::collections::BTreeMap;
+    use axalloc::GlobalPage;
+    use axerrno::{ax_err, AxError, AxResult};
+    use axhal::mem::{virt_to_phys, PhysAddr, PAGE_SIZE_4K};
+    use axsync::Mutex;
+    use scheme::Map;
+
+    use super::Device;
+
+    /// Physically contiguous buffers for drivers in user mode.
+    ///
+    /// Every `fmap` allocates a new zeroed buffer,
+    /// and `read` returns the physical address of the latest one of the handle.
+    pub struct DmaDevice {
+        handles: Mutex<BTreeMap<usize, Option<PhysAddr>>>,
+        buffers: Mutex<BTreeMap<usize, GlobalPage>>,
+    }
+
+    impl DmaDevice {
+        pub fn new() -> DmaDevice {
+            DmaDevice {
+                handles: Mutex::new(BTreeMap::new()),
+                buffers: Mutex::new(BTreeMap::new()),
+            }
+        }
+    }
+
+    impl Device for DmaDevice {
+        fn open(&self, path: &str, id: usize) -> AxResult {
+            if !path.trim_matches('/').is_empty() {
+                return ax_err!(NotFound);
+            }
+            self.handles.lock().insert(id, None);
+            Ok(())
+        }
+
+        fn close(&self, id: usize) -> AxResult<usize> {
+            self.handles
+                .lock()
+                .remove(&id)
+                .map(|_| 0)
+                .ok_or(AxError::BadFileDescriptor)
+        }
+
+        fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+            let handle = self.handles.lock();
+            let paddr = handle
+                .get(&id)
+                .ok_or(AxError::BadFileDescriptor)?
+                .ok_or(AxError::BadState)?;
+            let data = paddr.as_usize().to_ne_bytes();
+            if buf.len() < data.len() {
+                return ax_err!(InvalidInput);
+            }
+            buf[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        }
+
+        fn write(&self, _id: usize, _buf: &[u8]) -> AxResult<usize> {
+            ax_err!(PermissionDenied)
+        }
+
+        fn fmap(&self, id: usize, map: &Map) -> AxResult<usize> {
+            let mut handle = self.handles.lock();
+            let paddr = handle.get_mut(&id).ok_or(AxError::BadFileDescriptor)?;
+            if map.size == 0 {
+                return ax_err!(InvalidInput);
+            }
+            let pages = map.size / PAGE_SIZE_4K + (map.size % PAGE_SIZE_4K != 0) as usize;
+            let mut buffer = GlobalPage::alloc_contiguous(pages, PAGE_SIZE_4K)?;
+            buffer.zero();
+            *paddr = Some(buffer.start_paddr(virt_to_phys));
+            let address = buffer.start_vaddr().as_usize();
+            self.buffers.lock().insert(address, buffer);
+            Ok(address)
+        }
+
+        fn funmap(&self, address: usize, _length: usize) -> AxResult<usize> {
+            self.buffers
+                .lock()
+                .remove(&address)
+                .map(|_| 0)
+                .ok_or(AxError::InvalidInput)
+        }
+    }
+}
+
 struct ReadOnlyFile {
     data: Vec<u8>,
     offset: usize,
@@ -0,0 +1,251 @@
+//! `event:` scheme: waits for readiness of several files at once
+//!
+//! Writing [`Event`]s to a handle watches the files given by `id` (a file descriptor),
+//! and reading from it blocks until some of them are ready.
+//! Handles opened as `event:/<ms>` stop waiting after `ms` milliseconds, and read no events then.
+//! Schemes report readiness through [`trigger`].
+extern crate alloc;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use axerrno::{ax_err, AxError, AxResult};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use scheme::{Event, EventFlags, Scheme};
+use spinlock::SpinNoIrq;
+use syscall_number::io::{OpenFlags, F_SETFL};
+
+use super::{find_fd, find_scheme, KernelScheme, SchemeId};
+
+struct EventQueue {
+    // checked inside `WaitQueue::wait_until`, so a spinlock is used.
+    events: SpinNoIrq<VecDeque<Event>>,
+    wq: WaitQueue,
+    /// the `NONBLOCK` status flag, set on `open` or by `fcntl`
+    nonblock: AtomicBool,
+    timeout: Option<Duration>,
+}
+
+impl EventQueue {
+    fn push(&self, event: Event) {
+        self.events.lock().push_back(event);
+        self.wq.notify_one(false);
+    }
+
+    /// Blocks until an event is ready or the timeout has elapsed.
+    fn wait(&self) -> AxResult<()> {
+        let ready = || !self.events.lock().is_empty();
+        match self.timeout {
+            // A kill is noticed once the timeout has elapsed.
+            #[cfg(feature = "irq")]
+            Some(timeout) => {
+                self.wq.wait_timeout_until(timeout, ready);
+            }
+            // Timers are not available, so it does not block at all.
+            #[cfg(not(feature = "irq"))]
+            Some(_) => {}
+            None => {
+                self.wq.wait_until_interruptible(ready);
+            }
+        }
+        if axtask::current().is_killed() {
+            return ax_err!(Interrupted);
+        }
+        Ok(())
+    }
+}
+
+struct Registration {
+    queue: Weak<EventQueue>,
+    /// file descriptor in the process that registers it
+    fd: usize,
+    flags: EventFlags,
+    data: usize,
+}
+
+/// Key is (scheme, file id in the scheme)
+static REGISTRY: Mutex<BTreeMap<(SchemeId, usize), Vec<Registration>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Posts readiness of file `id` in scheme `scheme_id` to all event queues watching it.
+pub fn trigger(scheme_id: SchemeId, id: usize, flags: EventFlags) {
+    let mut registry = REGISTRY.lock();
+    if let Some(list) = registry.get_mut(&(scheme_id, id)) {
+        list.retain(|reg| {
+            if let Some(queue) = reg.queue.upgrade() {
+                let flags = flags & reg.flags;
+                if !flags.is_empty() {
+                    queue.push(Event {
+                        id: reg.fd,
+                        flags,
+                        data: reg.data,
+                    });
+                }
+                true
+            } else {
+                false
+            }
+        });
+        if list.is_empty() {
+            registry.remove(&(scheme_id, id));
+        }
+    }
+}
+
+/// Event Scheme
+pub(crate) struct EventScheme {
+    handles: Mutex<BTreeMap<usize, Arc<EventQueue>>>,
+    next_id: AtomicUsize,
+}
+
+impl EventScheme {
+    pub(crate) fn new() -> Self {
+        EventScheme {
+            handles: Mutex::new(BTreeMap::new()),
+            next_id: 0.into(),
+        }
+    }
+
+    fn find(&self, id: usize) -> AxResult<Arc<EventQueue>> {
+        self.handles
+            .lock()
+            .get(&id)
+            .cloned()
+            .ok_or(AxError::BadFileDescriptor)
+    }
+}
+
+/// Watches `event.flags` of `event.id` (a file descriptor of current process) in `queue`.
+fn register(queue: &Arc<EventQueue>, event: &Event) -> AxResult<()> {
+    let handle = find_fd(event.id)?;
+    let key = (handle.scheme_id, handle.file_id);
+    let weak = Arc::downgrade(queue);
+    // The scheme is told about the union of all watched events of the file.
+    let flags = {
+        let mut registry = REGISTRY.lock();
+        let list = registry.entry(key).or_default();
+        list.retain(|reg| !(reg.fd == event.id && Weak::ptr_eq(&reg.queue, &weak)));
+        if !event.flags.is_empty() {
+            list.push(Registration {
+                queue: weak,
+                fd: event.id,
+                flags: event.flags,
+                data: event.data,
+            });
+        }
+        let flags = list
+            .iter()
+            .fold(EventFlags::empty(), |flags, reg| flags | reg.flags);
+        if list.is_empty() {
+            registry.remove(&key);
+        }
+        flags
+    };
+
+    let scheme = find_scheme(handle.scheme_id)?;
+    let ready = scheme.fevent(handle.file_id, flags)? & event.flags;
+    if !ready.is_empty() {
+        queue.push(Event {
+            id: event.id,
+            flags: ready,
+            data: event.data,
+        });
+    }
+    Ok(())
+}
+
+impl Scheme for EventScheme {
+    fn open(&self, path: &str, flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        let path = path.trim_matches('/');
+        let timeout = if path.is_empty() {
+            None
+        } else {
+            let ms = path.parse().map_err(|_| AxError::NotFound)?;
+            Some(Duration::from_millis(ms))
+        };
+        let flags = OpenFlags::from_bits_truncate(flags);
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.handles.lock().insert(
+            id,
+            Arc::new(EventQueue {
+                events: SpinNoIrq::new(VecDeque::new()),
+                wq: WaitQueue::new(),
+                nonblock: AtomicBool::new(flags.contains(OpenFlags::NONBLOCK)),
+                timeout,
+            }),
+        );
+        Ok(id)
+    }
+
+    /// Blocks until at least one event is ready, then takes as many as `buf` can hold.
+    /// Returns 0 if the handle has a timeout and it has elapsed.
+    fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let queue = self.find(id)?;
+        // `buf` may not be aligned for events.
+        let capacity = buf.len() / core::mem::size_of::<Event>();
+        if capacity == 0 {
+            return Ok(0);
+        }
+        if queue.nonblock.load(Ordering::Relaxed) {
+            if queue.events.lock().is_empty() {
+                return ax_err!(WouldBlock);
+            }
+        } else {
+            queue.wait()?;
+        }
+
+        let mut events = queue.events.lock();
+        let count = capacity.min(events.len());
+        for (chunk, event) in buf
+            .chunks_exact_mut(core::mem::size_of::<Event>())
+            .zip(events.drain(..count))
+        {
+            unsafe { core::ptr::write_unaligned(chunk.as_mut_ptr() as *mut Event, event) };
+        }
+        Ok(count * core::mem::size_of::<Event>())
+    }
+
+    /// Registers events, empty flags stop watching the file.
+    fn write(&self, id: usize, buf: &[u8]) -> AxResult<usize> {
+        let queue = self.find(id)?;
+        if buf.len() % core::mem::size_of::<Event>() != 0 {
+            return ax_err!(InvalidInput);
+        }
+        for chunk in buf.chunks_exact(core::mem::size_of::<Event>()) {
+            let event = unsafe { core::ptr::read_unaligned(chunk.as_ptr() as *const Event) };
+            register(&queue, &event)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> AxResult<usize> {
+        let queue = self.find(id)?;
+        match cmd {
+            F_SETFL => {
+                let nonblock = OpenFlags::from_bits_truncate(arg).contains(OpenFlags::NONBLOCK);
+                queue.nonblock.store(nonblock, Ordering::Relaxed);
+                Ok(0)
+            }
+            _ => ax_err!(InvalidInput),
+        }
+    }
+
+    fn close(&self, id: usize) -> AxResult<usize> {
+        let queue = self
+            .handles
+            .lock()
+            .remove(&id)
+            .ok_or(AxError::BadFileDescriptor)?;
+        let weak = Arc::downgrade(&queue);
+        REGISTRY.lock().retain(|_, list| {
+            list.retain(|reg| !Weak::ptr_eq(&reg.queue, &weak));
+            !list.is_empty()
+        });
+        Ok(0)
+    }
+}
+
+impl KernelScheme for EventScheme {}
@@ -5,7 +5,9 @@
 //!
 //! `user_fs`: block drivers
 //! `user_net`: net drivers
+//! `user_display`: display drivers
 //! `process`: process support
+//! `irq`: timeouts of `event:` handles
 #![no_std]
 
 extern crate alloc;
@@ -16,22 +18,61 @@ extern crate axlog;
 #[macro_use]
 extern crate crate_interface;
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use alloc::boxed::Box;
 use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::{collections::BTreeMap, sync::Arc};
-use axerrno::{ax_err, to_ret_code, AxError, AxResult};
-use axmem::{copy_byte_buffer_to_user, copy_slice_from_user};
+use axerrno::{ax_err, from_ret_code, to_ret_code, AxError, AxResult};
+use axhal::mem::{virt_to_phys, VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::MappingFlags;
+use axmem::{copy_byte_buffer_to_user, copy_data_to_user, copy_slice_from_user, LentFrame};
 use axsync::{Mutex, MutexGuard};
-use axtask::current;
 use lazy_init::LazyInit;
+use syscall_number::io::{OpenFlags, FD_CLOEXEC, F_GETFD, F_GETFL, F_SETFD, F_SETFL};
 
-/// File handle
+/// File handle, i.e. an open file description shared by `dup`ed and inherited descriptors
 pub struct FileHandle {
     /// related scheme id
     pub scheme_id: SchemeId,
     /// id in the scheme
     pub file_id: usize,
+    /// status flags (access mode, `APPEND` and `NONBLOCK`), see `fcntl`
+    flags: AtomicUsize,
+}
+
+/// Status flags kept in a [`FileHandle`]
+const STATUS_FLAGS: OpenFlags = OpenFlags::READ
+    .union(OpenFlags::WRITE)
+    .union(OpenFlags::APPEND)
+    .union(OpenFlags::NONBLOCK);
+/// Status flags changeable by `fcntl`
+const SETTABLE_FLAGS: OpenFlags = OpenFlags::APPEND.union(OpenFlags::NONBLOCK);
+
+impl FileHandle {
+    fn new(scheme_id: SchemeId, file_id: usize, flags: usize) -> Self {
+        FileHandle {
+            scheme_id,
+            file_id,
+            flags: AtomicUsize::new(flags & STATUS_FLAGS.bits()),
+        }
+    }
+
+    /// Closes the file, returning the result of the scheme.
+    fn close(self) -> AxResult<usize> {
+        let handle = core::mem::ManuallyDrop::new(self);
+        find_scheme(handle.scheme_id)?.close(handle.file_id)
+    }
+}
+
+/// The file is closed when the last descriptor referring to it is gone.
+impl Drop for FileHandle {
+    fn drop(&mut self) {
+        if let Ok(scheme) = find_scheme(self.scheme_id) {
+            let _ = scheme.close(self.file_id);
+        }
+    }
 }
 
 /// Gets current file table
@@ -41,9 +82,64 @@ pub trait CurrentFileTable {
     fn current_file_table() -> Arc<FileTable>;
 }
 
+/// A region of current process mapped by `fmap`
+struct FmapRegion {
+    scheme_id: SchemeId,
+    /// address returned by the scheme, passed back on `funmap`
+    address: usize,
+    size: usize,
+}
+
+/// Gets and sets the scheme namespace of current process
+#[crate_interface::def_interface]
+pub trait CurrentNamespace {
+    /// Get the namespace of current process
+    fn current_namespace() -> SchemeNamespace;
+    /// Switch current process into `ns`
+    fn set_current_namespace(ns: SchemeNamespace);
+}
+
+fn current_ns() -> SchemeNamespace {
+    call_interface!(CurrentNamespace::current_namespace)
+}
+
+/// Credentials of a process, checked by schemes on `open`, `rmdir`, `unlink`, etc.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Credentials {
+    /// real user id
+    pub uid: u32,
+    /// real group id
+    pub gid: u32,
+    /// effective user id, used for permission checks
+    pub euid: u32,
+    /// effective group id, used for permission checks
+    pub egid: u32,
+}
+
+/// Gets the credentials of current process
+#[crate_interface::def_interface]
+pub trait CurrentCredentials {
+    /// Get the credentials of current process
+    fn current_credentials() -> Credentials;
+}
+
+fn current_cred() -> Credentials {
+    call_interface!(CurrentCredentials::current_credentials)
+}
+
+/// An entry of the file table
+#[derive(Clone)]
+struct FileDescriptor {
+    handle: Arc<FileHandle>,
+    /// closed on `exec`
+    cloexec: bool,
+}
+
 /// File table
 pub struct FileTable {
-    inner: Mutex<Vec<Option<Arc<FileHandle>>>>,
+    inner: Mutex<Vec<Option<FileDescriptor>>>,
+    /// regions mapped by `fmap`, keyed by their address in the process
+    fmaps: Mutex<BTreeMap<VirtAddr, FmapRegion>>,
 }
 
 impl Default for FileTable {
@@ -55,8 +151,11 @@ impl Default for FileTable {
 impl Clone for FileTable {
     fn clone(&self) -> Self {
         let inner = self.inner.lock();
+        // `fmap`ed regions are copied into private pages of the new address space,
+        // so they are not inherited.
         FileTable {
             inner: Mutex::new(inner.iter().cloned().collect()),
+            fmaps: Mutex::new(BTreeMap::new()),
         }
     }
 }
@@ -66,75 +165,126 @@ impl FileTable {
     pub fn new() -> Self {
         Self {
             inner: Mutex::new(Vec::new()),
+            fmaps: Mutex::new(BTreeMap::new()),
         }
     }
 
     /// Remove all opening fds.
     pub fn clear(&self) {
-        self.inner.lock().iter().for_each(|handle| {
-            if let Some(handle) = handle {
-                let scheme = schemes().find_id(handle.scheme_id).unwrap();
-                let _ = scheme.close(handle.file_id);
-            }
-        });
-        self.inner.lock().clear();
+        // Closing may block on a user scheme, so the table must not be locked meanwhile.
+        let handles = core::mem::take(&mut *self.inner.lock());
+        drop(handles);
+        self.clear_fmaps();
     }
 
-    /// reset file table to init state (with only fd 0, 1, 2 as stdin, stdout, stderr)
+    fn clear_fmaps(&self) {
+        // The address space is already gone or reset, so only the schemes need to know.
+        core::mem::take(&mut *self.fmaps.lock())
+            .into_values()
+            .for_each(|region| {
+                if let Ok(scheme) = find_scheme(region.scheme_id) {
+                    let _ = scheme.funmap(region.address, region.size);
+                }
+            });
+    }
+
+    /// Closes the files marked close-on-exec, on `exec`.
+    ///
+    /// Other files are kept, e.g. the standard streams redirected by the parent.
+    /// The standard streams (fd 0, 1, 2) are reopened if they are closed.
     pub fn reset(&self) {
-        self.clear();
-        fn open_inner(scheme: &str, path: &str) -> Option<Arc<FileHandle>> {
-            let id = schemes().find_name(scheme).unwrap();
-            let scheme = schemes().find_id(id).unwrap();
+        let mut closed = Vec::new();
+        for file in self.inner.lock().iter_mut() {
+            if file.as_ref().map_or(false, |file| file.cloexec) {
+                closed.push(file.take());
+            }
+        }
+        // Closing may block on a user scheme, so the table must not be locked meanwhile.
+        drop(closed);
+        self.clear_fmaps();
+
+        fn open_inner(scheme: &str, path: &str) -> FileDescriptor {
+            let id = schemes().find_name(current_ns(), scheme).unwrap();
+            let scheme = find_scheme(id).unwrap();
             let ret = scheme.open(path, 0, 0, 0).unwrap();
-            Some(Arc::new(FileHandle {
-                scheme_id: id,
-                file_id: ret,
-            }))
+            FileDescriptor {
+                handle: Arc::new(FileHandle::new(id, ret, 0)),
+                cloexec: false,
+            }
+        }
+        let mut fd_list = self.inner.lock();
+        if fd_list.len() < 3 {
+            fd_list.resize(3, None);
+        }
+        for (fd, scheme) in ["stdin", "stdout", "stdout"].iter().enumerate() {
+            if fd_list[fd].is_none() {
+                fd_list[fd] = Some(open_inner(scheme, "/"));
+            }
         }
-        self.inner.lock().extend_from_slice(&[
-            open_inner("stdin", "/"),
-            open_inner("stdout", "/"),
-            open_inner("stdout", "/"),
-        ]);
     }
 
-    /// Inserts a file handle
-    pub fn insert(&self, file_handle: Arc<FileHandle>) -> AxResult<usize> {
+    /// Inserts a file handle, it is closed on `exec` if `cloexec` is set.
+    pub fn insert(&self, file_handle: Arc<FileHandle>, cloexec: bool) -> AxResult<usize> {
+        let file = FileDescriptor {
+            handle: file_handle,
+            cloexec,
+        };
         let mut fd_list = self.inner.lock();
-        if let Some(fd) = fd_list.iter_mut().enumerate().find_map(|(fd, handle)| {
-            if handle.is_none() {
-                *handle = Some(file_handle.clone());
-                Some(fd)
-            } else {
-                None
-            }
-        }) {
+        if let Some(fd) = fd_list.iter().position(Option::is_none) {
+            fd_list[fd] = Some(file);
             Ok(fd)
         } else {
-            fd_list.push(Some(file_handle.clone()));
+            fd_list.push(Some(file));
             Ok(fd_list.len() - 1)
         }
     }
 
-    /// Finds by file descriptor
-    pub fn find(&self, fd: usize) -> AxResult<Arc<FileHandle>> {
-        let fd_list = self.inner.lock();
+    /// Inserts a file handle as `fd`, returns the one replaced.
+    /// The new descriptor is kept on `exec`.
+    pub fn insert_at(&self, fd: usize, file_handle: Arc<FileHandle>) -> Option<Arc<FileHandle>> {
+        let mut fd_list = self.inner.lock();
         if fd >= fd_list.len() {
-            return ax_err!(BadFileDescriptor);
+            fd_list.resize(fd + 1, None);
         }
-        if let Some(handle) = &fd_list[fd] {
-            Ok(handle.clone())
-        } else {
-            ax_err!(BadFileDescriptor)
+        fd_list[fd]
+            .replace(FileDescriptor {
+                handle: file_handle,
+                cloexec: false,
+            })
+            .map(|file| file.handle)
+    }
+
+    /// Finds by file descriptor
+    pub fn find(&self, fd: usize) -> AxResult<Arc<FileHandle>> {
+        self.with_fd(fd, |file| file.handle.clone())
+    }
+
+    /// Whether `fd` is closed on `exec`
+    pub fn cloexec(&self, fd: usize) -> AxResult<bool> {
+        self.with_fd(fd, |file| file.cloexec)
+    }
+
+    /// Sets whether `fd` is closed on `exec`
+    pub fn set_cloexec(&self, fd: usize, cloexec: bool) -> AxResult<()> {
+        self.with_fd(fd, |file| file.cloexec = cloexec)
+    }
+
+    fn with_fd<T>(&self, fd: usize, f: impl FnOnce(&mut FileDescriptor) -> T) -> AxResult<T> {
+        let mut fd_list = self.inner.lock();
+        match fd_list.get_mut(fd) {
+            Some(Some(file)) => Ok(f(file)),
+            _ => ax_err!(BadFileDescriptor),
         }
     }
 
     /// Remove by file descriptor
-    pub fn remove(&self, fd: usize) -> AxResult<()> {
+    pub fn remove(&self, fd: usize) -> AxResult<Arc<FileHandle>> {
         let mut fd_list = self.inner.lock();
-        *fd_list.get_mut(fd).ok_or(AxError::BadFileDescriptor)? = None;
-        Ok(())
+        fd_list
+            .get_mut(fd)
+            .and_then(|file| file.take())
+            .map(|file| file.handle)
+            .ok_or(AxError::BadFileDescriptor)
     }
 }
 
@@ -150,18 +300,14 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
         SYS_CLASS_FILE => {
             let fd = params[0];
             match id & SYS_ARG {
-                SYS_ARG_SLICE => {
-                    match id {
-                        SYS_FMAP => ax_err!(Unsupported), // TODO
-                        _ => file_op_slice(
-                            id,
-                            fd,
-                            &copy_slice_from_user(params[1].into(), params[2]),
-                        ),
-                    }
-                }
+                SYS_ARG_SLICE => match id {
+                    SYS_FMAP => fmap(fd, &copy_slice_from_user(params[1].into(), params[2])),
+                    SYS_WRITE => write_user(fd, params[1].into(), params[2]),
+                    _ => file_op_slice(id, fd, &copy_slice_from_user(params[1].into(), params[2])),
+                },
                 SYS_ARG_MSLICE => {
                     match id {
+                        SYS_READ => read_user(fd, params[1].into(), params[2]),
                         SYS_FSTAT => file_op_slice_mut(id, fd, params[1], params[2]), // TODO: Support UNIX stat struct
                         _ => file_op_slice_mut(id, fd, params[1], params[2]),
                     }
@@ -172,10 +318,18 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
                         params[0],
                         &copy_slice_from_user(params[1].into(), params[2]),
                     ),
-                    SYS_DUP2 => ax_err!(Unsupported),    // TODO
-                    SYS_FCNTL => ax_err!(Unsupported),   // TODO
-                    SYS_FRENAME => ax_err!(Unsupported), // TODO
-                    SYS_FUNMAP => ax_err!(Unsupported),  // TODO
+                    SYS_DUP2 => dup2(
+                        fd,
+                        params[1],
+                        &copy_slice_from_user(params[2].into(), params[3]),
+                    ),
+                    SYS_FRENAME => file_op_slice(
+                        id,
+                        fd,
+                        axmem::copy_str_from_user(params[1].into(), params[2]).as_bytes(),
+                    ),
+                    SYS_FUNMAP => funmap(params[0].into(), params[1]),
+                    SYS_FCNTL => fcntl(fd, params[1], params[2]),
                     _ => file_op(id, fd, params[1], params[2]),
                 },
             }
@@ -185,12 +339,19 @@ pub fn syscall_handler(id: usize, params: [usize; 6]) -> isize {
                 &axmem::copy_str_from_user(params[0].into(), params[1]),
                 params[2],
             ),
-            op @ (SYS_RMDIR | SYS_UNLINK) => {
-                rm_op(op, &axmem::copy_str_from_user(params[0].into(), params[1]))
-            }
+            op @ (SYS_CHMOD | SYS_RMDIR | SYS_UNLINK) => path_op(
+                op,
+                &axmem::copy_str_from_user(params[0].into(), params[1]),
+                params[2],
+            ),
+            _ => ax_err!(Unsupported),
+        },
+        _ => match id {
+            SYS_MKNS => mkns(&axmem::copy_str_from_user(params[0].into(), params[1])),
+            SYS_SETNS => setns(SchemeNamespace(params[0])),
+            SYS_PIPE2 => pipe2(params[0].into(), params[1]),
             _ => ax_err!(Unsupported),
         },
-        _ => ax_err!(Unsupported),
     };
     to_ret_code(ret)
 }
@@ -214,9 +375,9 @@ pub fn init_scheme() {
     open("stdout:", 0).unwrap();
 }
 
-fn insert_fd(fd: Arc<FileHandle>) -> AxResult<usize> {
+fn insert_fd(fd: Arc<FileHandle>, cloexec: bool) -> AxResult<usize> {
     let file_table = call_interface!(CurrentFileTable::current_file_table);
-    file_table.insert(fd)
+    file_table.insert(fd, cloexec)
 }
 
 fn find_fd(fd: usize) -> AxResult<Arc<FileHandle>> {
@@ -224,74 +385,171 @@ fn find_fd(fd: usize) -> AxResult<Arc<FileHandle>> {
     file_table.find(fd)
 }
 
-// TODO: all flags
-fn open(path: &str, options: usize) -> AxResult<usize> {
+/// Finds the scheme of `path` (`scheme:path`, or a path of `file:`) in current namespace,
+/// returns its id and the path in it.
+fn resolve(path: &str) -> AxResult<(SchemeId, &str)> {
     let mut path_split = path.splitn(2, ':');
     let (scheme, path) = match (path_split.next(), path_split.next()) {
         (Some(scheme), Some(path)) => (scheme, path),
         (Some(path), None) => ("file", path),
         _ => return ax_err!(NotFound),
     };
-    trace!("Open {}:{}", scheme, path);
-    let scheme_id = schemes().find_name(scheme).ok_or(AxError::NotFound)?;
-    let scheme = schemes().find_id(scheme_id).unwrap();
+    let scheme_id = schemes()
+        .find_name(current_ns(), scheme)
+        .ok_or(AxError::NotFound)?;
+    Ok((scheme_id, path))
+}
 
-    let file_handle = Arc::new(FileHandle {
-        scheme_id,
-        file_id: scheme.open(path, options, 0, 0)?,
-    });
+// TODO: all flags
+fn open(path: &str, options: usize) -> AxResult<usize> {
+    trace!("Open {}", path);
+    let (scheme_id, path) = resolve(path)?;
+    let scheme = find_scheme(scheme_id)?;
+    let cred = current_cred();
+
+    let file_id = scheme.open(path, options, cred.euid, cred.egid)?;
+    let file_handle = Arc::new(FileHandle::new(scheme_id, file_id, options));
 
-    insert_fd(file_handle)
+    let cloexec = OpenFlags::from_bits_truncate(options).contains(OpenFlags::CLOEXEC);
+    insert_fd(file_handle, cloexec)
 }
 
-fn rm_op(op: usize, path: &str) -> AxResult<usize> {
-    let mut path_split = path.splitn(2, ':');
-    let (scheme, path) = match (path_split.next(), path_split.next()) {
-        (Some(scheme), Some(path)) => (scheme, path),
-        (Some(path), None) => ("file", path),
-        _ => return ax_err!(NotFound),
-    };
-    trace!("rm {}:{}", scheme, path);
-    let scheme_id = schemes().find_name(scheme).ok_or(AxError::NotFound)?;
-    let scheme = schemes().find_id(scheme_id).unwrap();
+fn path_op(op: usize, path: &str, arg: usize) -> AxResult<usize> {
+    trace!("path op {:#x} {}", op, path);
+    let (scheme_id, path) = resolve(path)?;
+    let scheme = find_scheme(scheme_id)?;
+    let cred = current_cred();
     match op {
-        SYS_RMDIR => scheme.rmdir(path, 0, 0),
-        SYS_UNLINK => scheme.unlink(path, 0, 0),
+        SYS_CHMOD => scheme.chmod(path, arg as u16, cred.euid, cred.egid),
+        SYS_RMDIR => scheme.rmdir(path, cred.euid, cred.egid),
+        SYS_UNLINK => scheme.unlink(path, cred.euid, cred.egid),
         _ => unreachable!(),
     }
 }
 
+/// Reads the whole file at `path` into the kernel, opened with the namespace
+/// and credentials of current process but without taking a descriptor.
+///
+/// Fails with `InvalidInput` if the file is larger than `limit` bytes.
+pub fn read_file(path: &str, limit: usize) -> AxResult<Vec<u8>> {
+    let (scheme_id, path) = resolve(path)?;
+    let scheme = find_scheme(scheme_id)?;
+    let cred = current_cred();
+    let options = OpenFlags::READ.bits();
+    let file_id = scheme.open(path, options, cred.euid, cred.egid)?;
+    // The file is closed when the handle is dropped.
+    let handle = FileHandle::new(scheme_id, file_id, options);
+    let mut data = Vec::new();
+    let mut buffer: Vec<u8> = alloc::vec![0; PAGE_SIZE_4K];
+    loop {
+        let len = scheme.read(handle.file_id, &mut buffer)?;
+        if len == 0 {
+            return Ok(data);
+        }
+        if data.len() + len > limit {
+            return ax_err!(InvalidInput);
+        }
+        data.extend_from_slice(&buffer[..len.min(buffer.len())]);
+    }
+}
+
 fn file_op(op: usize, fd: usize, c: usize, d: usize) -> AxResult<usize> {
     let handle = find_fd(fd)?;
-    let scheme = schemes().find_id(handle.scheme_id).unwrap();
+    let scheme = find_scheme(handle.scheme_id)?;
     let file = handle.file_id;
+    let cred = current_cred();
     let mut packet = Packet {
         a: op,
         b: file,
         c,
         d,
         id: 0,
-        pid: current().id().as_u64() as usize,
-        uid: 0,
-        gid: 0,
+        pid: axtask::current_pid().unwrap_or(0) as usize,
+        uid: cred.euid,
+        gid: cred.egid,
     };
     scheme.handle(&mut packet);
     Ok(packet.a)
 }
 
-fn close(fd: usize) -> AxResult<usize> {
+fn read_user(fd: usize, buf: VirtAddr, len: usize) -> AxResult<usize> {
     let handle = find_fd(fd)?;
+    let scheme = find_scheme(handle.scheme_id)?;
+    scheme.read_user(handle.file_id, buf, len)
+}
 
-    let scheme = schemes().find_id(handle.scheme_id).unwrap();
+fn write_user(fd: usize, buf: VirtAddr, len: usize) -> AxResult<usize> {
+    let handle = find_fd(fd)?;
+    let scheme = find_scheme(handle.scheme_id)?;
+    scheme.write_user(handle.file_id, buf, len)
+}
 
-    let ret = scheme.close(handle.file_id)?;
+fn fmap(fd: usize, buf: &[u8]) -> AxResult<usize> {
+    if buf.len() < core::mem::size_of::<Map>() {
+        return ax_err!(BadAddress);
+    }
+    let map = unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const Map) };
+    if map.size == 0 || map.flags.contains(MapFlags::MAP_FIXED) {
+        return ax_err!(InvalidInput);
+    }
+    let mut flags = MappingFlags::USER;
+    if map.flags.contains(MapFlags::PROT_READ) {
+        flags |= MappingFlags::READ;
+    }
+    if map.flags.contains(MapFlags::PROT_WRITE) {
+        flags |= MappingFlags::WRITE;
+    }
+    if map.flags.contains(MapFlags::PROT_EXEC) {
+        flags |= MappingFlags::EXECUTE;
+    }
 
-    {
-        let file_table = call_interface!(CurrentFileTable::current_file_table);
-        file_table.remove(fd)?;
+    let handle = find_fd(fd)?;
+    let scheme = find_scheme(handle.scheme_id)?;
+    let (address, frames) = scheme.fmap_frames(handle.file_id, &map)?;
+    let vaddr = match axmem::mmap_lend(&frames, flags) {
+        Ok(vaddr) => vaddr,
+        Err(e) => {
+            let _ = scheme.funmap(address, map.size);
+            return Err(e);
+        }
+    };
+
+    let file_table = call_interface!(CurrentFileTable::current_file_table);
+    file_table.fmaps.lock().insert(
+        vaddr,
+        FmapRegion {
+            scheme_id: handle.scheme_id,
+            address,
+            size: map.size,
+        },
+    );
+    Ok(vaddr.into())
+}
+
+fn funmap(addr: VirtAddr, len: usize) -> AxResult<usize> {
+    let file_table = call_interface!(CurrentFileTable::current_file_table);
+    let region = file_table.fmaps.lock().remove(&addr);
+    match region {
+        Some(region) => {
+            axmem::munmap_page(addr, region.size)?;
+            find_scheme(region.scheme_id)?.funmap(region.address, region.size)
+        }
+        // Pages that do not come from a scheme (e.g. `fmap`ed before `fork`),
+        // lent pages are refused as their lender still owns them
+        None => axmem::munmap(addr, len).map(|_| 0),
     }
+}
 
-    Ok(ret)
+fn close(fd: usize) -> AxResult<usize> {
+    let handle = {
+        let file_table = call_interface!(CurrentFileTable::current_file_table);
+        file_table.remove(fd)?
+    };
+    // Other descriptors (e.g. `dup`ed or inherited by `fork`) may still refer to the file.
+    match Arc::try_unwrap(handle) {
+        Ok(handle) => handle.close(),
+        Err(_) => Ok(0),
+    }
 }
 
 fn dup_inner(fd: usize, buf: &[u8]) -> AxResult<Arc<FileHandle>> {
@@ -300,68 +558,247 @@ fn dup_inner(fd: usize, buf: &[u8]) -> AxResult<Arc<FileHandle>> {
     if buf.is_empty() {
         Ok(handle)
     } else {
-        let scheme = schemes().find_id(handle.scheme_id).unwrap();
+        let scheme = find_scheme(handle.scheme_id)?;
         let new_id = scheme.dup(handle.file_id, buf)?;
 
-        Ok(Arc::new(FileHandle {
-            scheme_id: handle.scheme_id,
-            file_id: new_id,
-        }))
+        Ok(Arc::new(FileHandle::new(
+            handle.scheme_id,
+            new_id,
+            handle.flags.load(Ordering::Relaxed),
+        )))
     }
 }
 
 fn dup(fd: usize, buf: &[u8]) -> AxResult<usize> {
     let handle = dup_inner(fd, buf)?;
-    insert_fd(handle)
+    insert_fd(handle, false)
+}
+
+/// Like `dup`, but the new descriptor is `new_fd`, which is closed first if it is open.
+fn dup2(fd: usize, new_fd: usize, buf: &[u8]) -> AxResult<usize> {
+    let handle = dup_inner(fd, buf)?;
+    let file_table = call_interface!(CurrentFileTable::current_file_table);
+    let old = file_table.insert_at(new_fd, handle);
+    // Closing may block, so the file table is not locked here.
+    drop(old);
+    Ok(new_fd)
+}
+
+/// `fcntl` syscall: descriptor flags and status flags are kept in kernel,
+/// other commands are passed to the scheme.
+fn fcntl(fd: usize, cmd: usize, arg: usize) -> AxResult<usize> {
+    let file_table = call_interface!(CurrentFileTable::current_file_table);
+    match cmd {
+        F_GETFD => Ok(if file_table.cloexec(fd)? {
+            FD_CLOEXEC
+        } else {
+            0
+        }),
+        F_SETFD => file_table.set_cloexec(fd, arg & FD_CLOEXEC != 0).map(|_| 0),
+        F_GETFL => Ok(file_table.find(fd)?.flags.load(Ordering::Relaxed)),
+        F_SETFL => {
+            let handle = file_table.find(fd)?;
+            let settable = SETTABLE_FLAGS.bits();
+            let flags = (handle.flags.load(Ordering::Relaxed) & !settable) | (arg & settable);
+            // The scheme enforces the flags, so they are only kept if it takes them.
+            from_ret_code(file_op(SYS_FCNTL, fd, F_SETFL, flags)? as isize)?;
+            handle.flags.store(flags, Ordering::Relaxed);
+            Ok(0)
+        }
+        _ => file_op(SYS_FCNTL, fd, cmd, arg),
+    }
+}
+
+/// `pipe2` syscall: opens a pipe, the read end and write end are stored into `fds`.
+fn pipe2(fds: VirtAddr, flags: usize) -> AxResult<usize> {
+    let flags = OpenFlags::from_bits_truncate(flags) - OpenFlags::READ - OpenFlags::WRITE;
+    let read = open("pipe:", (flags | OpenFlags::READ).bits())?;
+    let write = match dup(read, b"write") {
+        Ok(write) => write,
+        Err(e) => {
+            let _ = close(read);
+            return Err(e);
+        }
+    };
+    let file_table = call_interface!(CurrentFileTable::current_file_table);
+    let status = (flags | OpenFlags::WRITE) & STATUS_FLAGS;
+    file_table
+        .find(write)?
+        .flags
+        .store(status.bits(), Ordering::Relaxed);
+    file_table.set_cloexec(write, flags.contains(OpenFlags::CLOEXEC))?;
+    copy_data_to_user(0, fds.as_ptr(), &[read, write]);
+    Ok(0)
+}
+
+/// `mkns` syscall: creates a namespace with the schemes in `names` (separated by `\n`)
+/// of the current one.
+fn mkns(names: &str) -> AxResult<usize> {
+    let names: Vec<&str> = names.split('\n').filter(|name| !name.is_empty()).collect();
+    let ns = schemes().make_ns(current_ns(), &names)?;
+    Ok(ns.0)
+}
+
+/// `setns` syscall: only namespaces created from the current one can be entered.
+fn setns(ns: SchemeNamespace) -> AxResult<usize> {
+    if !schemes().is_descendant(ns, current_ns()) {
+        return ax_err!(PermissionDenied);
+    }
+    call_interface!(CurrentNamespace::set_current_namespace, ns);
+    Ok(0)
 }
 
 /// Id of a Scheme
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct SchemeId(usize);
 
+/// Id of a scheme namespace
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub struct SchemeNamespace(usize);
+
+impl SchemeNamespace {
+    /// The namespace with all kernel schemes, where the init process lives
+    pub const ROOT: SchemeNamespace = SchemeNamespace(0);
+}
+
+/// Schemes in every namespace
+const CORE_SCHEMES: [&str; 5] = ["", "stdin", "stdout", "event", "pipe"];
+
+struct Namespace {
+    names: BTreeMap<Box<str>, SchemeId>,
+    /// the namespace it is created from
+    parent: Option<SchemeNamespace>,
+}
+
 /// Container of all scheme implementations
 pub struct SchemeList {
     map: BTreeMap<SchemeId, Arc<dyn KernelScheme + Sync + Send>>,
-    names: BTreeMap<Box<str>, SchemeId>,
+    namespaces: BTreeMap<SchemeNamespace, Namespace>,
+    next_ns: usize,
     next_id: usize,
 }
 
 impl SchemeList {
     fn new() -> Self {
+        let mut namespaces = BTreeMap::new();
+        namespaces.insert(
+            SchemeNamespace::ROOT,
+            Namespace {
+                names: BTreeMap::new(),
+                parent: None,
+            },
+        );
         SchemeList {
             map: BTreeMap::new(),
-            names: BTreeMap::new(),
+            namespaces,
+            next_ns: 1,
             next_id: 1,
         }
     }
     fn new_init() -> Self {
         let mut result = Self::new();
-        // TODO: add basic Schemes
-        result.insert("", Arc::new(RootScheme::new()));
-        result.insert("stdout", Arc::new(Stdout::new()));
-        result.insert("stdin", Arc::new(Stdin));
+        let ns = SchemeNamespace::ROOT;
+        result.insert(ns, "", Arc::new(RootScheme::new())).unwrap();
+        result
+            .insert(ns, "stdout", Arc::new(Stdout::new()))
+            .unwrap();
+        result.insert(ns, "stdin", Arc::new(Stdin)).unwrap();
+        result
+            .insert(ns, "event", Arc::new(EventScheme::new()))
+            .unwrap();
+        result
+            .insert(ns, "pipe", Arc::new(PipeScheme::new()))
+            .unwrap();
+        result.insert(ns, "memory", Arc::new(MemoryScheme)).unwrap();
         result
     }
-    /// Inserts a scheme
-    pub fn insert(&mut self, name: &str, scheme: Arc<dyn KernelScheme + Sync + Send>) {
+    /// Inserts a scheme into namespace `ns`
+    pub fn insert(
+        &mut self,
+        ns: SchemeNamespace,
+        name: &str,
+        scheme: Arc<dyn KernelScheme + Sync + Send>,
+    ) -> AxResult<SchemeId> {
+        let names = &mut self.namespaces.get_mut(&ns).ok_or(AxError::NotFound)?.names;
+        if names.contains_key(name) {
+            return ax_err!(AlreadyExists);
+        }
         let id = SchemeId(self.next_id);
         trace!("insert {} scheme", name);
         self.next_id += 1;
-        assert!(self
-            .names
-            .insert(name.to_string().into_boxed_str(), id)
-            .is_none());
+        names.insert(name.to_string().into_boxed_str(), id);
         assert!(self.map.insert(id, scheme).is_none());
+        Ok(id)
     }
 
-    /// Finds a scheme by name
-    pub fn find_name(&self, name: &str) -> Option<SchemeId> {
-        self.names.get(name).copied()
+    /// Creates a namespace from `parent` with the core schemes and schemes in `names`
+    pub fn make_ns(
+        &mut self,
+        parent: SchemeNamespace,
+        names: &[&str],
+    ) -> AxResult<SchemeNamespace> {
+        let parent_names = &self.namespaces.get(&parent).ok_or(AxError::NotFound)?.names;
+        let mut new_names = BTreeMap::new();
+        for name in CORE_SCHEMES.iter().chain(names) {
+            let id = parent_names.get(*name).ok_or(AxError::NotFound)?;
+            new_names.insert(name.to_string().into_boxed_str(), *id);
+        }
+        let ns = SchemeNamespace(self.next_ns);
+        self.next_ns += 1;
+        self.namespaces.insert(
+            ns,
+            Namespace {
+                names: new_names,
+                parent: Some(parent),
+            },
+        );
+        Ok(ns)
+    }
+
+    /// Whether `ns` is `ancestor` itself or created (indirectly) from it
+    pub fn is_descendant(&self, ns: SchemeNamespace, ancestor: SchemeNamespace) -> bool {
+        let mut ns = Some(ns);
+        while let Some(current) = ns {
+            if current == ancestor {
+                return true;
+            }
+            ns = self.namespaces.get(&current).and_then(|ns| ns.parent);
+        }
+        false
+    }
+
+    /// Finds a scheme by name in namespace `ns`
+    pub fn find_name(&self, ns: SchemeNamespace, name: &str) -> Option<SchemeId> {
+        self.namespaces.get(&ns)?.names.get(name).copied()
+    }
+    /// Lists schemes in namespace `ns` with their names
+    pub fn list(
+        &self,
+        ns: SchemeNamespace,
+    ) -> impl Iterator<Item = (&str, &Arc<dyn KernelScheme + Sync + Send>)> {
+        self.namespaces
+            .get(&ns)
+            .into_iter()
+            .flat_map(|ns| ns.names.iter())
+            .filter_map(|(name, id)| Some((name.as_ref(), self.map.get(id)?)))
     }
     /// Finds a scheme by id
     pub fn find_id(&self, id: SchemeId) -> Option<Arc<dyn KernelScheme + Sync + Send>> {
         self.map.get(&id).cloned()
     }
+    /// Removes a scheme, its names in all namespaces become free
+    pub fn remove(&mut self, id: SchemeId) -> Option<Arc<dyn KernelScheme + Sync + Send>> {
+        for ns in self.namespaces.values_mut() {
+            ns.names.retain(|_, scheme_id| *scheme_id != id);
+        }
+        self.map.remove(&id)
+    }
+}
+
+/// Finds a scheme by id, files of a removed scheme fail with `ConnectionReset`.
+fn find_scheme(id: SchemeId) -> AxResult<Arc<dyn KernelScheme + Sync + Send>> {
+    let scheme = schemes().find_id(id);
+    scheme.ok_or(AxError::ConnectionReset)
 }
 
 static GLOBAL_SCHEME_LIST: LazyInit<Mutex<SchemeList>> = LazyInit::new();
@@ -371,17 +808,80 @@ pub fn schemes() -> MutexGuard<'static, SchemeList> {
     GLOBAL_SCHEME_LIST.lock()
 }
 
-use scheme::Packet;
 pub use scheme::Scheme;
+use scheme::{Map, MapFlags, Packet};
 use syscall_number::*;
 /// Schemes implemented in kernel
-pub trait KernelScheme: Scheme {}
+pub trait KernelScheme: Scheme {
+    /// `read` into the buffer `[buf, buf + len)` of current process.
+    ///
+    /// By default, data is read into a kernel buffer and then copied to the user.
+    fn read_user(&self, id: usize, buf: VirtAddr, len: usize) -> AxResult<usize> {
+        read_user_copy(self, id, buf, len)
+    }
+
+    /// `write` from the buffer `[buf, buf + len)` of current process.
+    ///
+    /// By default, data is copied into a kernel buffer first.
+    fn write_user(&self, id: usize, buf: VirtAddr, len: usize) -> AxResult<usize> {
+        write_user_copy(self, id, buf, len)
+    }
+
+    /// `fmap` for current process, returns the address given by the scheme
+    /// and the frames of the `map.size` bytes starting from it, kept alive while mapped.
+    ///
+    /// By default, the address is in kernel space and must be page-aligned.
+    fn fmap_frames(&self, id: usize, map: &Map) -> AxResult<(usize, Vec<LentFrame>)> {
+        let address = self.fmap(id, map)?;
+        let start = VirtAddr::from(address);
+        if !start.is_aligned_4k() {
+            let _ = self.funmap(address, map.size);
+            return ax_err!(BadAddress);
+        }
+        let frames = (0..(map.size + PAGE_SIZE_4K - 1) / PAGE_SIZE_4K)
+            .map(|offset| LentFrame::unowned(virt_to_phys(start + offset * PAGE_SIZE_4K)))
+            .collect();
+        Ok((address, frames))
+    }
+
+    /// pid of the process serving this scheme, `None` if it is implemented in kernel.
+    fn owner(&self) -> Option<u64> {
+        None
+    }
+}
+
+fn read_user_copy<S: Scheme + ?Sized>(
+    scheme: &S,
+    id: usize,
+    buf: VirtAddr,
+    len: usize,
+) -> AxResult<usize> {
+    let mut buffer: Vec<u8> = alloc::vec![0; len];
+    let ret = scheme.read(id, &mut buffer)?;
+    copy_byte_buffer_to_user(0, buf.as_ptr(), &buffer[..ret.min(len)]);
+    Ok(ret)
+}
+
+fn write_user_copy<S: Scheme + ?Sized>(
+    scheme: &S,
+    id: usize,
+    buf: VirtAddr,
+    len: usize,
+) -> AxResult<usize> {
+    scheme.write(id, &copy_slice_from_user(buf, len))
+}
 
 pub mod dev;
+pub mod event;
 mod io;
+mod memory;
+mod pipe;
 mod root;
 mod user;
+use event::EventScheme;
 use io::{Stdin, Stdout};
+use memory::MemoryScheme;
+use pipe::PipeScheme;
 
 use self::root::RootScheme;
 pub use user::FindAddrSpace;
@@ -0,0 +1,27 @@
+//! Scheme reporting physical memory usage with `fstatvfs`, like `memory:` of Redox
+use axerrno::AxResult;
+use axhal::mem::PAGE_SIZE_4K;
+use scheme::{Scheme, StatVfs};
+
+use super::KernelScheme;
+
+pub struct MemoryScheme;
+impl Scheme for MemoryScheme {
+    fn open(&self, _path: &str, _flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        Ok(0)
+    }
+    /// Blocks are frames, free ones include the free memory of the kernel heap.
+    fn fstatvfs(&self, _id: usize, stat: &mut StatVfs) -> AxResult<usize> {
+        let allocator = axalloc::global_allocator();
+        let free = allocator.available_pages() + allocator.available_bytes() / PAGE_SIZE_4K;
+        stat.f_bsize = PAGE_SIZE_4K as u32;
+        stat.f_blocks = (allocator.used_pages() + allocator.available_pages()) as u64;
+        stat.f_bfree = free as u64;
+        stat.f_bavail = free as u64;
+        Ok(0)
+    }
+    fn close(&self, _id: usize) -> AxResult<usize> {
+        Ok(0)
+    }
+}
+impl KernelScheme for MemoryScheme {}
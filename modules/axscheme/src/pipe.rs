@@ -0,0 +1,238 @@
+//! `pipe:` scheme: unidirectional byte channels between processes
+//!
+//! Opening `pipe:` creates a pipe and returns its read end,
+//! `dup` with `write` on any end of it gives a write end (as in Redox).
+//! The `pipe2` syscall does both.
+//! Reading from an empty pipe blocks until data arrives or the last write end is closed (EOF),
+//! writing to a full pipe blocks until there is space or the last read end is closed (`BrokenPipe`).
+//! With `NONBLOCK`, both fail with `WouldBlock` instead.
+extern crate alloc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use axerrno::{ax_err, AxError, AxResult};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use scheme::Scheme;
+use spinlock::SpinNoIrq;
+use syscall_number::io::{OpenFlags, F_SETFL};
+
+use super::KernelScheme;
+
+/// Capacity of a pipe in bytes
+const PIPE_SIZE: usize = 4096;
+
+struct Pipe {
+    // checked inside `WaitQueue::wait_until`, so a spinlock is used.
+    buffer: SpinNoIrq<VecDeque<u8>>,
+    readers: AtomicUsize,
+    writers: AtomicUsize,
+    /// readers waiting for data
+    read_wq: WaitQueue,
+    /// writers waiting for space
+    write_wq: WaitQueue,
+}
+
+impl Pipe {
+    fn new() -> Self {
+        Pipe {
+            buffer: SpinNoIrq::new(VecDeque::with_capacity(PIPE_SIZE)),
+            readers: AtomicUsize::new(1),
+            writers: AtomicUsize::new(0),
+            read_wq: WaitQueue::new(),
+            write_wq: WaitQueue::new(),
+        }
+    }
+
+    fn read(&self, buf: &mut [u8], nonblock: bool) -> AxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if !nonblock
+                && !self.read_wq.wait_until_interruptible(|| {
+                    !self.buffer.lock().is_empty() || self.writers.load(Ordering::Acquire) == 0
+                })
+            {
+                return ax_err!(Interrupted);
+            }
+            let mut buffer = self.buffer.lock();
+            if buffer.is_empty() {
+                if self.writers.load(Ordering::Acquire) == 0 {
+                    return Ok(0);
+                }
+                if nonblock {
+                    return ax_err!(WouldBlock);
+                }
+                // Taken by another reader
+                continue;
+            }
+            let len = buf.len().min(buffer.len());
+            for (dst, src) in buf.iter_mut().zip(buffer.drain(..len)) {
+                *dst = src;
+            }
+            drop(buffer);
+            self.write_wq.notify_all(false);
+            return Ok(len);
+        }
+    }
+
+    fn write(&self, buf: &[u8], nonblock: bool) -> AxResult<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            if !nonblock
+                && !self.write_wq.wait_until_interruptible(|| {
+                    self.buffer.lock().len() < PIPE_SIZE
+                        || self.readers.load(Ordering::Acquire) == 0
+                })
+            {
+                return ax_err!(Interrupted);
+            }
+            if self.readers.load(Ordering::Acquire) == 0 {
+                // The data written so far is lost anyway.
+                return ax_err!(BrokenPipe);
+            }
+            let mut buffer = self.buffer.lock();
+            if nonblock && buffer.len() == PIPE_SIZE {
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    ax_err!(WouldBlock)
+                };
+            }
+            let len = (buf.len() - written).min(PIPE_SIZE - buffer.len());
+            buffer.extend(&buf[written..written + len]);
+            drop(buffer);
+            written += len;
+            self.read_wq.notify_all(false);
+        }
+        Ok(written)
+    }
+}
+
+#[derive(Clone)]
+enum PipeEnd {
+    Read(Arc<Pipe>),
+    Write(Arc<Pipe>),
+}
+
+#[derive(Clone)]
+struct PipeHandle {
+    end: PipeEnd,
+    /// the `NONBLOCK` status flag, set on `open` or by `fcntl`
+    nonblock: bool,
+}
+
+/// Pipe Scheme
+pub(crate) struct PipeScheme {
+    handles: Mutex<BTreeMap<usize, PipeHandle>>,
+    next_id: AtomicUsize,
+}
+
+impl PipeScheme {
+    pub(crate) fn new() -> Self {
+        PipeScheme {
+            handles: Mutex::new(BTreeMap::new()),
+            next_id: AtomicUsize::new(1),
+        }
+    }
+
+    fn insert(&self, handle: PipeHandle) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.handles.lock().insert(id, handle);
+        id
+    }
+
+    fn get(&self, id: usize) -> AxResult<PipeHandle> {
+        self.handles
+            .lock()
+            .get(&id)
+            .cloned()
+            .ok_or(AxError::BadFileDescriptor)
+    }
+}
+
+impl Scheme for PipeScheme {
+    /// Creates a pipe, returns its read end.
+    fn open(&self, _path: &str, flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
+        Ok(self.insert(PipeHandle {
+            end: PipeEnd::Read(Arc::new(Pipe::new())),
+            nonblock: OpenFlags::from_bits_truncate(flags).contains(OpenFlags::NONBLOCK),
+        }))
+    }
+
+    /// `write` gives a write end of the pipe, others give the same end.
+    fn dup(&self, old_id: usize, buf: &[u8]) -> AxResult<usize> {
+        let handle = self.get(old_id)?;
+        let end = match (handle.end, buf) {
+            (PipeEnd::Read(pipe) | PipeEnd::Write(pipe), b"write") => PipeEnd::Write(pipe),
+            (end, b"") => end,
+            _ => return ax_err!(InvalidInput),
+        };
+        match &end {
+            PipeEnd::Read(pipe) => pipe.readers.fetch_add(1, Ordering::AcqRel),
+            PipeEnd::Write(pipe) => pipe.writers.fetch_add(1, Ordering::AcqRel),
+        };
+        Ok(self.insert(PipeHandle {
+            end,
+            nonblock: handle.nonblock,
+        }))
+    }
+
+    fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        // The handle lock is released before blocking.
+        let handle = self.get(id)?;
+        match handle.end {
+            PipeEnd::Read(pipe) => pipe.read(buf, handle.nonblock),
+            PipeEnd::Write(_) => ax_err!(BadFileDescriptor),
+        }
+    }
+
+    fn write(&self, id: usize, buf: &[u8]) -> AxResult<usize> {
+        let handle = self.get(id)?;
+        match handle.end {
+            PipeEnd::Read(_) => ax_err!(BadFileDescriptor),
+            PipeEnd::Write(pipe) => pipe.write(buf, handle.nonblock),
+        }
+    }
+
+    fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> AxResult<usize> {
+        let mut handles = self.handles.lock();
+        let handle = handles.get_mut(&id).ok_or(AxError::BadFileDescriptor)?;
+        match cmd {
+            F_SETFL => {
+                handle.nonblock = OpenFlags::from_bits_truncate(arg).contains(OpenFlags::NONBLOCK);
+                Ok(0)
+            }
+            _ => ax_err!(InvalidInput),
+        }
+    }
+
+    fn fsync(&self, id: usize) -> AxResult<usize> {
+        self.get(id).map(|_| 0)
+    }
+
+    fn close(&self, id: usize) -> AxResult<usize> {
+        let handle = self
+            .handles
+            .lock()
+            .remove(&id)
+            .ok_or(AxError::BadFileDescriptor)?;
+        match handle.end {
+            PipeEnd::Read(pipe) => {
+                if pipe.readers.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    pipe.write_wq.notify_all(false);
+                }
+            }
+            PipeEnd::Write(pipe) => {
+                if pipe.writers.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    pipe.read_wq.notify_all(false);
+                }
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl KernelScheme for PipeScheme {}
@@ -1,15 +1,16 @@
 extern crate alloc;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use alloc::{collections::BTreeMap, sync::Arc};
 use axerrno::{ax_err, AxError, AxResult};
 use axsync::Mutex;
-use scheme::Scheme;
-use syscall_number::io::OpenFlags;
+use core::fmt::Write;
+use scheme::{EventFlags, Scheme};
+use syscall_number::io::{OpenFlags, F_SETFL};
 
 use super::{
-    schemes,
+    current_ns, schemes,
     user::{UserInner, UserScheme},
     KernelScheme,
 };
@@ -19,7 +20,12 @@ pub struct RootScheme {
     next_id: AtomicUsize,
 }
 enum RootHandle {
-    Scheme(Arc<UserInner>),
+    Scheme {
+        inner: Arc<UserInner>,
+        flags: OpenFlags,
+    },
+    /// Names of all schemes when the handle is opened
+    List { data: String, offset: usize },
 }
 impl RootScheme {
     pub fn new() -> Self {
@@ -37,19 +43,43 @@ impl Scheme for RootScheme {
         if flags.contains(OpenFlags::CREATE) {
             // Create a user scheme
             let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            // The scheme is only visible in the namespace of its creator.
+            let ns = current_ns();
             let inner = {
                 let path_box = path.to_string().into_boxed_str();
-                let inner = Arc::new(UserInner::new(id, path_box));
-                schemes().insert(path, Arc::new(UserScheme::new(Arc::downgrade(&inner))));
-                inner
+                let mut schemes = schemes();
+                if schemes.find_name(ns, path).is_some() {
+                    return ax_err!(AlreadyExists);
+                }
+                let root_id = schemes.find_name(ns, "").unwrap();
+                Arc::new_cyclic(|inner| {
+                    let scheme_id = schemes
+                        .insert(ns, path, Arc::new(UserScheme::new(inner.clone())))
+                        .unwrap();
+                    UserInner::new(id, path_box, scheme_id, root_id)
+                })
             };
 
-            self.handles.lock().insert(id, RootHandle::Scheme(inner));
+            self.handles
+                .lock()
+                .insert(id, RootHandle::Scheme { inner, flags });
             trace!("Root Scheme: create {} -> {}", path, id);
             Ok(id)
         } else if path.is_empty() {
-            // list all schemes
-            todo!();
+            // list all schemes, one `<name>: kernel -` or `<name>: user <pid>` per line
+            let mut data = String::new();
+            for (name, scheme) in schemes().list(current_ns()) {
+                match scheme.owner() {
+                    Some(pid) => writeln!(data, "{}: user {}", name, pid),
+                    None => writeln!(data, "{}: kernel -", name),
+                }
+                .unwrap();
+            }
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            self.handles
+                .lock()
+                .insert(id, RootHandle::List { data, offset: 0 });
+            Ok(id)
         } else {
             // in redox, this was implemented as a unreadable and unwritable file,
             // we simply reject it.
@@ -65,23 +95,30 @@ impl Scheme for RootScheme {
             .ok_or(AxError::BadFileDescriptor)?;
 
         match handle {
-            RootHandle::Scheme(_inner) => {
-                // TODO: remove a scheme
-            }
+            RootHandle::Scheme { inner, .. } => inner.close(),
+            RootHandle::List { .. } => {}
         }
         Ok(0)
     }
 
     fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
-        let handles = self.handles.lock();
-        let handle = handles.get(&id).ok_or(AxError::BadFileDescriptor)?;
+        let mut handles = self.handles.lock();
+        let handle = handles.get_mut(&id).ok_or(AxError::BadFileDescriptor)?;
         trace!("Root Scheme {}: read", id);
         match handle {
-            RootHandle::Scheme(inner) => {
+            RootHandle::Scheme { inner, flags } => {
                 // There is a blocking situation, so lock must be dropped.
                 let inner = inner.clone();
+                let nonblock = flags.contains(OpenFlags::NONBLOCK);
                 drop(handles);
-                inner.scheme_read(buf)
+                inner.scheme_read(buf, nonblock)
+            }
+            RootHandle::List { data, offset } => {
+                let data = &data.as_bytes()[*offset..];
+                let len = buf.len().min(data.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                *offset += len;
+                Ok(len)
             }
         }
     }
@@ -91,7 +128,33 @@ impl Scheme for RootScheme {
         let handle = handles.get(&id).ok_or(AxError::BadFileDescriptor)?;
         trace!("Root Scheme {}: write", id);
         match handle {
-            RootHandle::Scheme(inner) => inner.scheme_write(buf),
+            RootHandle::Scheme { inner, .. } => inner.scheme_write(buf),
+            RootHandle::List { .. } => ax_err!(BadFileDescriptor),
+        }
+    }
+
+    /// Only `F_SETFL` is supported, `NONBLOCK` applies to reading requests.
+    fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> AxResult<usize> {
+        let mut handles = self.handles.lock();
+        let handle = handles.get_mut(&id).ok_or(AxError::BadFileDescriptor)?;
+        match cmd {
+            F_SETFL => {
+                if let RootHandle::Scheme { flags, .. } = handle {
+                    let nonblock = OpenFlags::from_bits_truncate(arg).contains(OpenFlags::NONBLOCK);
+                    flags.set(OpenFlags::NONBLOCK, nonblock);
+                }
+                Ok(0)
+            }
+            _ => ax_err!(InvalidInput),
+        }
+    }
+
+    /// Servers can wait for requests with the `event:` scheme.
+    fn fevent(&self, id: usize, flags: EventFlags) -> AxResult<EventFlags> {
+        let handles = self.handles.lock();
+        match handles.get(&id).ok_or(AxError::BadFileDescriptor)? {
+            RootHandle::Scheme { inner, .. } => Ok(inner.readiness() & flags),
+            RootHandle::List { .. } => Ok(EventFlags::EVENT_READ & flags),
         }
     }
 }
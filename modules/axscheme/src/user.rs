@@ -1,74 +1,127 @@
 extern crate alloc;
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
-use alloc::{boxed::Box, sync::Arc};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use alloc::{
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     sync::Weak,
 };
 use axerrno::{ax_err, from_ret_code, AxError, AxResult};
-use axhal::{mem::VirtAddr, paging::MappingFlags};
-use axmem::AddrSpace;
-use axsync::Mutex;
-use axtask::{current, current_pid, yield_now};
-use scheme::{Packet, Scheme, Stat};
+use axhal::{
+    mem::{VirtAddr, PAGE_SIZE_4K},
+    paging::MappingFlags,
+};
+use axmem::{AddrSpace, LentFrame};
+use axtask::{current_pid, WaitQueue};
+use scheme::{EventFlags, Map, MapFlags, Packet, Scheme, Stat, StatVfs, TimeSpec};
+use spinlock::SpinNoIrq;
 use syscall_number::{
-    SYS_CLOSE, SYS_DUP, SYS_FSTAT, SYS_FTRUNCATE, SYS_LSEEK, SYS_OPEN, SYS_READ, SYS_RMDIR,
-    SYS_UNLINK, SYS_WRITE,
+    SYS_CHMOD, SYS_CLOSE, SYS_DUP, SYS_FCHMOD, SYS_FCHOWN, SYS_FCNTL, SYS_FEVENT, SYS_FMAP,
+    SYS_FPATH, SYS_FRENAME, SYS_FSTAT, SYS_FSTATVFS, SYS_FSYNC, SYS_FTRUNCATE, SYS_FUNMAP,
+    SYS_FUTIMENS, SYS_LSEEK, SYS_OPEN, SYS_READ, SYS_RMDIR, SYS_UNLINK, SYS_WRITE,
 };
 
-use super::KernelScheme;
+use super::{
+    current_cred, event, read_user_copy, schemes, write_user_copy, KernelScheme, SchemeId,
+};
 
 pub struct UserInner {
-    #[allow(unused)]
+    /// id of the root handle of the server
     id: usize,
+    /// the root scheme, where the readiness of the root handle is posted
+    root_id: SchemeId,
     #[allow(unused)]
     name: Box<str>,
+    scheme_id: SchemeId,
     next_id: AtomicU64,
-    requests: Mutex<VecDeque<Packet>>,
+    // Both queues are checked inside `WaitQueue::wait_until`, where the run queue
+    // is locked, so they must be guarded by spinlocks instead of sleeping mutexes.
+    requests: SpinNoIrq<VecDeque<Packet>>,
+    /// server waits here for new requests
+    requests_wq: WaitQueue,
     /// response from server, Key is id, Value is return value.
-    response: Mutex<BTreeMap<u64, usize>>,
+    response: SpinNoIrq<BTreeMap<u64, usize>>,
+    /// ids of requests taken by the server whose clients are interrupted,
+    /// their responses are dropped. Accessed with `response` locked.
+    abandoned: SpinNoIrq<BTreeSet<u64>>,
+    /// clients wait here for their responses
+    response_wq: WaitQueue,
+    /// set when the server closes its root handle
+    closed: AtomicBool,
     #[cfg(feature = "process")]
     /// pid of the server
     pid: u64,
 }
 
 impl UserInner {
-    pub fn new(id: usize, path: Box<str>) -> UserInner {
+    pub fn new(id: usize, path: Box<str>, scheme_id: SchemeId, root_id: SchemeId) -> UserInner {
         UserInner {
             id,
+            root_id,
             name: path,
+            scheme_id,
             next_id: 1.into(),
-            requests: Mutex::new(VecDeque::new()),
-            response: Mutex::new(BTreeMap::new()),
+            requests: SpinNoIrq::new(VecDeque::new()),
+            requests_wq: WaitQueue::new(),
+            response: SpinNoIrq::new(BTreeMap::new()),
+            abandoned: SpinNoIrq::new(BTreeSet::new()),
+            response_wq: WaitQueue::new(),
+            closed: AtomicBool::new(false),
             #[cfg(feature = "process")]
             pid: current_pid().unwrap(),
         }
     }
-    /// read a request from the clients to the server
-    pub fn scheme_read(&self, buf: &mut [u8]) -> AxResult<usize> {
+    /// read requests from the clients to the server
+    ///
+    /// Blocks until at least one request arrives (or returns `WouldBlock` if `nonblock`),
+    /// then takes as many pending requests as `buf` can hold.
+    /// Returns 0 once the scheme is closed.
+    pub fn scheme_read(&self, buf: &mut [u8], nonblock: bool) -> AxResult<usize> {
         let buf: &mut [Packet] = unsafe {
             let ptr = buf.as_mut_ptr() as *mut Packet;
             core::slice::from_raw_parts_mut(ptr, buf.len() / core::mem::size_of::<Packet>())
         };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Several server threads may read at the same time, so the queue can be empty again
+        // when a reader is woken up.
+        let mut requests = loop {
+            if !nonblock
+                && !self.requests_wq.wait_until_interruptible(|| {
+                    self.closed.load(Ordering::Acquire) || !self.requests.lock().is_empty()
+                })
+            {
+                return ax_err!(Interrupted);
+            }
+            let requests = self.requests.lock();
+            if !requests.is_empty() {
+                break requests;
+            }
+            if nonblock {
+                return ax_err!(WouldBlock);
+            }
+            if self.closed.load(Ordering::Acquire) {
+                return Ok(0);
+            }
+        };
+        let mut count = 0;
         for copy_item in buf.iter_mut() {
-            loop {
-                let request = self.requests.lock().pop_front();
-                if let Some(request) = request {
-                    trace!("Root recv {:#?}", request);
-                    let _ = core::mem::replace(copy_item, request);
-                    break;
-                } else {
-                    // TODO: option to return EAGAIN
-                    // TODO: use blocking instead of yield
-                    assert!(!self.requests.is_locked());
-                    yield_now();
-                }
+            if let Some(request) = requests.pop_front() {
+                trace!("Root recv {:#?}", request);
+                *copy_item = request;
+                count += 1;
+            } else {
+                break;
             }
         }
-        Ok(core::mem::size_of_val(buf))
+        Ok(count * core::mem::size_of::<Packet>())
     }
     /// Write a response form the server
+    ///
+    /// A packet with `id == 0` and `a == SYS_FEVENT` is not a response,
+    /// but posts readiness `c` of file `b` instead.
     pub fn scheme_write(&self, buf: &[u8]) -> AxResult<usize> {
         if buf.len() % core::mem::size_of::<Packet>() != 0 {
             return ax_err!(InvalidData);
@@ -78,34 +131,115 @@ impl UserInner {
             core::slice::from_raw_parts(ptr, buf.len() / core::mem::size_of::<Packet>())
         };
         for result_item in buf.iter() {
+            if result_item.id == 0 && result_item.a == SYS_FEVENT {
+                trace!("Root event {} -> {:#x}", result_item.b, result_item.c);
+                event::trigger(
+                    self.scheme_id,
+                    result_item.b,
+                    EventFlags::from_bits_truncate(result_item.c),
+                );
+                continue;
+            }
             trace!("Root send {} -> {}", result_item.id, result_item.a);
-            self.response.lock().insert(result_item.id, result_item.a);
+            let mut response = self.response.lock();
+            if !self.abandoned.lock().remove(&result_item.id) {
+                response.insert(result_item.id, result_item.a);
+            }
         }
+        // Each client checks its own id, so all of them have to be woken up.
+        self.response_wq.notify_all(false);
         Ok(core::mem::size_of_val(buf))
     }
 
     pub fn handle_request(&self, a: usize, b: usize, c: usize, d: usize) -> AxResult<usize> {
+        let cred = current_cred();
+        self.handle_request_as(cred.euid, cred.egid, a, b, c, d)
+    }
+
+    /// Sends a request on behalf of user `uid` in group `gid` of current process.
+    ///
+    /// Returns `Interrupted` if current task is killed before the response arrives.
+    pub fn handle_request_as(
+        &self,
+        uid: u32,
+        gid: u32,
+        a: usize,
+        b: usize,
+        c: usize,
+        d: usize,
+    ) -> AxResult<usize> {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let packet = Packet {
             id,
-            uid: 0,
-            gid: 0,
-            pid: current().id().as_u64() as usize,
+            uid,
+            gid,
+            pid: current_pid().unwrap_or(0) as usize,
             a,
             b,
             c,
             d,
         };
         trace!("User Request: {:#?}", packet);
+        if self.closed.load(Ordering::Acquire) {
+            return ax_err!(ConnectionReset);
+        }
         self.requests.lock().push_back(packet);
-        loop {
-            let value = self.response.lock().remove(&id);
-            if let Some(value) = value {
-                return from_ret_code(value as isize);
-            } else {
-                assert!(!self.response.is_locked());
-                yield_now();
-            }
+        self.requests_wq.notify_one(false);
+        event::trigger(self.root_id, self.id, EventFlags::EVENT_READ);
+
+        if !self.response_wq.wait_until_interruptible(|| {
+            self.closed.load(Ordering::Acquire) || self.response.lock().contains_key(&id)
+        }) {
+            self.abandon(id);
+            return ax_err!(Interrupted);
+        }
+        let value = self
+            .response
+            .lock()
+            .remove(&id)
+            .ok_or(AxError::ConnectionReset)?;
+        from_ret_code(value as isize)
+    }
+
+    /// Forgets request `id` of an interrupted client: it is dropped if still queued,
+    /// otherwise its response is dropped once the server sends it.
+    ///
+    /// Closes are still sent, e.g. those of a killed process closing its files.
+    fn abandon(&self, id: u64) {
+        let mut requests = self.requests.lock();
+        let queued = requests.len();
+        requests.retain(|request| request.id != id || request.a == SYS_CLOSE);
+        if requests.len() < queued {
+            return;
+        }
+        drop(requests);
+        let mut response = self.response.lock();
+        if response.remove(&id).is_none() {
+            self.abandoned.lock().insert(id);
+        }
+    }
+
+    /// Shuts the scheme down when its server closes the root handle.
+    ///
+    /// The name is unregistered, and all pending and future requests fail with `ConnectionReset`.
+    pub fn close(&self) {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        trace!("Root close scheme {}", self.name);
+        schemes().remove(self.scheme_id);
+        self.requests.lock().clear();
+        self.requests_wq.notify_all(false);
+        self.response_wq.notify_all(false);
+        event::trigger(self.root_id, self.id, EventFlags::EVENT_READ);
+    }
+
+    /// The root handle is readable when requests are pending, or once it is closed.
+    pub fn readiness(&self) -> EventFlags {
+        if self.closed.load(Ordering::Acquire) || !self.requests.lock().is_empty() {
+            EventFlags::EVENT_READ
+        } else {
+            EventFlags::empty()
         }
     }
 }
@@ -119,32 +253,44 @@ impl UserScheme {
     }
 }
 impl Scheme for UserScheme {
-    fn open(&self, path: &str, flags: usize, _uid: u32, _gid: u32) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+    fn open(&self, path: &str, flags: usize, uid: u32, gid: u32) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let addr = ShadowMemory::new(path.as_bytes(), inner.pid)?;
-        inner.handle_request(SYS_OPEN, addr.addr().into(), path.len(), flags)
+        inner.handle_request_as(uid, gid, SYS_OPEN, addr.addr().into(), path.len(), flags)
+    }
+    fn chmod(&self, path: &str, mode: u16, uid: u32, gid: u32) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let addr = ShadowMemory::new(path.as_bytes(), inner.pid)?;
+        inner.handle_request_as(
+            uid,
+            gid,
+            SYS_CHMOD,
+            addr.addr().into(),
+            path.len(),
+            mode as usize,
+        )
     }
     fn read(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let addr = ShadowMemoryMut::new(buf, inner.pid)?;
         inner.handle_request(SYS_READ, id, addr.addr().into(), addr.len())
     }
     fn write(&self, id: usize, buf: &[u8]) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let addr = ShadowMemory::new(buf, inner.pid)?;
         inner.handle_request(SYS_WRITE, id, addr.addr().into(), buf.len())
     }
     fn close(&self, id: usize) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         inner.handle_request(SYS_CLOSE, id, 0, 0)
     }
     fn dup(&self, id: usize, buf: &[u8]) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let addr = ShadowMemory::new(buf, inner.pid)?;
         inner.handle_request(SYS_DUP, id, addr.addr().into(), buf.len())
     }
     fn fstat(&self, id: usize, stat: &mut Stat) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let buf = unsafe {
             core::slice::from_raw_parts_mut(
                 stat as *mut Stat as *mut u8,
@@ -155,33 +301,141 @@ impl Scheme for UserScheme {
         inner.handle_request(SYS_FSTAT, id, addr.addr().into(), addr.len())
     }
     fn ftruncate(&self, id: usize, len: usize) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         inner.handle_request(SYS_FTRUNCATE, id, len, 0)
     }
     fn fsync(&self, id: usize) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
-        inner.handle_request(SYS_FTRUNCATE, id, 0, 0)
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        inner.handle_request(SYS_FSYNC, id, 0, 0)
+    }
+    fn fstatvfs(&self, id: usize, stat: &mut StatVfs) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let buf = unsafe {
+            core::slice::from_raw_parts_mut(
+                stat as *mut StatVfs as *mut u8,
+                core::mem::size_of::<StatVfs>(),
+            )
+        };
+        let addr = ShadowMemoryMut::new(buf, inner.pid)?;
+        inner.handle_request(SYS_FSTATVFS, id, addr.addr().into(), addr.len())
+    }
+    fn futimens(&self, id: usize, times: &[TimeSpec]) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let buf = unsafe {
+            core::slice::from_raw_parts(times.as_ptr() as *const u8, core::mem::size_of_val(times))
+        };
+        let addr = ShadowMemory::new(buf, inner.pid)?;
+        inner.handle_request(SYS_FUTIMENS, id, addr.addr().into(), buf.len())
+    }
+    fn fchmod(&self, id: usize, mode: u16) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        inner.handle_request(SYS_FCHMOD, id, mode as usize, 0)
+    }
+    fn fchown(&self, id: usize, uid: u32, gid: u32) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        inner.handle_request(SYS_FCHOWN, id, uid as usize, gid as usize)
+    }
+    fn fcntl(&self, id: usize, cmd: usize, arg: usize) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        inner.handle_request(SYS_FCNTL, id, cmd, arg)
+    }
+    fn fpath(&self, id: usize, buf: &mut [u8]) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let addr = ShadowMemoryMut::new(buf, inner.pid)?;
+        inner.handle_request(SYS_FPATH, id, addr.addr().into(), addr.len())
+    }
+    fn frename(&self, id: usize, path: &str, uid: u32, gid: u32) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let addr = ShadowMemory::new(path.as_bytes(), inner.pid)?;
+        inner.handle_request_as(uid, gid, SYS_FRENAME, id, addr.addr().into(), path.len())
     }
     fn seek(&self, id: usize, pos: isize, whence: usize) -> AxResult<isize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         inner
             .handle_request(SYS_LSEEK, id, pos as usize, whence)
             .map(|x| x as isize)
     }
+    fn fevent(&self, id: usize, flags: EventFlags) -> AxResult<EventFlags> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        inner
+            .handle_request(SYS_FEVENT, id, flags.bits(), 0)
+            .map(EventFlags::from_bits_truncate)
+    }
+    fn fmap(&self, id: usize, map: &Map) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let addr = ShadowMemory::new(map, inner.pid)?;
+        inner.handle_request(SYS_FMAP, id, addr.addr().into(), map.len())
+    }
+    fn funmap(&self, address: usize, length: usize) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        inner.handle_request(SYS_FUNMAP, address, length, 0)
+    }
 
-    fn rmdir(&self, path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+    fn rmdir(&self, path: &str, uid: u32, gid: u32) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let addr = ShadowMemory::new(path.as_bytes(), inner.pid)?;
-        inner.handle_request(SYS_RMDIR, addr.addr().into(), path.len(), 0)
+        inner.handle_request_as(uid, gid, SYS_RMDIR, addr.addr().into(), path.len(), 0)
     }
 
-    fn unlink(&self, path: &str, _uid: u32, _gid: u32) -> AxResult<usize> {
-        let inner = self.inner.upgrade().ok_or(AxError::NotFound)?;
+    fn unlink(&self, path: &str, uid: u32, gid: u32) -> AxResult<usize> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
         let addr = ShadowMemory::new(path.as_bytes(), inner.pid)?;
-        inner.handle_request(SYS_UNLINK, addr.addr().into(), path.len(), 0)
+        inner.handle_request_as(uid, gid, SYS_UNLINK, addr.addr().into(), path.len(), 0)
+    }
+}
+impl KernelScheme for UserScheme {
+    fn owner(&self) -> Option<u64> {
+        self.inner.upgrade().map(|inner| inner.pid)
+    }
+    fn read_user(&self, id: usize, buf: VirtAddr, len: usize) -> AxResult<usize> {
+        if !should_lend(buf, len) {
+            return read_user_copy(self, id, buf, len);
+        }
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let addr = LentMemory::new(buf, len, inner.pid, true)?;
+        inner.handle_request(SYS_READ, id, addr.addr().into(), len)
+    }
+    fn write_user(&self, id: usize, buf: VirtAddr, len: usize) -> AxResult<usize> {
+        if !should_lend(buf, len) {
+            return write_user_copy(self, id, buf, len);
+        }
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let addr = LentMemory::new(buf, len, inner.pid, false)?;
+        inner.handle_request(SYS_WRITE, id, addr.addr().into(), len)
+    }
+    /// The server returns an address in its own address space, whose frames are shared.
+    fn fmap_frames(&self, id: usize, map: &Map) -> AxResult<(usize, Vec<LentFrame>)> {
+        let inner = self.inner.upgrade().ok_or(AxError::ConnectionReset)?;
+        let address = self.fmap(id, map)?;
+        let frames = if VirtAddr::from(address).is_aligned_4k() {
+            let addr_space = call_interface!(FindAddrSpace::find_addr_space, inner.pid)
+                .ok_or(AxError::ConnectionReset)?;
+            let write = map.flags.contains(MapFlags::PROT_WRITE);
+            let frames = addr_space
+                .lock()
+                .query_frames(address.into(), map.size, write);
+            frames
+        } else {
+            ax_err!(BadAddress)
+        };
+        if frames.is_err() {
+            let _ = self.funmap(address, map.size);
+        }
+        Ok((address, frames?))
     }
 }
-impl KernelScheme for UserScheme {}
+
+/// Buffers of at least this size are lent to the server instead of being copied.
+const LEND_THRESHOLD: usize = 4 * PAGE_SIZE_4K;
+
+/// Whether a client buffer should be lent to the server.
+///
+/// Only the pages inside the buffer are lent, the partial pages at its ends are copied,
+/// so small unaligned buffers are copied altogether.
+fn should_lend(buf: VirtAddr, len: usize) -> bool {
+    len >= LEND_THRESHOLD || (len > 0 && buf.is_aligned_4k() && len % PAGE_SIZE_4K == 0)
+}
+
 struct TempMemory {
     page_start: VirtAddr,
     page_end: VirtAddr,
@@ -203,17 +457,74 @@ impl ShadowMemory {
             MappingFlags::READ | MappingFlags::USER,
         )?;
         let page_end = page_start + data.len();
-        copy_buffer_to_user(pid, page_start, data);
+        let mem = TempMemory {
+            page_start,
+            page_end,
+            pid,
+        };
+        copy_buffer_to_user(pid, page_start, data)?;
+        Ok(Self { mem })
+    }
+    fn addr(&self) -> VirtAddr {
+        self.mem.page_start
+    }
+}
+/// Pages of current process (the client) mapped into the server's address space.
+///
+/// The server never sees client data outside the buffer: partial pages at its ends
+/// are mapped as copies, written back on drop if `write` is set.
+struct LentMemory {
+    mem: TempMemory,
+    offset: usize,
+    /// client frames of the partial pages, with their copies and the ranges of the buffer in them
+    partial: Vec<(LentFrame, LentFrame, Range<usize>)>,
+    write: bool,
+}
+impl LentMemory {
+    fn new(buf: VirtAddr, len: usize, pid: u64, write: bool) -> AxResult<Self> {
+        // The loans keep the frames alive even if the client unmaps the buffer meanwhile.
+        let mut frames = axmem::query_frames(buf, len, write)?;
+        let mut partial = Vec::new();
+        let (start, end) = (buf.as_usize(), buf.as_usize() + len);
+        for (i, frame) in frames.iter_mut().enumerate() {
+            let page = buf.align_down_4k().as_usize() + i * PAGE_SIZE_4K;
+            let range = start.max(page) - page..end.min(page + PAGE_SIZE_4K) - page;
+            if range.len() < PAGE_SIZE_4K {
+                let copy = LentFrame::alloc()?;
+                copy.copy_from(frame, range.clone());
+                let client = core::mem::replace(frame, copy.clone());
+                partial.push((client, copy, range));
+            }
+        }
+        let mut flags = MappingFlags::READ | MappingFlags::USER;
+        if write {
+            flags |= MappingFlags::WRITE;
+        }
+        let addr_space =
+            call_interface!(FindAddrSpace::find_addr_space, pid).ok_or(AxError::ConnectionReset)?;
+        let page_start = addr_space.lock().mmap_lend(&frames, flags)?;
         Ok(Self {
             mem: TempMemory {
                 page_start,
-                page_end,
+                page_end: page_start + frames.len() * PAGE_SIZE_4K,
                 pid,
             },
+            offset: buf.align_offset_4k(),
+            partial,
+            write,
         })
     }
     fn addr(&self) -> VirtAddr {
-        self.mem.page_start
+        self.mem.page_start + self.offset
+    }
+}
+impl Drop for LentMemory {
+    fn drop(&mut self) {
+        if self.write {
+            for (client, copy, range) in &self.partial {
+                client.copy_from(copy, range.clone());
+            }
+        }
     }
 }
 impl<'a> ShadowMemoryMut<'a> {
@@ -243,12 +554,17 @@ impl<'a> ShadowMemoryMut<'a> {
 }
 impl Drop for TempMemory {
     fn drop(&mut self) {
-        munmap(
+        // An interrupted client may leave its request to the server, which can still be
+        // using the pages then, so they are kept until the server exits.
+        if axtask::current().is_killed() {
+            return;
+        }
+        // The server may have exited in the meantime.
+        let _ = munmap(
             self.pid,
             self.page_start,
             (self.page_end - self.page_start.into()).into(),
-        )
-        .unwrap();
+        );
     }
 }
 impl<'a> Drop for ShadowMemoryMut<'a> {
@@ -266,23 +582,23 @@ pub trait FindAddrSpace {
 }
 
 fn mmap(pid: u64, addr: Option<VirtAddr>, len: usize, flags: MappingFlags) -> AxResult<VirtAddr> {
-    let addr_space = call_interface!(FindAddrSpace::find_addr_space, pid).unwrap();
+    let addr_space =
+        call_interface!(FindAddrSpace::find_addr_space, pid).ok_or(AxError::ConnectionReset)?;
     let ret = addr_space.lock().mmap_page(addr, len, flags);
     ret
 }
 
 fn munmap(pid: u64, addr: VirtAddr, len: usize) -> AxResult<()> {
-    let addr_space = call_interface!(FindAddrSpace::find_addr_space, pid).unwrap();
+    let addr_space =
+        call_interface!(FindAddrSpace::find_addr_space, pid).ok_or(AxError::ConnectionReset)?;
     let ret = addr_space.lock().munmap_page(addr, len);
     ret
 }
 
-fn copy_buffer_to_user(pid: u64, dest: VirtAddr, data: &[u8]) {
-    let addr_space = call_interface!(FindAddrSpace::find_addr_space, pid).unwrap();
-    let paddrs = addr_space
-        .lock()
-        .translate_buffer(dest, data.len(), true)
-        .unwrap();
+fn copy_buffer_to_user(pid: u64, dest: VirtAddr, data: &[u8]) -> AxResult<()> {
+    let addr_space =
+        call_interface!(FindAddrSpace::find_addr_space, pid).ok_or(AxError::ConnectionReset)?;
+    let paddrs = addr_space.lock().translate_buffer(dest, data.len(), true)?;
     let mut tot = 0;
     for paddr in paddrs {
         let len = paddr.len().min(data.len() - tot);
@@ -291,13 +607,16 @@ fn copy_buffer_to_user(pid: u64, dest: VirtAddr, data: &[u8]) {
             tot += len;
         }
     }
+    Ok(())
 }
 fn copy_buffer_from_user(pid: u64, dest: VirtAddr, data: &mut [u8]) {
-    let addr_space = call_interface!(FindAddrSpace::find_addr_space, pid).unwrap();
-    let paddrs = addr_space
-        .lock()
-        .translate_buffer(dest, data.len(), true)
-        .unwrap();
+    let Some(addr_space) = call_interface!(FindAddrSpace::find_addr_space, pid) else {
+        return;
+    };
+    // The server may have unmapped the buffer itself.
+    let Ok(paddrs) = addr_space.lock().translate_buffer(dest, data.len(), false) else {
+        return;
+    };
     let mut tot = 0;
     for paddr in paddrs {
         let len = paddr.len().min(data.len() - tot);
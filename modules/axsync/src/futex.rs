@@ -4,39 +4,37 @@ use core::sync::atomic::{AtomicU32, Ordering};
 use alloc::{collections::BTreeMap, sync::Arc};
 use axtask::WaitQueue;
 use lazy_init::LazyInit;
-use memory_addr::PhysAddr;
 use spinlock::SpinNoIrq;
 
 extern crate alloc;
 
-struct FutexPool(SpinNoIrq<BTreeMap<PhysAddr, Arc<WaitQueue>>>);
+/// Identifies a private futex: the address space and the virtual address of the word,
+/// which stay the same when the word moves to another frame (e.g. on copy-on-write).
+pub type FutexKey = (usize, usize);
 
-impl FutexPool {
-    pub(crate) fn current_wait(&self, paddr: PhysAddr, val: u32) -> bool {
-        if !paddr.is_aligned(4usize) {
-            panic!("Align is invalid!");
-        }
-
-        let data: &'static AtomicU32 = unsafe { &*(paddr.as_usize() as *const u32).cast() };
+struct FutexPool(SpinNoIrq<BTreeMap<FutexKey, Arc<WaitQueue>>>);
 
+impl FutexPool {
+    pub(crate) fn current_wait(&self, key: FutexKey, data: &AtomicU32, val: u32) -> bool {
         if data.load(Ordering::Acquire) != val {
             return false;
         }
-        if let Some(queue) = self.0.lock().get(&paddr).map(Arc::clone) {
-            queue.wait();
-        } else {
-            let queue = Arc::new(WaitQueue::new());
-            self.0.lock().insert(paddr, queue.clone()).unwrap();
-            queue.wait();
-        }
+        let queue = self
+            .0
+            .lock()
+            .entry(key)
+            .or_insert_with(|| Arc::new(WaitQueue::new()))
+            .clone();
+        // A killed task returns as if woken up.
+        queue.wait_interruptible();
         true
     }
-    pub(crate) fn current_wake(&self, paddr: PhysAddr, val: u32) -> u32 {
+    pub(crate) fn current_wake(&self, key: FutexKey, val: u32) -> u32 {
         for i in 0..val {
-            if let Some(queue) = self.0.lock().get(&paddr).map(Arc::clone) {
+            if let Some(queue) = self.0.lock().get(&key).map(Arc::clone) {
                 queue.notify_one(true);
                 if queue.is_empty() {
-                    self.0.lock().remove(&paddr);
+                    self.0.lock().remove(&key);
                 }
             } else {
                 return i;
@@ -56,17 +54,17 @@ pub fn init() {
     FUTEX_GLOBAL_POOL.init_by(FutexPool(SpinNoIrq::new(BTreeMap::new())));
 }
 
-/// Handles futex operations, see `man 2 futex`.
-pub fn futex_call(paddr: usize, op: usize, val: u32) -> isize {
+/// Handles futex operations on `word`, see `man 2 futex`.
+pub fn futex_call(key: FutexKey, word: &AtomicU32, op: usize, val: u32) -> isize {
     match op {
         FUTEX_WAIT => {
-            if FUTEX_GLOBAL_POOL.current_wait(paddr.into(), val) {
+            if FUTEX_GLOBAL_POOL.current_wait(key, word, val) {
                 0
             } else {
                 -2
             }
         }
-        FUTEX_WAKE => FUTEX_GLOBAL_POOL.current_wake(paddr.into(), val) as isize,
+        FUTEX_WAKE => FUTEX_GLOBAL_POOL.current_wake(key, val) as isize,
         _ => -1,
     }
 }
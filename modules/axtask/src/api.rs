@@ -89,10 +89,10 @@ pub fn on_timer_tick() {
 
 cfg_if::cfg_if! {
 if #[cfg(all(feature = "user-paging", not(feature = "test")))] {
-pub fn spawn_args(f: usize, arg: usize) -> AxTaskRef {
-    let task = TaskInner::new_user(f, axconfig::TASK_STACK_SIZE, arg);
+pub fn spawn_args(f: usize, arg: usize, stack_size: usize) -> axerrno::AxResult<AxTaskRef> {
+    let task = TaskInner::new_user(f, axconfig::TASK_STACK_SIZE, arg, stack_size)?;
     RUN_QUEUE.lock().add_task(task.clone());
-    task
+    Ok(task)
 }
 pub fn spawn<F>(_f: F) -> AxTaskRef
 where
@@ -110,7 +110,7 @@ where
 } else {
 /// Spawns a new task with an argument
 /// only available in with user syscalls
-pub fn spawn_args(_f: usize, _arg: usize) -> AxTaskRef {
+pub fn spawn_args(_f: usize, _arg: usize, _stack_size: usize) -> axerrno::AxResult<AxTaskRef> {
     unimplemented!();
 }
 
@@ -184,6 +184,14 @@ pub fn sleep_until(deadline: axhal::time::TimeValue) {
     axhal::time::busy_wait_until(deadline);
 }
 
+/// Kills the given task: it is woken up if it is blocked in an interruptible
+/// wait, and such waits (including sleeps) return at once from now on.
+///
+/// The task keeps running, it is up to the task to exit.
+pub fn kill(task: &AxTaskRef) {
+    RUN_QUEUE.lock().kill_task(task);
+}
+
 /// Exits the current task.
 pub fn exit(exit_code: i32) -> ! {
     RUN_QUEUE.lock().exit_current(exit_code)
@@ -136,6 +136,24 @@ impl AxRunQueue {
         }
     }
 
+    /// Marks `task` as killed, and wakes it up if it is blocked in an
+    /// interruptible wait or a sleep.
+    ///
+    /// The task is left in its wait queue, it removes itself once it runs
+    /// (see `WaitQueue::cancel_events`), and notifications skip it until then.
+    pub fn kill_task(&mut self, task: &AxTaskRef) {
+        debug!("task kill: {}", task.id_name());
+        task.set_killed();
+        if !task.is_blocked() || !task.is_interruptible() {
+            return;
+        }
+        #[cfg(feature = "irq")]
+        if task.in_timer_list() {
+            crate::timers::cancel_alarm(task);
+        }
+        self.unblock_task(task.clone(), false);
+    }
+
     #[cfg(feature = "irq")]
     pub fn sleep_until(&mut self, deadline: axhal::time::TimeValue) {
         let curr = crate::current();
@@ -144,10 +162,12 @@ impl AxRunQueue {
         assert!(!curr.is_idle());
 
         let now = axhal::time::current_time();
-        if now < deadline {
+        if now < deadline && !curr.is_killed() {
             crate::timers::set_alarm_wakeup(deadline, curr.clone());
+            curr.set_interruptible(true);
             curr.set_state(TaskState::Blocked);
             self.resched_inner(false);
+            curr.set_interruptible(false);
         }
     }
 }
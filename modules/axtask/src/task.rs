@@ -38,6 +38,10 @@ pub struct TaskInner {
     in_wait_queue: AtomicBool,
     #[cfg(feature = "irq")]
     in_timer_list: AtomicBool,
+    /// set by [`kill`](crate::kill), the interruptible waits of the task return at once then
+    killed: AtomicBool,
+    /// whether the task is blocked in an interruptible wait (or sleep), which `kill` wakes up
+    interruptible: AtomicBool,
 
     #[cfg(feature = "preempt")]
     need_resched: AtomicBool,
@@ -53,7 +57,8 @@ pub struct TaskInner {
     #[cfg(feature = "user-paging")]
     trap_frame: Option<(Arc<axalloc::GlobalPage>, VirtAddr)>,
     #[cfg(feature = "user-paging")]
-    ustack: Option<(Arc<axalloc::GlobalPage>, VirtAddr)>,
+    /// lowest address and size of the user stack
+    ustack: Option<(VirtAddr, usize)>,
     #[cfg(feature = "process")]
     pid: AtomicU64,
 }
@@ -104,11 +109,17 @@ impl TaskInner {
 
     /// Wait for the task to exit, and return the exit code.
     ///
-    /// It will return immediately if the task has already exited (but not dropped).
+    /// It will return immediately if the task has already exited (but not dropped),
+    /// or return `None` if the current task is killed before.
     pub fn join(&self) -> Option<i32> {
         self.wait_for_exit
-            .wait_until(|| self.state() == TaskState::Exited);
-        Some(self.exit_code.load(Ordering::Acquire))
+            .wait_until_interruptible(|| self.state() == TaskState::Exited)
+            .then(|| self.exit_code.load(Ordering::Acquire))
+    }
+
+    /// Whether the task is killed, its interruptible waits return at once then.
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Acquire)
     }
 
     /// current task's pid
@@ -123,25 +134,22 @@ impl TaskInner {
     where
         F: Fn(VirtAddr),
     {
-        remove_fn(self.ustack.as_ref().unwrap().1);
+        // A forked task runs on the stack copied from its parent, which is not its own.
+        if let Some((ustack, _)) = self.ustack {
+            remove_fn(ustack);
+        }
         remove_fn(self.trap_frame.as_ref().unwrap().1);
     }
 }
 
 #[cfg(feature = "user-paging")]
 impl TaskInner {
-    fn setup_ustack(&mut self) {
-        use axhal::paging::MappingFlags;
-
-        let ustack_start = get_ustack_vaddr(self.id);
-        self.ustack = Some((
-            axmem::alloc_user_page(
-                ustack_start,
-                axmem::USTACK_SIZE,
-                MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
-            ),
-            ustack_start,
-        ));
+    /// The stack is reserved with `size` bytes, and grows on demand.
+    fn setup_ustack(&mut self, size: usize) -> axerrno::AxResult {
+        let size = align_up_4k(size);
+        let ustack_start = axmem::alloc_user_stack(size)?;
+        self.ustack = Some((ustack_start, size));
+        Ok(())
     }
 
     fn setup_trapframe(&mut self, start: usize) {
@@ -152,18 +160,36 @@ impl TaskInner {
             TRAP_FRAME_SIZE,
             MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
         );
-        let ustack_start = self.ustack.as_ref().unwrap().1;
+        let (ustack_start, ustack_size) = self.ustack.unwrap();
+        // The TLS block of the thread is at the top of its stack, the thread pointer
+        // is saved and restored with the other user registers in the trap frame.
+        let top = ustack_start + ustack_size;
+        let tp = axmem::init_user_tls(top);
 
         // TODO: make a HAL wrapper
         #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
         unsafe {
             let trap_frame = &mut *(trap_frame.as_ptr() as *mut axhal::arch::TrapFrame);
-            *trap_frame =
-                axhal::arch::TrapFrame::new(start, (ustack_start + axmem::USTACK_SIZE).into());
+            *trap_frame = axhal::arch::TrapFrame::new(start, tp.unwrap_or(top).into());
+            trap_frame.regs.tp = tp.map_or(0, usize::from);
             trap_frame.kstack = self.kstack.as_ref().unwrap().top().into();
         }
         self.trap_frame = Some((trap_frame, tf_addr));
     }
+
+    /// Puts `args` and `envs` (strings each ended by `\0`) and the auxiliary vector
+    /// on the user stack (below the TLS block), whose pointer is passed in `a0` as well.
+    fn setup_args(&mut self, args: &[u8], envs: &[u8]) {
+        // TODO: make a HAL wrapper
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        unsafe {
+            let trap_frame =
+                &mut *(self.trap_frame.as_ref().unwrap().0.as_ptr() as *mut axhal::arch::TrapFrame);
+            let sp = axmem::init_user_stack(trap_frame.regs.sp.into(), args, envs);
+            trap_frame.regs.sp = sp.into();
+            trap_frame.regs.a0 = sp.into();
+        }
+    }
 }
 
 // private methods
@@ -179,6 +205,8 @@ impl TaskInner {
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
             in_timer_list: AtomicBool::new(false),
+            killed: AtomicBool::new(false),
+            interruptible: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
@@ -218,14 +246,15 @@ impl TaskInner {
         entry: usize,
         kstack_size: usize,
         #[allow(unused)] args: usize,
-    ) -> AxTaskRef {
+        ustack_size: usize,
+    ) -> axerrno::AxResult<AxTaskRef> {
         let mut t = Self::new_common(TaskId::new(), "".into());
         debug!("new user task: {} {}", t.id_name(), entry);
         let kstack = TaskStack::alloc(align_up_4k(kstack_size));
         t.ctx.get_mut().init(task_user_entry as usize, kstack.top());
         t.kstack = Some(kstack);
 
-        t.setup_ustack();
+        t.setup_ustack(ustack_size)?;
         t.setup_trapframe(entry);
 
         #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
@@ -240,7 +269,7 @@ impl TaskInner {
             t.pid = current_pid().unwrap().into();
         }
 
-        Arc::new(AxTask::new(t))
+        Ok(Arc::new(AxTask::new(t)))
     }
 
     pub(crate) fn new_init(name: String) -> AxTaskRef {
@@ -253,12 +282,13 @@ impl TaskInner {
         debug!("init task: {}", t.id_name());
         #[cfg(feature = "user-paging")]
         {
-            t.setup_ustack();
+            t.setup_ustack(axmem::stack_limit()).expect("Memory Error");
 
             let kstack = TaskStack::alloc(axconfig::TASK_STACK_SIZE);
             t.kstack = Some(kstack);
 
-            t.setup_trapframe(axmem::USER_START);
+            t.setup_trapframe(axmem::entry().into());
+            t.setup_args(&[], &[]);
         }
 
         #[cfg(feature = "process")]
@@ -270,7 +300,7 @@ impl TaskInner {
     }
 
     #[cfg(all(feature = "user-paging", feature = "process"))]
-    pub(crate) fn new_exec() -> AxTaskRef {
+    pub(crate) fn new_exec(args: alloc::vec::Vec<u8>, envs: alloc::vec::Vec<u8>) -> AxTaskRef {
         let mut t = Self::new_common(TaskId::new(), String::new());
         t.is_init = true;
         debug!("task exec: {}", t.id_name());
@@ -279,9 +309,10 @@ impl TaskInner {
         t.ctx.get_mut().init(task_user_entry as usize, kstack.top());
         t.kstack = Some(kstack);
 
-        t.setup_ustack();
+        t.setup_ustack(axmem::stack_limit()).expect("Memory Error");
 
-        t.setup_trapframe(axmem::USER_START);
+        t.setup_trapframe(axmem::entry().into());
+        t.setup_args(&args, &envs);
 
         t.pid
             .store(current().pid.load(Ordering::Relaxed), Ordering::Relaxed);
@@ -390,6 +421,21 @@ impl TaskInner {
         self.in_timer_list.store(in_timer_list, Ordering::Release);
     }
 
+    #[inline]
+    pub(crate) fn set_killed(&self) {
+        self.killed.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn is_interruptible(&self) -> bool {
+        self.interruptible.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_interruptible(&self, interruptible: bool) {
+        self.interruptible.store(interruptible, Ordering::Release);
+    }
+
     #[inline]
     #[cfg(feature = "preempt")]
     pub(crate) fn set_preempt_pending(&self, pending: bool) {
@@ -572,9 +618,6 @@ if #[cfg(feature = "user-paging")] {
     fn get_trap_frame_vaddr(id: TaskId) -> VirtAddr {
         (TRAP_FRAME_BASE - id.0 as usize * TRAP_FRAME_SIZE).into()
     }
-    fn get_ustack_vaddr(id: TaskId) -> VirtAddr {
-        (axmem::USTACK_START - id.0 as usize * axmem::USTACK_SIZE).into()
-    }
 }
 }
 
@@ -596,11 +639,12 @@ cfg_if::cfg_if! {
             task
         }
 
-        /// Inits a new task for the new program and run it
-        pub fn handle_exec<F>(post_fn: F) -> !
+        /// Inits a new task for the new program with `args` and `envs`
+        /// (strings each ended by `\0`) and run it
+        pub fn handle_exec<F>(args: alloc::vec::Vec<u8>, envs: alloc::vec::Vec<u8>, post_fn: F) -> !
         where F: FnOnce(AxTaskRef) {
 
-            let task = TaskInner::new_exec();
+            let task = TaskInner::new_exec(args, envs);
             post_fn(task.clone());
 
             crate::run_queue::run_exec(task)
@@ -72,6 +72,18 @@ impl WaitQueue {
         self.cancel_events(crate::current());
     }
 
+    /// Like [`wait`](Self::wait), but also returns once the current task is
+    /// [killed](crate::kill), returns `false` then.
+    pub fn wait_interruptible(&self) -> bool {
+        let mut rq = RUN_QUEUE.lock();
+        if !crate::current().is_killed() {
+            self.block_interruptible(&mut rq);
+        }
+        drop(rq);
+        self.cancel_events(crate::current());
+        !crate::current().is_killed()
+    }
+
     /// Blocks the current task and put it into the wait queue, until the given
     /// `condition` becomes true.
     ///
@@ -94,6 +106,39 @@ impl WaitQueue {
         self.cancel_events(crate::current());
     }
 
+    /// Like [`wait_until`](Self::wait_until), but also returns once the current
+    /// task is [killed](crate::kill).
+    ///
+    /// Returns whether the condition is true, `false` means the task is killed.
+    pub fn wait_until_interruptible<F>(&self, condition: F) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let met = loop {
+            let mut rq = RUN_QUEUE.lock();
+            if condition() {
+                break true;
+            }
+            if crate::current().is_killed() {
+                break false;
+            }
+            self.block_interruptible(&mut rq);
+        };
+        self.cancel_events(crate::current());
+        met
+    }
+
+    /// Blocks the current task in the wait queue, [`kill`](crate::kill) wakes it up as well.
+    fn block_interruptible(&self, rq: &mut AxRunQueue) {
+        let curr = crate::current();
+        curr.set_interruptible(true);
+        rq.block_current(|task| {
+            task.set_in_wait_queue(true);
+            self.queue.lock().push_back(task);
+        });
+        curr.set_interruptible(false);
+    }
+
     /// Blocks the current task and put it into the wait queue, until other tasks
     /// notify it, or the given duration has elapsed.
     #[cfg(feature = "irq")]
@@ -197,13 +242,19 @@ impl WaitQueue {
         }
     }
 
+    /// Tasks woken up otherwise (killed or timed out) but not yet removed from
+    /// the queue are skipped, so that the notification is not lost on them.
     pub(crate) fn notify_one_locked(&self, resched: bool, rq: &mut AxRunQueue) -> bool {
-        if let Some(task) = self.queue.lock().pop_front() {
-            task.set_in_wait_queue(false);
-            rq.unblock_task(task, resched);
-            true
-        } else {
-            false
+        loop {
+            let Some(task) = self.queue.lock().pop_front() else {
+                return false;
+            };
+            // The skipped ones still see they were in the queue (e.g. for timeouts).
+            if task.is_blocked() {
+                task.set_in_wait_queue(false);
+                rq.unblock_task(task, resched);
+                return true;
+            }
         }
     }
 
@@ -28,6 +28,7 @@ endif
 
 features-kern-$(FS) += user-fs
 features-kern-$(NET) += user-net
+features-kern-$(GRAPHIC) += user-display
 # features-$(GRAPHIC) += libax/display
 
 # ifeq ($(BUS),pci)
@@ -2,7 +2,7 @@ use crate::task;
 
 #[no_mangle]
 #[link_section = ".text.start"]
-extern "C" fn _start() {
+extern "C" fn _start(sp: usize) {
     extern "Rust" {
         fn main();
     }
@@ -10,6 +10,7 @@ extern "C" fn _start() {
     super::logging::set_max_level(option_env!("LOG").unwrap_or(""));
     super::allocate::init();
     super::io::init();
+    super::io::env::init_args(sp);
     unsafe {
         main();
     }
@@ -0,0 +1,59 @@
+//! Waiting for readiness of several files at once, see the `event:` scheme.
+
+extern crate alloc;
+use core::time::Duration;
+
+use alloc::format;
+use axerrno::AxResult;
+pub use scheme::{Event, EventFlags};
+use syscall_number::io::OpenFlags;
+
+use super::{File, Read};
+
+/// A `poll`-like queue of readiness events
+pub struct EventQueue {
+    file: File,
+}
+
+impl EventQueue {
+    /// Creates an empty queue
+    pub fn new() -> AxResult<Self> {
+        File::open_with("event:/", OpenFlags::READ | OpenFlags::WRITE).map(|file| Self { file })
+    }
+
+    /// Creates an empty queue whose waits return no events after `timeout`
+    pub fn with_timeout(timeout: Duration) -> AxResult<Self> {
+        let path = format!("event:/{}", timeout.as_millis());
+        File::open_with(&path, OpenFlags::READ | OpenFlags::WRITE).map(|file| Self { file })
+    }
+
+    /// Watches `flags` of `file`, `data` is returned along with its events.
+    /// Events that are already ready are reported at once.
+    pub fn subscribe(&mut self, file: &File, flags: EventFlags, data: usize) -> AxResult<()> {
+        self.file.write_data(&Event {
+            id: file.fd,
+            flags,
+            data,
+        })?;
+        Ok(())
+    }
+
+    /// Stops watching `file`
+    pub fn unsubscribe(&mut self, file: &File) -> AxResult<()> {
+        self.subscribe(file, EventFlags::empty(), 0)
+    }
+
+    /// Blocks until some events are ready, returns the number of events stored in `events`,
+    /// 0 if the timeout of the queue has elapsed.
+    pub fn wait(&mut self, events: &mut [Event]) -> AxResult<usize> {
+        let buf = unsafe {
+            core::slice::from_raw_parts_mut(
+                events.as_mut_ptr() as *mut u8,
+                core::mem::size_of_val(events),
+            )
+        };
+        self.file
+            .read(buf)
+            .map(|len| len / core::mem::size_of::<Event>())
+    }
+}
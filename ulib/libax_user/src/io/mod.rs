@@ -8,14 +8,18 @@ pub use axio::{BufRead, BufReader, Error, Read, Result, Seek, SeekFrom, Write};
 
 #[macro_use]
 pub mod logging;
-use axerrno::{from_ret_code, AxError, AxResult};
+pub mod event;
+use axerrno::{ax_err, from_ret_code, AxError, AxResult};
 use log::info;
-use scheme::Stat;
-use syscall_number::io::{OpenFlags, SEEK_CUR, SEEK_END, SEEK_SET};
+use scheme::{Map, MapFlags, Stat, StatVfs};
+use syscall_number::io::{
+    OpenFlags, FD_CLOEXEC, F_GETFD, F_GETFL, F_SETFD, F_SETFL, SEEK_CUR, SEEK_END, SEEK_SET,
+};
 
 use crate::syscall::io::{
-    close, dup, fstat, fsync, lseek, open, read, remove_dir as remove_dir_inner,
-    remove_file as remove_file_inner, write,
+    close, dup, dup2, fcntl, fmap, fpath, frename, fstat, fstatvfs, fsync, funmap as funmap_inner,
+    lseek, open, pipe2, read, remove_dir as remove_dir_inner, remove_file as remove_file_inner,
+    write,
 };
 use crate::Mutex;
 extern crate alloc;
@@ -93,12 +97,91 @@ impl File {
         let fd = from_ret_code(dup(self.fd, buf))?;
         Ok(File { fd })
     }
+    /// make file descriptor `new_fd` refer to (a duplicate of) this file,
+    /// closing the file it referred to, e.g. to redirect stdout (fd 1) before `exec`.
+    ///
+    /// `new_fd` is not owned by the returned value.
+    pub fn dup2(&mut self, new_fd: usize, buf: &str) -> AxResult<usize> {
+        from_ret_code(dup2(self.fd, new_fd, buf))
+    }
+    /// whether the file is closed on `exec`
+    pub fn cloexec(&self) -> AxResult<bool> {
+        from_ret_code(fcntl(self.fd, F_GETFD, 0)).map(|flags| flags & FD_CLOEXEC != 0)
+    }
+    /// set whether the file is closed on `exec`, files are kept by default
+    pub fn set_cloexec(&mut self, cloexec: bool) -> AxResult<()> {
+        let flags = if cloexec { FD_CLOEXEC } else { 0 };
+        from_ret_code(fcntl(self.fd, F_SETFD, flags))?;
+        Ok(())
+    }
+    /// get the status flags (access mode, `APPEND` and `NONBLOCK`),
+    /// which are shared with duplicates of the file
+    pub fn status_flags(&self) -> AxResult<OpenFlags> {
+        from_ret_code(fcntl(self.fd, F_GETFL, 0)).map(OpenFlags::from_bits_truncate)
+    }
+    /// set the `APPEND` and `NONBLOCK` status flags, others in `flags` are ignored
+    pub fn set_status_flags(&mut self, flags: OpenFlags) -> AxResult<()> {
+        from_ret_code(fcntl(self.fd, F_SETFL, flags.bits()))?;
+        Ok(())
+    }
     /// get the metadata of the file
     pub fn stat(&mut self) -> AxResult<Stat> {
         let mut ret: Stat = Stat::new_file(0, 0);
         from_ret_code(fstat(self.fd, &mut ret))?;
         Ok(ret)
     }
+    /// get the statistics of the file system of the file (e.g. free frames of `memory:`)
+    pub fn statvfs(&mut self) -> AxResult<StatVfs> {
+        let mut ret = StatVfs::default();
+        from_ret_code(fstatvfs(self.fd, &mut ret))?;
+        Ok(ret)
+    }
+    /// map `size` bytes of the file starting from `offset` into memory,
+    /// returns the address of the mapping, which should be released by [funmap].
+    pub fn fmap(&mut self, offset: usize, size: usize, flags: MapFlags) -> AxResult<usize> {
+        let map = Map {
+            offset,
+            size,
+            flags,
+            address: 0,
+        };
+        from_ret_code(fmap(self.fd, &map))
+    }
+    /// get the full path (with the scheme) of the file
+    pub fn path(&mut self) -> AxResult<String> {
+        let mut buf = [0u8; 256];
+        let len = from_ret_code(fpath(self.fd, &mut buf))?;
+        let path = core::str::from_utf8(&buf[..len]).map_err(|_| AxError::InvalidData)?;
+        Ok(path.to_string())
+    }
+}
+
+/// rename a file, both paths must be in the same scheme
+pub fn rename(from: &str, to: &str) -> AxResult<()> {
+    let (from_scheme, _) = scheme_helper::get_scheme(from).ok_or(AxError::InvalidInput)?;
+    let (to_scheme, to_path) = scheme_helper::get_scheme(to).ok_or(AxError::InvalidInput)?;
+    if from_scheme != to_scheme {
+        return ax_err!(InvalidInput);
+    }
+    let to_path = if to_scheme == "file" {
+        absolute_path(to_path)?
+    } else {
+        to_path.to_string()
+    };
+    let file = File::open(from)?;
+    from_ret_code(frename(file.fd, &to_path))?;
+    Ok(())
+}
+
+/// returns the full path (with the scheme) of `path`, resolved by its scheme
+pub fn canonicalize(path: &str) -> AxResult<String> {
+    File::open(path)?.path()
+}
+
+/// Unmaps memory returned by [File::fmap]
+pub fn funmap(addr: usize, len: usize) -> AxResult<()> {
+    from_ret_code(funmap_inner(addr, len))?;
+    Ok(())
 }
 
 impl Drop for File {
@@ -107,6 +190,16 @@ impl Drop for File {
     }
 }
 
+/// Creates a pipe, returns its read end and write end.
+///
+/// Reading gets EOF after all write ends are closed,
+/// writing fails with `BrokenPipe` after all read ends are closed.
+pub fn pipe() -> AxResult<(File, File)> {
+    let mut fds = [0; 2];
+    from_ret_code(pipe2(&mut fds, OpenFlags::empty()))?;
+    Ok((File { fd: fds[0] }, File { fd: fds[1] }))
+}
+
 /// Return the `stdin` `File` wrapper
 /// NOTE: fd 0 (stdin) will not be closed after drop
 pub fn stdin() -> File {
@@ -117,6 +210,14 @@ pub fn stdin() -> File {
 pub fn stdout() -> File {
     File::open("stdout:/").unwrap()
 }
+/// Duplicate fd 0, which follows redirections (e.g. by the shell) unlike [`stdin`]
+pub fn dup_stdin() -> AxResult<File> {
+    from_ret_code(dup(0, "")).map(|fd| File { fd })
+}
+/// Duplicate fd 1, which follows redirections (e.g. by the shell) unlike [`stdout`]
+pub fn dup_stdout() -> AxResult<File> {
+    from_ret_code(dup(1, "")).map(|fd| File { fd })
+}
 
 /// remove a directory, recursively delete is not supported
 pub fn remove_dir(path: &str) -> AxResult<()> {
@@ -167,9 +268,55 @@ pub mod env {
     use axerrno::{ax_err, AxError, AxResult};
     extern crate alloc;
     use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
 
     use super::{scheme_helper, CURRENT_DIR_PATH};
 
+    /// Stack pointer at the entry of the program, pointing to `argc`,
+    /// followed by `argv` and `envp`, each ended by `NULL`
+    static INITIAL_SP: AtomicUsize = AtomicUsize::new(0);
+
+    pub(crate) fn init_args(sp: usize) {
+        INITIAL_SP.store(sp, Ordering::Relaxed);
+    }
+
+    /// Reads the strings of a `NULL`-ended array of C strings
+    unsafe fn c_strings(mut array: *const *const u8) -> Vec<String> {
+        let mut strings = Vec::new();
+        while !(*array).is_null() {
+            let ptr = *array;
+            let len = (0..).take_while(|i| *ptr.add(*i) != 0).count();
+            let bytes = core::slice::from_raw_parts(ptr, len);
+            strings.push(String::from_utf8_lossy(bytes).into_owned());
+            array = array.add(1);
+        }
+        strings
+    }
+
+    /// Returns the arguments the program was started with, usually beginning with its path.
+    pub fn args() -> impl Iterator<Item = String> {
+        let sp = INITIAL_SP.load(Ordering::Relaxed) as *const usize;
+        if sp.is_null() {
+            return Vec::new().into_iter();
+        }
+        unsafe { c_strings(sp.add(1) as *const *const u8) }.into_iter()
+    }
+
+    /// Returns the environment variables the program was started with, as `(key, value)` pairs.
+    pub fn vars() -> impl Iterator<Item = (String, String)> {
+        let sp = INITIAL_SP.load(Ordering::Relaxed) as *const usize;
+        let vars = if sp.is_null() {
+            Vec::new()
+        } else {
+            unsafe { c_strings(sp.add(*sp + 2) as *const *const u8) }
+        };
+        vars.into_iter().map(|var| match var.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (var, String::new()),
+        })
+    }
+
     pub(crate) fn canonicalize(path: &str) -> AxResult<String> {
         Ok(axfs_vfs::path::canonicalize(path))
     }
@@ -1,6 +1,9 @@
 //! User library of the microkernel version of ArceOS.
 //!
 #![cfg_attr(all(not(test), not(doc), target_os = "none"), no_std)]
+// `thread_local!` expands to `#[thread_local]` statics in user crates.
+#![feature(allow_internal_unstable)]
+#![allow(unknown_lints, internal_features)]
 
 mod syscall;
 pub use syscall::*;
@@ -18,6 +21,7 @@ mod entry;
 pub mod rand;
 mod sync;
 pub use sync::{Mutex, MutexGuard};
+pub mod tls;
 
 #[cfg(all(target_os = "none", not(test)))]
 use core::panic::PanicInfo;
@@ -29,10 +33,7 @@ fn panic(info: &PanicInfo) -> ! {
     task::exit(1);
 }
 
-/// `Scheme` re-export
-pub mod scheme {
-    pub use scheme::*;
-}
+pub mod scheme;
 /// Error number re-export
 pub mod axerrno {
     pub use axerrno::*;
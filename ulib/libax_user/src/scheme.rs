@@ -0,0 +1,130 @@
+//! `Scheme` re-export, and a runtime for scheme servers
+extern crate alloc;
+use alloc::{collections::VecDeque, format, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+pub use scheme::*;
+
+use crate::axerrno::AxResult;
+use crate::io::File;
+use crate::syscall::sync::{futex, FUTEX_WAIT, FUTEX_WAKE};
+use crate::Mutex;
+
+/// Number of packets read from the kernel at once
+const BATCH_SIZE: usize = 16;
+
+/// Serves a [`SchemeBlock`] with a pool of worker threads.
+///
+/// One thread reads batches of requests and queues them, the workers handle and answer them
+/// out of order. Requests left pending by the scheme are retried whenever another request
+/// is answered, or when [`BlockServer::wake`] is called.
+pub struct BlockServer<S> {
+    scheme: S,
+    queue: Mutex<VecDeque<Packet>>,
+    pending: Mutex<Vec<Packet>>,
+    /// changed whenever requests are queued, idle workers wait on it
+    seq: AtomicU32,
+    /// number of answered requests
+    answered: AtomicU32,
+    closed: AtomicBool,
+}
+
+impl<S: SchemeBlock + Send + Sync + 'static> BlockServer<S> {
+    /// Creates a server of `scheme`
+    pub fn new(scheme: S) -> Arc<Self> {
+        Arc::new(BlockServer {
+            scheme,
+            queue: Mutex::new(VecDeque::new()),
+            pending: Mutex::new(Vec::new()),
+            seq: AtomicU32::new(0),
+            answered: AtomicU32::new(0),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Gets the scheme being served
+    pub fn scheme(&self) -> &S {
+        &self.scheme
+    }
+
+    /// Registers the scheme as `name` and serves it with `workers` threads.
+    ///
+    /// Returns when the scheme is closed or reading requests fails.
+    pub fn run(self: &Arc<Self>, name: &str, workers: usize) -> AxResult<()> {
+        let mut channel = File::create(&format!(":/{}", name))?;
+        for _ in 0..workers.max(1) {
+            let server = self.clone();
+            let channel = channel.dup("")?;
+            crate::task::spawn(move || server.work(channel));
+        }
+        let ret = self.read_loop(&mut channel);
+        self.closed.store(true, Ordering::Release);
+        self.notify();
+        ret
+    }
+
+    /// Retries all pending requests
+    pub fn wake(&self) {
+        let pending = core::mem::take(&mut *self.pending.lock());
+        if !pending.is_empty() {
+            self.queue.lock().extend(pending);
+            self.notify();
+        }
+    }
+
+    fn read_loop(&self, channel: &mut File) -> AxResult<()> {
+        let mut packets = [Packet::default(); BATCH_SIZE];
+        loop {
+            let count = channel.read_data(&mut packets)? / core::mem::size_of::<Packet>();
+            if count == 0 {
+                return Ok(());
+            }
+            self.queue.lock().extend(&packets[..count]);
+            self.notify();
+        }
+    }
+
+    fn work(&self, mut channel: File) {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            let packet = self.queue.lock().pop_front();
+            let Some(mut packet) = packet else {
+                if self.closed.load(Ordering::Acquire) {
+                    return;
+                }
+                futex(self.seq.as_ptr(), FUTEX_WAIT, seq, 0, core::ptr::null(), 0);
+                continue;
+            };
+            let answered = self.answered.load(Ordering::Acquire);
+            match self.scheme.handle(&packet) {
+                Some(ret) => {
+                    packet.a = ret;
+                    if channel.write_data(&packet).is_err() {
+                        return;
+                    }
+                    self.answered.fetch_add(1, Ordering::AcqRel);
+                    self.wake();
+                }
+                None => {
+                    self.pending.lock().push(packet);
+                    // Another request may have been answered in the meantime.
+                    if self.answered.load(Ordering::Acquire) != answered {
+                        self.wake();
+                    }
+                }
+            }
+        }
+    }
+
+    fn notify(&self) {
+        self.seq.fetch_add(1, Ordering::AcqRel);
+        futex(
+            self.seq.as_ptr(),
+            FUTEX_WAKE,
+            u32::MAX,
+            0,
+            core::ptr::null(),
+            0,
+        );
+    }
+}
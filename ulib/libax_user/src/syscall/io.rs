@@ -1,6 +1,7 @@
-use scheme::Stat;
+use scheme::{Map, Stat, StatVfs};
 use syscall_number::{
-    io::OpenFlags, SYS_CLOSE, SYS_DUP, SYS_FSTAT, SYS_FSYNC, SYS_LSEEK, SYS_OPEN, SYS_READ,
+    io::OpenFlags, SYS_CLOSE, SYS_DUP, SYS_DUP2, SYS_FCNTL, SYS_FMAP, SYS_FPATH, SYS_FRENAME,
+    SYS_FSTAT, SYS_FSTATVFS, SYS_FSYNC, SYS_FUNMAP, SYS_LSEEK, SYS_OPEN, SYS_PIPE2, SYS_READ,
     SYS_RMDIR, SYS_UNLINK,
 };
 
@@ -34,6 +35,23 @@ pub(crate) fn close(fd: usize) -> isize {
 pub(crate) fn dup(fd: usize, buf: &str) -> isize {
     crate::syscall(SYS_DUP, [fd, buf.as_ptr() as usize, buf.len(), 0, 0, 0])
 }
+pub(crate) fn dup2(fd: usize, new_fd: usize, buf: &str) -> isize {
+    crate::syscall(
+        SYS_DUP2,
+        [fd, new_fd, buf.as_ptr() as usize, buf.len(), 0, 0],
+    )
+}
+
+pub(crate) fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    crate::syscall(SYS_FCNTL, [fd, cmd, arg, 0, 0, 0])
+}
+
+pub(crate) fn pipe2(fds: &mut [usize; 2], flags: OpenFlags) -> isize {
+    crate::syscall(
+        SYS_PIPE2,
+        [fds.as_mut_ptr() as usize, flags.bits(), 0, 0, 0, 0],
+    )
+}
 
 pub(crate) fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
     crate::syscall(SYS_LSEEK, [fd, offset as usize, whence, 0, 0, 0])
@@ -53,6 +71,39 @@ pub(crate) fn fstat(fd: usize, stat: *mut Stat) -> isize {
         [fd, stat as usize, core::mem::size_of::<Stat>(), 0, 0, 0],
     )
 }
+pub(crate) fn fstatvfs(fd: usize, stat: *mut StatVfs) -> isize {
+    crate::syscall(
+        SYS_FSTATVFS,
+        [fd, stat as usize, core::mem::size_of::<StatVfs>(), 0, 0, 0],
+    )
+}
 pub(crate) fn fsync(fd: usize) -> isize {
     crate::syscall(SYS_FSYNC, [fd, 0, 0, 0, 0, 0])
 }
+
+pub(crate) fn fmap(fd: usize, map: &Map) -> isize {
+    crate::syscall(
+        SYS_FMAP,
+        [
+            fd,
+            map as *const Map as usize,
+            core::mem::size_of::<Map>(),
+            0,
+            0,
+            0,
+        ],
+    )
+}
+pub(crate) fn funmap(addr: usize, len: usize) -> isize {
+    crate::syscall(SYS_FUNMAP, [addr, len, 0, 0, 0, 0])
+}
+
+pub(crate) fn fpath(fd: usize, buf: &mut [u8]) -> isize {
+    crate::syscall(SYS_FPATH, [fd, buf.as_ptr() as usize, buf.len(), 0, 0, 0])
+}
+pub(crate) fn frename(fd: usize, path: &str) -> isize {
+    crate::syscall(
+        SYS_FRENAME,
+        [fd, path.as_ptr() as usize, path.len(), 0, 0, 0],
+    )
+}
@@ -0,0 +1,30 @@
+//! syscalls about memory management
+use axerrno::{from_ret_code, AxResult};
+
+use super::sys_number::{SYS_MMAP, SYS_MPROTECT, SYS_MUNMAP};
+
+pub use super::sys_number::mmap::MapFlags;
+
+/// map `len` bytes of zero-filled memory with the `PROT_*` permissions of `flags`,
+/// returns its address.
+///
+/// `addr` is a hint (`0` for none), or the exact address with `MAP_FIXED`,
+/// in which case the mappings there are replaced.
+/// The memory is private, `MAP_SHARED` is not supported.
+pub fn mmap(addr: usize, len: usize, flags: MapFlags) -> AxResult<usize> {
+    from_ret_code(crate::syscall(SYS_MMAP, [addr, len, flags.bits(), 0, 0, 0]))
+}
+
+/// unmap the pages in `[addr, addr + len)`, which may be parts of mappings
+pub fn munmap(addr: usize, len: usize) -> AxResult<()> {
+    from_ret_code(crate::syscall(SYS_MUNMAP, [addr, len, 0, 0, 0, 0])).map(|_| ())
+}
+
+/// change the permissions of the pages in `[addr, addr + len)` to the `PROT_*` ones of `flags`
+pub fn mprotect(addr: usize, len: usize, flags: MapFlags) -> AxResult<()> {
+    from_ret_code(crate::syscall(
+        SYS_MPROTECT,
+        [addr, len, flags.bits(), 0, 0, 0],
+    ))
+    .map(|_| ())
+}
@@ -1,6 +1,8 @@
 //! syscall wrappers of the user library.
 pub mod io;
+pub mod mem;
 pub mod process;
+pub mod signal;
 pub mod sync;
 pub mod task;
 
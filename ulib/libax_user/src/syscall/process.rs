@@ -1,30 +1,128 @@
 //! syscalls about processes
-use axio::Read;
-use syscall_number::{SYS_EXEC, SYS_FORK, SYS_WAIT};
+use axerrno::{from_ret_code, AxResult};
+use syscall_number::{
+    SYS_EXECVE, SYS_FORK, SYS_GETEGID, SYS_GETEUID, SYS_GETGID, SYS_GETPID, SYS_GETUID, SYS_MKNS,
+    SYS_SETGID, SYS_SETNS, SYS_SETUID, SYS_WAIT,
+};
 
-use crate::{io::File, syscall};
+use crate::{io::env, syscall};
 extern crate alloc;
-use alloc::vec::Vec;
+use alloc::{format, string::String, vec::Vec};
 
 /// `fork` another process with the same memory contents and file tables.
 pub fn fork() -> isize {
     syscall(SYS_FORK, [0, 0, 0, 0, 0, 0])
 }
 
-/// `wait` for process to stop
+pub use syscall_number::wait::WNOHANG;
+
+/// `wait` for process `pid` (`0` for any child) to stop,
+/// returns its pid and stores its exit code in `ret`, see [`waitpid`].
 pub fn wait(pid: usize, ret: &mut i32) -> usize {
     syscall(SYS_WAIT, [pid, ret as *mut i32 as usize, 0, 0, 0, 0]) as usize
 }
 
-/// run the program
+/// `wait` for child process `pid` (`-1` for any child) to stop,
+/// returns its pid and stores its exit code in `ret`.
+///
+/// With [`WNOHANG`] in `options`, returns `Ok(0)` if no such child has stopped yet,
+/// otherwise blocks until one stops.
+/// Fails with `NoChildProcess` if there is no such child.
+pub fn waitpid(pid: isize, ret: &mut i32, options: usize) -> AxResult<usize> {
+    from_ret_code(syscall(
+        SYS_WAIT,
+        [pid as usize, ret as *mut i32 as usize, options, 0, 0, 0],
+    ))
+}
+
+/// run the program, with its path as the only argument and the environment of current process.
+/// only returns on error
 pub fn exec(path: &str) -> isize {
-    if let Ok(data) = File::open(path).and_then(|mut file| {
-        let mut data: Vec<u8> = Vec::new();
-        file.read_to_end(&mut data)?;
-        Ok(data)
-    }) {
-        syscall(SYS_EXEC, [data.as_ptr() as usize, data.len(), 0, 0, 0, 0])
-    } else {
-        -1
-    }
+    let envs: Vec<String> = env::vars()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    let envs: Vec<&str> = envs.iter().map(String::as_str).collect();
+    execve(path, &[path], &envs)
+}
+
+/// run the program with arguments `args` (usually beginning with its path)
+/// and environment variables `envs` (`KEY=value`).
+/// The program is read by the kernel, a script starting with `#!interpreter [arg]`
+/// runs the interpreter with arguments `interpreter [arg] path args[1..]`.
+/// only returns on error
+pub fn execve(path: &str, args: &[&str], envs: &[&str]) -> isize {
+    // Strings are passed each ended by `\0`.
+    let strings = |strings: &[&str]| -> Vec<u8> {
+        strings
+            .iter()
+            .flat_map(|string| string.bytes().chain([0]))
+            .collect()
+    };
+    let (args, envs) = (strings(args), strings(envs));
+    syscall(
+        SYS_EXECVE,
+        [
+            path.as_ptr() as usize,
+            path.len(),
+            args.as_ptr() as usize,
+            args.len(),
+            envs.as_ptr() as usize,
+            envs.len(),
+        ],
+    )
+}
+
+/// create a scheme namespace from the current one,
+/// which has only the basic schemes (`:`, `stdin:`, `stdout:`, `event:` and `pipe:`) and `names`.
+pub fn mkns(names: &[&str]) -> AxResult<usize> {
+    let names = names.join("\n");
+    from_ret_code(syscall(
+        SYS_MKNS,
+        [names.as_ptr() as usize, names.len(), 0, 0, 0, 0],
+    ))
+}
+
+/// switch into scheme namespace `ns`, which must be created from the current one.
+/// Child processes inherit the namespace.
+pub fn setns(ns: usize) -> AxResult<()> {
+    from_ret_code(syscall(SYS_SETNS, [ns, 0, 0, 0, 0, 0]))?;
+    Ok(())
+}
+
+/// get the id of current process
+pub fn getpid() -> usize {
+    syscall(SYS_GETPID, [0; 6]) as usize
+}
+
+/// get the real user id of current process
+pub fn getuid() -> u32 {
+    syscall(SYS_GETUID, [0; 6]) as u32
+}
+
+/// get the effective user id of current process, which is checked by schemes
+pub fn geteuid() -> u32 {
+    syscall(SYS_GETEUID, [0; 6]) as u32
+}
+
+/// get the real group id of current process
+pub fn getgid() -> u32 {
+    syscall(SYS_GETGID, [0; 6]) as u32
+}
+
+/// get the effective group id of current process, which is checked by schemes
+pub fn getegid() -> u32 {
+    syscall(SYS_GETEGID, [0; 6]) as u32
+}
+
+/// set the user id of current process.
+/// Only root can change to another user; others can only reset the effective uid to the real one.
+pub fn setuid(uid: u32) -> AxResult<()> {
+    from_ret_code(syscall(SYS_SETUID, [uid as usize, 0, 0, 0, 0, 0]))?;
+    Ok(())
+}
+
+/// set the group id of current process, with the same rules as [`setuid`].
+pub fn setgid(gid: u32) -> AxResult<()> {
+    from_ret_code(syscall(SYS_SETGID, [gid as usize, 0, 0, 0, 0, 0]))?;
+    Ok(())
 }
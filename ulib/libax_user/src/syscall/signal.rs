@@ -0,0 +1,120 @@
+//! POSIX-style signals
+//!
+//! A handler is an `extern "C" fn(signal: usize)`, which runs when the process
+//! returns from a syscall or is interrupted, and returns to the interrupted code.
+//! Signals are blocked while their own handler runs.
+use axerrno::{from_ret_code, AxResult};
+pub use syscall_number::signal::*;
+use syscall_number::{SYS_KILL, SYS_SIGACTION, SYS_SIGPROCMASK, SYS_SIGRETURN};
+
+use crate::syscall;
+
+/// A signal handler
+pub type SigHandler = extern "C" fn(usize);
+
+// Handlers return here, with the stack pointer at the context saved by the kernel,
+// so this must not touch the stack.
+#[cfg(any(target_arch = "riscv64", target_arch = "riscv32"))]
+core::arch::global_asm!(
+    ".section .text",
+    ".globl __sigreturn_trampoline",
+    "__sigreturn_trampoline:",
+    "li a7, 73",
+    "ecall",
+);
+const _: () = assert!(SYS_SIGRETURN == 73);
+
+extern "C" {
+    fn __sigreturn_trampoline();
+}
+
+/// send signal `sig` to process `pid`, `0` only checks whether it can be sent.
+///
+/// Only root can send signals to processes of other users.
+pub fn kill(pid: usize, sig: usize) -> AxResult<()> {
+    from_ret_code(syscall(SYS_KILL, [pid, sig, 0, 0, 0, 0]))?;
+    Ok(())
+}
+
+/// get the action on signal `sig`, and set it to `action` if given.
+///
+/// `restorer` of a handler is filled in, the action of `SIGKILL` and `SIGSTOP` cannot be changed.
+pub fn sigaction(sig: usize, action: Option<&SigAction>) -> AxResult<SigAction> {
+    let action = action.map(|action| {
+        let mut action = *action;
+        if action.handler != SIG_DFL && action.handler != SIG_IGN {
+            action.restorer = __sigreturn_trampoline as usize;
+        }
+        action
+    });
+    let mut old = SigAction::default();
+    from_ret_code(syscall(
+        SYS_SIGACTION,
+        [
+            sig,
+            action
+                .as_ref()
+                .map_or(0, |action| action as *const SigAction as usize),
+            &mut old as *mut SigAction as usize,
+            0,
+            0,
+            0,
+        ],
+    ))?;
+    Ok(old)
+}
+
+/// call `handler` on signal `sig`
+pub fn signal(sig: usize, handler: SigHandler) -> AxResult<()> {
+    set_handler(sig, handler as usize)
+}
+
+/// ignore signal `sig`
+pub fn ignore(sig: usize) -> AxResult<()> {
+    set_handler(sig, SIG_IGN)
+}
+
+/// take the default action on signal `sig`, which terminates the process for most signals
+pub fn default(sig: usize) -> AxResult<()> {
+    set_handler(sig, SIG_DFL)
+}
+
+fn set_handler(sig: usize, handler: usize) -> AxResult<()> {
+    sigaction(
+        sig,
+        Some(&SigAction {
+            handler,
+            ..Default::default()
+        }),
+    )?;
+    Ok(())
+}
+
+/// get the blocked signals (bit `n` for signal `n`), and change them with `set` if given,
+/// `how` is one of `SIG_BLOCK`, `SIG_UNBLOCK` and `SIG_SETMASK`.
+pub fn sigprocmask(how: usize, set: Option<u64>) -> AxResult<u64> {
+    let mut old: u64 = 0;
+    from_ret_code(syscall(
+        SYS_SIGPROCMASK,
+        [
+            how,
+            set.as_ref().map_or(0, |set| set as *const u64 as usize),
+            &mut old as *mut u64 as usize,
+            0,
+            0,
+            0,
+        ],
+    ))?;
+    Ok(old)
+}
+
+/// the signal that terminated a process with exit `code` (as reported by `waitpid`),
+/// which is `128 + signal` as in shells
+pub fn exit_signal(code: i32) -> Option<usize> {
+    (code > 128 && code < 128 + NSIG as i32).then(|| (code - 128) as usize)
+}
+
+/// the mask of signal `sig` for [`sigprocmask`]
+pub const fn sigmask(sig: usize) -> u64 {
+    1 << sig
+}
@@ -1,18 +1,25 @@
 //! syscalls about task(threads) management
 extern crate alloc;
 use alloc::boxed::Box;
+use axerrno::{from_ret_code, AxResult};
 
 use super::sys_number::{SYS_SBRK, SYS_SLEEP, SYS_SPAWN, SYS_YIELD};
 
-use super::sys_number::SYS_EXIT;
+use super::sys_number::{SYS_EXIT, SYS_EXIT_GROUP};
 
-/// exit a thread, or exit the process if it is the main thread.
+/// exit the process, its other threads are terminated.
 pub fn exit(exitcode: usize) -> ! {
-    crate::syscall(SYS_EXIT, [exitcode, 0, 0, 0, 0, 0]);
+    crate::syscall(SYS_EXIT_GROUP, [exitcode, 0, 0, 0, 0, 0]);
     unreachable!("program already terminated")
 }
 
-/// create a thread
+/// exit the calling thread only, the process exits with it if it is the last thread.
+pub fn thread_exit(exitcode: usize) -> ! {
+    crate::syscall(SYS_EXIT, [exitcode, 0, 0, 0, 0, 0]);
+    unreachable!("thread already terminated")
+}
+
+/// create a thread, which should end with [`thread_exit`]
 pub fn spawn_fn(f: fn()) {
     crate::syscall(SYS_SPAWN, [f as usize, 0, 0, 0, 0, 0]);
 }
@@ -35,28 +42,43 @@ type BoxedFn = Box<dyn FnOnce() + Sync + 'static>;
 fn child_task_start(arg: usize) {
     let run_fn = unsafe { alloc::boxed::Box::from_raw(arg as *mut BoxedFn) };
     run_fn();
-    exit(0);
+    thread_exit(0);
 }
 
 /// create a thread, param can be a closure
 // reference: https://doc.rust-lang.org/src/std/sys/unix/thread.rs.html
 pub fn spawn<F>(f: F)
+where
+    F: FnOnce() + Sync + 'static,
+{
+    let _ = spawn_with_stack(f, 0);
+}
+
+/// create a thread with a stack of `stack_size` bytes (`0` for the default).
+///
+/// The stack grows on demand, a thread overflowing it is killed by `SIGSEGV`.
+/// The TLS block of the thread is put at its top, so smaller stacks are refused.
+pub fn spawn_with_stack<F>(f: F, stack_size: usize) -> AxResult<()>
 where
     F: FnOnce() + Sync + 'static,
 {
     let run_fn: BoxedFn = Box::new(f);
     let run_fn_raw = Box::into_raw(Box::new(run_fn));
-    crate::syscall(
+    let ret = crate::syscall(
         SYS_SPAWN,
         [
             child_task_start as usize,
             run_fn_raw as *const u8 as usize,
-            0,
+            stack_size,
             0,
             0,
             0,
         ],
     );
+    if ret < 0 {
+        drop(unsafe { Box::from_raw(run_fn_raw) });
+    }
+    from_ret_code(ret).map(|_| ())
 }
 
 /// modify heap space
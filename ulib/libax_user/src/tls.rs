@@ -0,0 +1,94 @@
+//! Thread-local storage, similar to
+//! [`std::thread::LocalKey`](https://doc.rust-lang.org/std/thread/struct.LocalKey.html).
+//!
+//! Each thread has its own copy of the values declared with [`thread_local!`](crate::thread_local),
+//! in a TLS block set up by the kernel at the top of its stack.
+//! Values are not dropped when a thread exits.
+
+/// A key to a thread-local value, declared with [`thread_local!`](crate::thread_local)
+pub struct LocalKey<T: 'static> {
+    get: fn() -> *const T,
+}
+
+impl<T: 'static> LocalKey<T> {
+    /// # Safety
+    ///
+    /// `get` must return a pointer to a value of current thread, valid until it exits.
+    #[doc(hidden)]
+    pub const unsafe fn new(get: fn() -> *const T) -> Self {
+        LocalKey { get }
+    }
+
+    /// Calls `f` with a reference to the value of current thread,
+    /// initialized on the first access if it is not declared `const`.
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        // The value lives as long as the thread, which outlives the call.
+        f(unsafe { &*(self.get)() })
+    }
+}
+
+/// Gets the value in `slot` of current thread, initialized with `init` first if it is empty.
+///
+/// # Safety
+///
+/// `slot` must be a thread-local value of current thread.
+#[doc(hidden)]
+pub unsafe fn lazy_get<T>(slot: *mut Option<T>, init: fn() -> T) -> *const T {
+    if (*slot).is_none() {
+        let value = init();
+        // `init` may have accessed the value already.
+        if (*slot).is_none() {
+            *slot = Some(value);
+        }
+    }
+    (*slot).as_ref().unwrap()
+}
+
+/// Declares thread-local values, with the syntax of
+/// [`std::thread_local!`](https://doc.rust-lang.org/std/macro.thread_local.html).
+///
+/// A value initialized with `const { ... }` is put in the TLS block directly,
+/// others are initialized on the first access of each thread.
+#[macro_export]
+#[allow_internal_unstable(thread_local)]
+macro_rules! thread_local {
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = const { $init:expr }; $($rest:tt)*) => {
+        $crate::thread_local!($(#[$attr])* $vis static $name: $t = const { $init });
+        $crate::thread_local!($($rest)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = const { $init:expr }) => {
+        $(#[$attr])* $vis const $name: $crate::tls::LocalKey<$t> = {
+            fn __get() -> *const $t {
+                #[thread_local]
+                static VALUE: $t = $init;
+                ::core::ptr::addr_of!(VALUE)
+            }
+            unsafe { $crate::tls::LocalKey::new(__get) }
+        };
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr; $($rest:tt)*) => {
+        $crate::thread_local!($(#[$attr])* $vis static $name: $t = $init);
+        $crate::thread_local!($($rest)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr) => {
+        $(#[$attr])* $vis const $name: $crate::tls::LocalKey<$t> = {
+            fn __init() -> $t {
+                $init
+            }
+            fn __get() -> *const $t {
+                #[thread_local]
+                static mut VALUE: ::core::option::Option<$t> = ::core::option::Option::None;
+                unsafe { $crate::tls::lazy_get(::core::ptr::addr_of_mut!(VALUE), __init) }
+            }
+            unsafe { $crate::tls::LocalKey::new(__get) }
+        };
+    };
+}